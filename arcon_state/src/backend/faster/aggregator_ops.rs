@@ -22,35 +22,53 @@ impl AggregatorOps for Faster {
         &self,
         handle: &Handle<AggregatorState<A>, IK, N>,
     ) -> Result<<A as Aggregator>::Result> {
+        let accumulator = self
+            .aggregator_get_accumulator(handle)?
+            .unwrap_or_else(|| handle.extra_data.create_accumulator());
+        Ok(handle.extra_data.accumulator_into_result(accumulator))
+    }
+
+    fn aggregator_aggregate<A: Aggregator, IK: Metakey, N: Metakey>(
+        &mut self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+        value: <A as Aggregator>::Input,
+    ) -> Result<()> {
+        let key = handle.serialize_id_and_metakeys()?;
+        let mut serialized = Vec::with_capacity(1 + protobuf::size_hint(&value).unwrap_or(0));
+        serialized.push(VALUE_MARKER);
+        protobuf::serialize_into(&mut serialized, &value)?;
+
+        // See the make_aggregate_fn function in this module. Its result is set as the
+        // merging operator for this state.
+        self.aggregate(&key, serialized, handle.id)
+    }
+
+    fn aggregator_get_accumulator<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+    ) -> Result<Option<<A as Aggregator>::Accumulator>> {
         let key = handle.serialize_id_and_metakeys()?;
 
         if let Some(serialized) = self.get_agg(&key)? {
             assert_eq!(serialized[0], ACCUMULATOR_MARKER);
             let serialized = &serialized[1..];
 
-            let current_accumulator = protobuf::deserialize(serialized)?;
-            Ok(handle
-                .extra_data
-                .accumulator_into_result(current_accumulator))
+            Ok(Some(protobuf::deserialize(serialized)?))
         } else {
-            Ok(handle
-                .extra_data
-                .accumulator_into_result(handle.extra_data.create_accumulator()))
+            Ok(None)
         }
     }
 
-    fn aggregator_aggregate<A: Aggregator, IK: Metakey, N: Metakey>(
+    fn aggregator_merge<A: Aggregator, IK: Metakey, N: Metakey>(
         &mut self,
         handle: &Handle<AggregatorState<A>, IK, N>,
-        value: <A as Aggregator>::Input,
+        accumulator: <A as Aggregator>::Accumulator,
     ) -> Result<()> {
         let key = handle.serialize_id_and_metakeys()?;
-        let mut serialized = Vec::with_capacity(1 + protobuf::size_hint(&value).unwrap_or(0));
-        serialized.push(VALUE_MARKER);
-        protobuf::serialize_into(&mut serialized, &value)?;
+        let mut serialized = Vec::with_capacity(1 + protobuf::size_hint(&accumulator).unwrap_or(0));
+        serialized.push(ACCUMULATOR_MARKER);
+        protobuf::serialize_into(&mut serialized, &accumulator)?;
 
-        // See the make_aggregate_fn function in this module. Its result is set as the
-        // merging operator for this state.
         self.aggregate(&key, serialized, handle.id)
     }
 }
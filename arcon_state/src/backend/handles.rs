@@ -458,6 +458,16 @@ impl<B: Backend, A: Aggregator, IK: Metakey, N: Metakey>
     pub fn aggregate(&self, value: A::Input) -> Result<()> {
         self.backend.aggregator_aggregate(&self.inner, value)
     }
+
+    #[inline]
+    pub fn get_accumulator(&self) -> Result<Option<A::Accumulator>> {
+        self.backend.aggregator_get_accumulator(&self.inner)
+    }
+
+    #[inline]
+    pub fn merge(&self, accumulator: A::Accumulator) -> Result<()> {
+        self.backend.aggregator_merge(&self.inner, accumulator)
+    }
 }
 
 #[cfg(test)]
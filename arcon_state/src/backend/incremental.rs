@@ -0,0 +1,307 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! Checkpoints that share immutable files with earlier checkpoints
+//!
+//! An incremental checkpoint only holds the files that were added since the previous
+//! checkpoint of the same backend. Its [SharedFiles] manifest lists every shared file
+//! the checkpoint consists of together with the checkpoint directory that holds it.
+//!
+//! The checkpoints of a backend form a chain of sibling directories named
+//! `{prefix}_{epoch}`, e.g., `checkpoint_{state_id}_{epoch}`. A shared file is kept
+//! around as long as any checkpoint of the chain references it, which is why
+//! checkpoints should be removed through [remove_checkpoint].
+
+use crate::error::*;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// File name of the manifest inside a checkpoint directory
+pub const MANIFEST_FILE: &str = "SHARED_FILES";
+
+/// Manifest of the shared files of an incremental checkpoint
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SharedFiles {
+    /// file name -> name of the checkpoint directory that holds the file
+    files: BTreeMap<String, String>,
+}
+
+impl SharedFiles {
+    /// Reads the manifest of the checkpoint at `checkpoint_path`
+    ///
+    /// Returns `None` if the checkpoint is not incremental.
+    pub fn read(checkpoint_path: &Path) -> Result<Option<SharedFiles>> {
+        let content = match fs::read_to_string(checkpoint_path.join(MANIFEST_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut files = BTreeMap::new();
+        for line in content.lines().filter(|l| !l.is_empty()) {
+            let mut parts = line.splitn(2, ' ');
+            match (parts.next(), parts.next()) {
+                (Some(file), Some(owner)) => {
+                    files.insert(file.to_owned(), owner.to_owned());
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Malformed manifest entry {:?}", line),
+                    )
+                    .into())
+                }
+            }
+        }
+
+        Ok(Some(SharedFiles { files }))
+    }
+
+    /// Writes the manifest into the checkpoint at `checkpoint_path`
+    pub fn write(&self, checkpoint_path: &Path) -> Result<()> {
+        let mut content = String::new();
+        for (file, owner) in &self.files {
+            content.push_str(file);
+            content.push(' ');
+            content.push_str(owner);
+            content.push('\n');
+        }
+
+        // Written to a temporary file first so that a crash never leaves a partial manifest
+        let tmp = checkpoint_path.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, checkpoint_path.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+
+    /// Records that `file` is held by the checkpoint directory named `owner`
+    pub fn insert(&mut self, file: impl Into<String>, owner: impl Into<String>) {
+        self.files.insert(file.into(), owner.into());
+    }
+
+    /// Returns the name of the checkpoint directory that holds `file`
+    pub fn owner(&self, file: &str) -> Option<&str> {
+        self.files.get(file).map(String::as_str)
+    }
+
+    pub fn contains(&self, file: &str) -> bool {
+        self.files.contains_key(file)
+    }
+
+    /// Iterates over `(file, owner)` pairs
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.files.iter().map(|(f, o)| (f.as_str(), o.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+/// Returns the path of a shared `file` of the checkpoint at `checkpoint_path`
+///
+/// If `owner` no longer holds the file, e.g., because it was handed over when `owner`
+/// was removed, the other checkpoints of the chain are searched.
+pub fn resolve(checkpoint_path: &Path, file: &str, owner: &str) -> Result<PathBuf> {
+    let parent = parent_dir(checkpoint_path)?;
+    let path = parent.join(owner).join(file);
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    for sibling in chain(checkpoint_path)? {
+        let path = sibling.join(file);
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "Shared file {} of checkpoint {} is missing",
+            file,
+            checkpoint_path.display()
+        ),
+    )
+    .into())
+}
+
+/// Copies the checkpoint at `from` into `to` so that the copy does not depend on other checkpoints
+///
+/// The copy holds all of its shared files itself. Checkpoints are expected to be flat directories.
+pub fn copy_checkpoint(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && entry.file_name() != MANIFEST_FILE {
+            fs::copy(entry.path(), to.join(entry.file_name()))?;
+        }
+    }
+
+    if let Some(shared) = SharedFiles::read(from)? {
+        let name = dir_name(to)?;
+        let mut copied = SharedFiles::default();
+        for (file, owner) in shared.iter() {
+            let target = to.join(file);
+            if !target.exists() {
+                fs::copy(resolve(from, file, owner)?, &target)?;
+            }
+            copied.insert(file, name.clone());
+        }
+        copied.write(to)?;
+    }
+
+    Ok(())
+}
+
+/// Removes the checkpoint at `checkpoint_path`
+///
+/// Shared files that are still referenced by other checkpoints of the chain are
+/// handed over to one of them before the directory is removed.
+pub fn remove_checkpoint(checkpoint_path: &Path) -> Result<()> {
+    // Manifests of the other checkpoints in the chain that reference files held by this one
+    let mut referencing: BTreeMap<PathBuf, SharedFiles> = BTreeMap::new();
+    for sibling in chain(checkpoint_path)? {
+        if let Some(shared) = SharedFiles::read(&sibling)? {
+            let references = shared
+                .iter()
+                .any(|(file, _)| checkpoint_path.join(file).is_file());
+            if references {
+                referencing.insert(sibling, shared);
+            }
+        }
+    }
+
+    for entry in fs::read_dir(checkpoint_path)? {
+        let file = entry?.file_name().to_string_lossy().into_owned();
+        let holders: Vec<PathBuf> = referencing
+            .iter()
+            .filter(|(_, shared)| shared.contains(&file))
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        if let Some(new_owner) = holders.first() {
+            let new_owner_name = dir_name(new_owner)?;
+            fs::rename(checkpoint_path.join(&file), new_owner.join(&file))?;
+            for holder in &holders {
+                if let Some(shared) = referencing.get_mut(holder) {
+                    shared.insert(file.clone(), new_owner_name.clone());
+                }
+            }
+        }
+    }
+
+    for (path, shared) in &referencing {
+        shared.write(path)?;
+    }
+
+    fs::remove_dir_all(checkpoint_path)?;
+    Ok(())
+}
+
+/// Returns true if the checkpoint at `next` may share files with the one at `previous`
+pub(crate) fn shares_files(previous: &Path, next: &Path) -> bool {
+    previous != next && previous.parent() == next.parent()
+}
+
+/// Returns the other checkpoints that belong to the same chain as `checkpoint_path`
+fn chain(checkpoint_path: &Path) -> Result<Vec<PathBuf>> {
+    let name = dir_name(checkpoint_path)?;
+    let prefix = chain_prefix(&name);
+    let mut checkpoints = Vec::new();
+
+    for entry in fs::read_dir(parent_dir(checkpoint_path)?)? {
+        let entry = entry?;
+        let sibling = entry.file_name().to_string_lossy().into_owned();
+        if sibling != name && chain_prefix(&sibling) == prefix && entry.file_type()?.is_dir() {
+            checkpoints.push(entry.path());
+        }
+    }
+
+    Ok(checkpoints)
+}
+
+/// Checkpoint directory names end with `_{epoch}`
+fn chain_prefix(name: &str) -> &str {
+    name.rsplitn(2, '_').nth(1).unwrap_or(name)
+}
+
+fn parent_dir(path: &Path) -> Result<&Path> {
+    path.parent().with_context(|| InvalidPath {
+        path: path.to_path_buf(),
+    })
+}
+
+pub(crate) fn dir_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .with_context(|| InvalidPath {
+            path: path.to_path_buf(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn checkpoint(dir: &Path, name: &str, own: &[&str], shared: &[(&str, &str)]) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(&path).unwrap();
+        let mut files = SharedFiles::default();
+        for file in own {
+            fs::write(path.join(file), file).unwrap();
+            files.insert(*file, name);
+        }
+        for (file, owner) in shared {
+            files.insert(*file, *owner);
+        }
+        files.write(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn shared_files_test() {
+        let dir = TempDir::new().unwrap();
+        let c0 = checkpoint(dir.path(), "checkpoint_op_0", &["1.sst", "2.sst"], &[]);
+        let c1 = checkpoint(dir.path(), "checkpoint_op_1", &["3.sst"], &[(
+            "2.sst",
+            "checkpoint_op_0",
+        )]);
+        // Belongs to another chain and is never touched
+        let other = checkpoint(dir.path(), "checkpoint_other_0", &["2.sst"], &[]);
+
+        let shared = SharedFiles::read(&c1).unwrap().unwrap();
+        assert_eq!(shared.len(), 2);
+        assert_eq!(shared.owner("2.sst"), Some("checkpoint_op_0"));
+        assert!(SharedFiles::read(dir.path()).unwrap().is_none());
+
+        // Files referenced by epoch 1 are handed over before epoch 0 is removed
+        remove_checkpoint(&c0).unwrap();
+        assert!(!c0.exists());
+        assert!(c1.join("2.sst").is_file());
+        let shared = SharedFiles::read(&c1).unwrap().unwrap();
+        assert_eq!(shared.owner("2.sst"), Some("checkpoint_op_1"));
+        assert!(other.join("2.sst").is_file());
+
+        let copy = dir.path().join("copy").join("checkpoint_op_1");
+        copy_checkpoint(&c1, &copy).unwrap();
+        let copied = SharedFiles::read(&copy).unwrap().unwrap();
+        assert!(copied
+            .iter()
+            .all(|(f, o)| o == "checkpoint_op_1" && copy.join(f).is_file()));
+
+        remove_checkpoint(&c1).unwrap();
+        assert!(!c1.exists());
+        assert!(other.join("2.sst").is_file());
+    }
+}
@@ -30,6 +30,9 @@ use std::{
     path::{Path, PathBuf},
 };
 
+/// Writes the files of a checkpoint that was taken using [Backend::snapshot]
+pub type Materialize = Box<dyn FnOnce() -> Result<()> + Send>;
+
 #[derive(Debug, Default)]
 pub struct Config {
     pub live_state_base_path: PathBuf,
@@ -77,19 +80,16 @@ pub trait Backend:
                 }
             );
 
+            // ids may contain underscores, so the epoch is everything after the last one
             let dir_name = &dir_name[CHECKPOINT_PREFIX.len()..];
-            let mut dir_name_parts = dir_name.split('_');
-            let id = dir_name_parts.next().with_context(invalid_path)?;
+            let mut dir_name_parts = dir_name.rsplitn(2, '_');
             let epoch: u64 = dir_name_parts
                 .next()
                 .with_context(invalid_path)?
                 .parse()
                 .ok()
                 .with_context(invalid_path)?;
-
-            ensure!(dir_name_parts.next().is_none(), InvalidPath {
-                path: directory.path(),
-            });
+            let id = dir_name_parts.next().with_context(invalid_path)?;
 
             let checkpoints_for_id = checkpoints.get_mut(id).with_context(|| UnknownNode {
                 unknown_node: id.to_string(),
@@ -138,6 +138,17 @@ pub trait Backend:
 
     fn checkpoint(&self, checkpoint_path: &Path) -> Result<()>;
 
+    /// Takes a consistent snapshot of the backend for a checkpoint at `checkpoint_path`
+    ///
+    /// Only the part that has to happen before the backend is modified again runs
+    /// synchronously, while the returned [Materialize] writes the checkpoint and may
+    /// run on another thread. Snapshots of a backend must be materialized in order.
+    /// By default, the whole checkpoint is taken synchronously.
+    fn snapshot(&self, checkpoint_path: &Path) -> Result<Materialize> {
+        self.checkpoint(checkpoint_path)?;
+        Ok(Box::new(|| Ok(())))
+    }
+
     /// should not be called from outside `BackendContainer::session`
     fn start_session(&mut self) {}
 
@@ -247,6 +258,9 @@ impl<A: Aggregator> Default for AggregatorState<A> {
     }
 }
 
+pub mod incremental;
+pub use self::incremental::{copy_checkpoint, remove_checkpoint};
+
 //pub mod metered;
 //pub use self::metered::Metered;
 
@@ -195,4 +195,15 @@ pub trait AggregatorOps {
         handle: &Handle<AggregatorState<A>, IK, N>,
         value: A::Input,
     ) -> Result<()>;
+    /// Returns the current accumulator, or `None` if nothing has been aggregated
+    fn aggregator_get_accumulator<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+    ) -> Result<Option<A::Accumulator>>;
+    /// Merges `accumulator` into the current accumulator using [Aggregator::merge_accumulators]
+    fn aggregator_merge<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+        accumulator: A::Accumulator,
+    ) -> Result<()>;
 }
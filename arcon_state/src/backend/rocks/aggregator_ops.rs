@@ -23,36 +23,57 @@ impl AggregatorOps for Rocks {
         &self,
         handle: &Handle<AggregatorState<A>, IK, N>,
     ) -> Result<<A as Aggregator>::Result> {
+        let accumulator = self
+            .aggregator_get_accumulator(handle)?
+            .unwrap_or_else(|| handle.extra_data.create_accumulator());
+        Ok(handle.extra_data.accumulator_into_result(accumulator))
+    }
+
+    fn aggregator_aggregate<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+        value: <A as Aggregator>::Input,
+    ) -> Result<()> {
+        let key = handle.serialize_metakeys()?;
+        let mut serialized = Vec::with_capacity(protobuf::size_hint(&value).unwrap_or(0) + 1);
+        serialized.push(VALUE_MARKER);
+        protobuf::serialize_into(&mut serialized, &value)?;
+
+        let cf = self.get_cf_handle(&handle.id)?;
+        // See the make_aggregating_merge function in this module. Its result is set as the
+        // merging operator for this state.
+        Ok(self
+            .db()
+            .merge_cf_opt(cf, key, serialized, &default_write_opts())?)
+    }
+
+    fn aggregator_get_accumulator<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+    ) -> Result<Option<<A as Aggregator>::Accumulator>> {
         let key = handle.serialize_metakeys()?;
 
         if let Some(serialized) = self.get(&handle.id, &key)? {
             assert_eq!(serialized[0], ACCUMULATOR_MARKER);
             let serialized = &serialized[1..];
 
-            let current_accumulator = protobuf::deserialize(serialized)?;
-            Ok(handle
-                .extra_data
-                .accumulator_into_result(current_accumulator))
+            Ok(Some(protobuf::deserialize(serialized)?))
         } else {
-            Ok(handle
-                .extra_data
-                .accumulator_into_result(handle.extra_data.create_accumulator()))
+            Ok(None)
         }
     }
 
-    fn aggregator_aggregate<A: Aggregator, IK: Metakey, N: Metakey>(
+    fn aggregator_merge<A: Aggregator, IK: Metakey, N: Metakey>(
         &self,
         handle: &Handle<AggregatorState<A>, IK, N>,
-        value: <A as Aggregator>::Input,
+        accumulator: <A as Aggregator>::Accumulator,
     ) -> Result<()> {
         let key = handle.serialize_metakeys()?;
-        let mut serialized = Vec::with_capacity(protobuf::size_hint(&value).unwrap_or(0) + 1);
-        serialized.push(VALUE_MARKER);
-        protobuf::serialize_into(&mut serialized, &value)?;
+        let mut serialized = Vec::with_capacity(protobuf::size_hint(&accumulator).unwrap_or(0) + 1);
+        serialized.push(ACCUMULATOR_MARKER);
+        protobuf::serialize_into(&mut serialized, &accumulator)?;
 
         let cf = self.get_cf_handle(&handle.id)?;
-        // See the make_aggregating_merge function in this module. Its result is set as the
-        // merging operator for this state.
         Ok(self
             .db()
             .merge_cf_opt(cf, key, serialized, &default_write_opts())?)
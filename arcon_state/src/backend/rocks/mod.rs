@@ -4,8 +4,9 @@
 use crate::{
     data::{Key, Metakey, Value},
     error::*,
-    Aggregator, AggregatorState, Backend, Handle, MapState, Reducer, ReducerState, ValueState,
-    VecState,
+    incremental::{self, SharedFiles, MANIFEST_FILE},
+    remove_checkpoint, Aggregator, AggregatorState, Backend, Handle, MapState, Materialize,
+    Reducer, ReducerState, ValueState, VecState,
 };
 use rocksdb::{
     checkpoint::Checkpoint, ColumnFamily, ColumnFamilyDescriptor, DBPinnableSlice, Options,
@@ -14,8 +15,9 @@ use rocksdb::{
 use std::{
     cell::UnsafeCell,
     collections::HashSet,
-    fs,
+    fs, io,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 
 unsafe impl Send for Rocks {}
@@ -26,6 +28,8 @@ pub struct Rocks {
     inner: UnsafeCell<DB>,
     path: PathBuf,
     restored: bool,
+    /// The previous checkpoint and its SST files, which the next checkpoint may share
+    last_checkpoint: Arc<Mutex<Option<(PathBuf, SharedFiles)>>>,
 }
 
 // we use epochs, so WAL is useless for us
@@ -123,6 +127,14 @@ impl Rocks {
     }
 }
 
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    // Renaming fails if the checkpoint is on another file system than the live state
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
 fn common_options<IK, N>() -> Options
 where
     IK: Metakey,
@@ -171,6 +183,7 @@ impl Backend for Rocks {
             inner: UnsafeCell::new(DB::open_cf_descriptors(&opts, &path, cfds)?),
             path,
             restored: false,
+            last_checkpoint: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -185,6 +198,8 @@ impl Backend for Rocks {
             RocksRestoreDirNotEmpty { dir: &(*live_path) }
         );
 
+        let shared = SharedFiles::read(checkpoint_path)?;
+
         let mut target_path: PathBuf = live_path.into();
         target_path.push("__DUMMY"); // the file name is replaced inside the loop below
         for entry in fs::read_dir(checkpoint_path)? {
@@ -195,6 +210,10 @@ impl Backend for Rocks {
                 .expect("Cannot read entry metadata")
                 .is_file());
 
+            if entry.file_name() == MANIFEST_FILE {
+                continue;
+            }
+
             let source_path = entry.path();
             // replaces the __DUMMY from above the loop
             target_path.set_file_name(
@@ -206,9 +225,23 @@ impl Backend for Rocks {
             fs::copy(&source_path, &target_path)?;
         }
 
+        // SST files that are held by earlier checkpoints of an incremental chain
+        if let Some(shared) = &shared {
+            for (file, owner) in shared.iter() {
+                let target_path = live_path.join(file);
+                if !target_path.exists() {
+                    fs::copy(
+                        incremental::resolve(checkpoint_path, file, owner)?,
+                        target_path,
+                    )?;
+                }
+            }
+        }
+
         Rocks::create(live_path).map(|mut r| {
             //r.get_mut().restored = true;
             r.restored = true;
+            r.last_checkpoint = Arc::new(Mutex::new(shared.map(|s| (checkpoint_path.into(), s))));
             r
         })
     }
@@ -218,6 +251,11 @@ impl Backend for Rocks {
     }
 
     fn checkpoint(&self, checkpoint_path: &Path) -> Result<()> {
+        let materialize = self.snapshot(checkpoint_path)?;
+        materialize()
+    }
+
+    fn snapshot(&self, checkpoint_path: &Path) -> Result<Materialize> {
         let db = self.db();
         db.flush()?;
 
@@ -226,11 +264,58 @@ impl Backend for Rocks {
         if checkpoint_path.exists() {
             // TODO: add a warning log here
             // warn!(logger, "Checkpoint path {:?} exists, deleting");
-            fs::remove_dir_all(checkpoint_path)?
+            remove_checkpoint(checkpoint_path)?
         }
 
-        checkpointer.create_checkpoint(checkpoint_path)?;
-        Ok(())
+        // The live files are hard linked, so the staging directory is kept next to the live state
+        let name = incremental::dir_name(checkpoint_path)?;
+        let staging =
+            self.path
+                .with_file_name(format!("{}.{}", incremental::dir_name(&self.path)?, name));
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        checkpointer.create_checkpoint(&staging)?;
+
+        // SST files are immutable, so the ones that are already part of the
+        // previous checkpoint in the same directory are shared rather than copied
+        let last_checkpoint = self.last_checkpoint.lock().unwrap();
+        let previous = match &*last_checkpoint {
+            Some((last, files)) if incremental::shares_files(last, checkpoint_path) => Some(files),
+            _ => None,
+        };
+
+        let mut shared = SharedFiles::default();
+        let mut owned = Vec::new();
+        for entry in fs::read_dir(&staging)? {
+            let file = entry?.file_name().to_string_lossy().into_owned();
+            let is_sst = file.ends_with(".sst");
+
+            match previous.and_then(|p| p.owner(&file)) {
+                Some(owner) if is_sst => shared.insert(file, owner),
+                _ => {
+                    if is_sst {
+                        shared.insert(file.as_str(), name.as_str());
+                    }
+                    owned.push(file);
+                }
+            }
+        }
+        drop(last_checkpoint);
+
+        // Only a checkpoint that has been written may be shared with the next one
+        let last_checkpoint = self.last_checkpoint.clone();
+        let checkpoint_path = checkpoint_path.to_path_buf();
+        Ok(Box::new(move || {
+            fs::create_dir_all(&checkpoint_path)?;
+            for file in owned {
+                move_file(&staging.join(&file), &checkpoint_path.join(&file))?;
+            }
+            shared.write(&checkpoint_path)?;
+            fs::remove_dir_all(&staging)?;
+            *last_checkpoint.lock().unwrap() = Some((checkpoint_path, shared));
+            Ok(())
+        }))
     }
 
     fn register_value_handle<'s, T: Value, IK: Metakey, N: Metakey>(
@@ -426,6 +511,52 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn incremental_checkpoint_test() {
+        let live_dir = TempDir::new().unwrap();
+        let checkpoints_dir = TempDir::new().unwrap();
+        let restore_dir = TempDir::new().unwrap();
+        let column_family = "default";
+
+        let db = Rocks::create(live_dir.path()).unwrap();
+        let checkpoint = |epoch: u64| {
+            let path = checkpoints_dir
+                .path()
+                .join(format!("checkpoint_op_{}", epoch));
+            db.checkpoint(&path).expect("checkpoint failed");
+            path
+        };
+
+        db.put(column_family, b"a", b"1").unwrap();
+        let first = checkpoint(0);
+        db.put(column_family, b"b", b"2").unwrap();
+        let second = checkpoint(1);
+
+        // The SST file of the first epoch is shared rather than copied
+        let shared = SharedFiles::read(&second).unwrap().unwrap();
+        let reused: Vec<_> = shared
+            .iter()
+            .filter(|(_, owner)| *owner == "checkpoint_op_0")
+            .map(|(file, _)| file.to_owned())
+            .collect();
+        assert!(!reused.is_empty());
+        assert!(reused.iter().all(|file| !second.join(file).exists()));
+
+        // Shared files survive the removal of the checkpoint that holds them
+        remove_checkpoint(&first).unwrap();
+        assert!(!first.exists());
+
+        let restored = Rocks::restore(&restore_dir.path().join("op"), &second).unwrap();
+        assert_eq!(
+            restored.get(column_family, b"a").unwrap().unwrap().as_ref(),
+            b"1"
+        );
+        assert_eq!(
+            restored.get(column_family, b"b").unwrap().unwrap().as_ref(),
+            b"2"
+        );
+    }
+
     #[test]
     fn checkpoint_restore_state_test() {
         let mut original_test = TestDb::new();
@@ -24,34 +24,53 @@ impl AggregatorOps for Sled {
         &self,
         handle: &Handle<AggregatorState<A>, IK, N>,
     ) -> Result<<A as Aggregator>::Result> {
+        let accumulator = self
+            .aggregator_get_accumulator(handle)?
+            .unwrap_or_else(|| handle.extra_data.create_accumulator());
+        Ok(handle.extra_data.accumulator_into_result(accumulator))
+    }
+
+    fn aggregator_aggregate<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+        value: <A as Aggregator>::Input,
+    ) -> Result<()> {
+        let key = handle.serialize_metakeys()?;
+        let mut serialized = vec![VALUE_MARKER];
+        protobuf::serialize_into(&mut serialized, &value)?;
+
+        // See the make_aggregator_merge function in this module. Its result is set as the merging operator for this state.
+        self.merge(&handle.id, &key, &serialized)?;
+
+        Ok(())
+    }
+
+    fn aggregator_get_accumulator<A: Aggregator, IK: Metakey, N: Metakey>(
+        &self,
+        handle: &Handle<AggregatorState<A>, IK, N>,
+    ) -> Result<Option<<A as Aggregator>::Accumulator>> {
         let key = handle.serialize_metakeys()?;
 
         if let Some(serialized) = self.get(&handle.id, &key)? {
             assert_eq!(serialized[0], ACCUMULATOR_MARKER);
             let serialized = &serialized[1..];
 
-            let current_accumulator = protobuf::deserialize(serialized)?;
-            Ok(handle
-                .extra_data
-                .accumulator_into_result(current_accumulator))
+            Ok(Some(protobuf::deserialize(serialized)?))
         } else {
-            Ok(handle
-                .extra_data
-                .accumulator_into_result(handle.extra_data.create_accumulator()))
+            Ok(None)
         }
     }
 
-    fn aggregator_aggregate<A: Aggregator, IK: Metakey, N: Metakey>(
+    fn aggregator_merge<A: Aggregator, IK: Metakey, N: Metakey>(
         &self,
         handle: &Handle<AggregatorState<A>, IK, N>,
-        value: <A as Aggregator>::Input,
+        accumulator: <A as Aggregator>::Accumulator,
     ) -> Result<()> {
         let key = handle.serialize_metakeys()?;
-        let mut serialized = vec![VALUE_MARKER];
-        protobuf::serialize_into(&mut serialized, &value)?;
+        let mut serialized = vec![ACCUMULATOR_MARKER];
+        protobuf::serialize_into(&mut serialized, &accumulator)?;
 
-        // See the make_aggregator_merge function in this module. Its result is set as the merging operator for this state.
-        self.tree(&handle.id)?.merge(key, serialized)?;
+        self.merge(&handle.id, &key, &serialized)?;
 
         Ok(())
     }
@@ -8,7 +8,6 @@ use crate::{
     sled::Sled,
     Handle, MapOps, MapState,
 };
-use sled::Batch;
 
 impl MapOps for Sled {
     fn map_clear<K: Key, V: Value, IK: Metakey, N: Metakey>(
@@ -81,16 +80,14 @@ impl MapOps for Sled {
         handle: &Handle<MapState<K, V>, IK, N>,
         key_value_pairs: impl IntoIterator<Item = (K, V)>,
     ) -> Result<()> {
-        let mut batch = Batch::default();
-        let tree = self.tree(&handle.id)?;
-
+        let mut entries = Vec::new();
         for (user_key, value) in key_value_pairs {
             let key = handle.serialize_metakeys_and_key(&user_key)?;
             let serialized = protobuf::serialize(&value)?;
-            batch.insert(key, serialized);
+            entries.push((key, serialized));
         }
 
-        Ok(tree.apply_batch(batch)?)
+        self.insert_all(&handle.id, entries)
     }
 
     fn map_insert_all_by_ref<'a, K: Key, V: Value, IK: Metakey, N: Metakey>(
@@ -98,16 +95,14 @@ impl MapOps for Sled {
         handle: &Handle<MapState<K, V>, IK, N>,
         key_value_pairs: impl IntoIterator<Item = (&'a K, &'a V)>,
     ) -> Result<()> {
-        let mut batch = Batch::default();
-        let tree = self.tree(&handle.id)?;
-
+        let mut entries = Vec::new();
         for (user_key, value) in key_value_pairs {
             let key = handle.serialize_metakeys_and_key(user_key)?;
             let serialized = protobuf::serialize(value)?;
-            batch.insert(key, serialized);
+            entries.push((key, serialized));
         }
 
-        Ok(tree.apply_batch(batch)?)
+        self.insert_all(&handle.id, entries)
     }
 
     fn map_remove<K: Key, V: Value, IK: Metakey, N: Metakey>(
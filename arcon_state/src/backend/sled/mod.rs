@@ -10,16 +10,21 @@ use crate::{
 use sled::{open, Batch, Db, IVec, Tree};
 use std::path::Path;
 #[cfg(feature = "sled_checkpoints")]
-use std::{
-    fs, io,
-    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
-    path::PathBuf,
-};
+use std::sync::{Arc, Mutex};
 
 #[derive(Debug)]
 pub struct Sled {
     db: Db,
     restored: bool,
+    /// The previous checkpoint, which the next checkpoint may share chunks with
+    #[cfg(feature = "sled_checkpoints")]
+    last_checkpoint: Arc<Mutex<Option<snapshot::LastSnapshot>>>,
+    /// Tells which trees have been written since they were last checkpointed
+    #[cfg(feature = "sled_checkpoints")]
+    versions: snapshot::Versions,
+    /// Views of the checkpoints that are still being written
+    #[cfg(feature = "sled_checkpoints")]
+    views: snapshot::Views,
 }
 
 impl Sled {
@@ -33,24 +38,67 @@ impl Sled {
         Ok(val)
     }
 
-    fn put(&self, tree_name: &str, key: &[u8], value: &[u8]) -> Result<Option<IVec>> {
+    /// Opens a tree whose entries at `keys` are about to be written
+    ///
+    /// All writes go through here, so that checkpoints know which trees have changed
+    /// and checkpoints that are still being written keep the previous values.
+    #[allow(unused_variables)]
+    fn tree_to_write<'k>(
+        &self,
+        tree_name: &str,
+        keys: impl IntoIterator<Item = &'k [u8]>,
+    ) -> Result<Tree> {
         let tree = self.tree(tree_name)?;
+        #[cfg(feature = "sled_checkpoints")]
+        {
+            self.versions.bump(tree_name.as_bytes());
+            self.views.preserve(tree_name.as_bytes(), &tree, keys)?;
+        }
+        Ok(tree)
+    }
+
+    fn put(&self, tree_name: &str, key: &[u8], value: &[u8]) -> Result<Option<IVec>> {
+        let tree = self.tree_to_write(tree_name, Some(key))?;
         let old = tree.insert(key, value)?;
         Ok(old)
     }
 
+    fn merge(&self, tree_name: &str, key: &[u8], value: &[u8]) -> Result<()> {
+        let tree = self.tree_to_write(tree_name, Some(key))?;
+        tree.merge(key, value)?;
+        Ok(())
+    }
+
+    fn insert_all(&self, tree_name: &str, entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        let tree = self.tree_to_write(tree_name, entries.iter().map(|(k, _)| k.as_slice()))?;
+
+        let mut batch = Batch::default();
+        for (key, value) in entries {
+            batch.insert(key, value);
+        }
+
+        tree.apply_batch(batch)?;
+
+        Ok(())
+    }
+
     fn remove(&self, tree_name: &str, key: &[u8]) -> Result<Option<IVec>> {
-        let tree = self.tree(tree_name)?;
+        let tree = self.tree_to_write(tree_name, Some(key))?;
         let old = tree.remove(key)?;
         Ok(old)
     }
 
     fn remove_prefix(&self, tree_name: &str, prefix: Vec<u8>) -> Result<()> {
-        let tree = self.tree(tree_name)?;
+        let keys = self
+            .tree(tree_name)?
+            .scan_prefix(prefix)
+            .keys()
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let tree = self.tree_to_write(tree_name, keys.iter().map(|k| &k[..]))?;
 
         let mut batch = Batch::default();
-        for key in tree.scan_prefix(prefix).keys() {
-            batch.remove(key?);
+        for key in keys {
+            batch.remove(key);
         }
 
         tree.apply_batch(batch)?;
@@ -73,6 +121,12 @@ impl Backend for Sled {
         Ok(Sled {
             db,
             restored: false,
+            #[cfg(feature = "sled_checkpoints")]
+            last_checkpoint: Arc::new(Mutex::new(None)),
+            #[cfg(feature = "sled_checkpoints")]
+            versions: snapshot::Versions::default(),
+            #[cfg(feature = "sled_checkpoints")]
+            views: snapshot::Views::default(),
         })
     }
 
@@ -86,16 +140,23 @@ impl Backend for Sled {
         let mut restored = false;
 
         #[cfg(feature = "sled_checkpoints")]
-        {
-            let mut p: PathBuf = checkpoint_path.into();
-            p.push("SLED_EXPORT");
-            let import_data = parse_dumped_sled_export(&p)?;
-            db.import(import_data);
-
+        let (last_checkpoint, versions) = {
+            let last = snapshot::restore(&db, checkpoint_path)?;
             restored = true;
-        }
+            let versions = snapshot::Versions::restored(&last);
+            (Arc::new(Mutex::new(Some(last))), versions)
+        };
 
-        Ok(Sled { db, restored })
+        Ok(Sled {
+            db,
+            restored,
+            #[cfg(feature = "sled_checkpoints")]
+            last_checkpoint,
+            #[cfg(feature = "sled_checkpoints")]
+            versions,
+            #[cfg(feature = "sled_checkpoints")]
+            views: snapshot::Views::default(),
+        })
     }
 
     fn was_restored(&self) -> bool {
@@ -112,52 +173,32 @@ impl Backend for Sled {
 
     #[cfg(feature = "sled_checkpoints")]
     fn checkpoint(&self, checkpoint_path: &Path) -> Result<()> {
-        // TODO: sled doesn't support checkpoints/snapshots, but that and MVCC is planned
-        //   for now we'll just dump it via the export/import mechanism WHICH MAY BE VERY SLOW
-        let export_data = self.db.export();
+        let materialize = self.snapshot(checkpoint_path)?;
+        materialize()
+    }
 
-        let mut p: PathBuf = checkpoint_path.into();
-        if !p.exists() {
-            fs::create_dir_all(&p)?;
+    #[cfg(feature = "sled_checkpoints")]
+    fn snapshot(&self, checkpoint_path: &Path) -> Result<crate::Materialize> {
+        if checkpoint_path.exists() {
+            crate::remove_checkpoint(checkpoint_path)?;
         }
 
-        p.push("SLED_EXPORT");
-        let out = fs::File::create(&p)?;
-        let mut writer = BufWriter::new(out);
-
-        writer.write_all(&export_data.len().to_le_bytes())?;
-
-        #[inline]
-        fn write_len_and_bytes(mut w: impl Write, bytes: &[u8]) -> io::Result<()> {
-            w.write_all(&bytes.len().to_le_bytes())?;
-            w.write_all(bytes)?;
+        // Unchanged trees are shared with the previous checkpoint in the same directory
+        let prepared = snapshot::prepare(
+            &self.db,
+            &self.versions,
+            &self.views,
+            checkpoint_path,
+            self.last_checkpoint.lock().unwrap().as_ref(),
+        )?;
+
+        // Only a checkpoint that has been written may be shared with the next one
+        let last_checkpoint = self.last_checkpoint.clone();
+        Ok(Box::new(move || {
+            let last = prepared.write()?;
+            *last_checkpoint.lock().unwrap() = Some(last);
             Ok(())
-        }
-
-        for (typ, name, vvecs) in export_data {
-            write_len_and_bytes(&mut writer, &typ)?;
-            write_len_and_bytes(&mut writer, &name)?;
-
-            // we don't know how many elements are in `vvecs`, so let's save the current position,
-            // write a dummy length, write all the elements and then go back and write how many we wrote
-            let length_spot = writer.seek(SeekFrom::Current(0))?;
-            writer.write_all(&0usize.to_le_bytes())?;
-            let mut length = 0usize;
-
-            for vecs in vvecs {
-                length += 1;
-                writer.write_all(&vecs.len().to_le_bytes())?;
-                for vec in vecs {
-                    write_len_and_bytes(&mut writer, &vec)?;
-                }
-            }
-
-            let after_vecs = writer.seek(SeekFrom::Current(0))?;
-            let _ = writer.seek(SeekFrom::Start(length_spot))?;
-            writer.write_all(&length.to_le_bytes())?;
-            let _ = writer.seek(SeekFrom::Start(after_vecs))?;
-        }
-        Ok(())
+        }))
     }
 
     fn register_value_handle<T: Value, IK: Metakey, N: Metakey>(
@@ -213,59 +254,11 @@ impl Backend for Sled {
 mod aggregator_ops;
 mod map_ops;
 mod reducer_ops;
+#[cfg(feature = "sled_checkpoints")]
+mod snapshot;
 mod value_ops;
 mod vec_ops;
 
-#[cfg(feature = "sled_checkpoints")]
-fn parse_dumped_sled_export(
-    dump_path: &Path,
-) -> Result<Vec<(Vec<u8>, Vec<u8>, impl Iterator<Item = Vec<Vec<u8>>>)>> {
-    let f = fs::File::open(dump_path)?;
-    let mut reader = BufReader::new(f);
-
-    #[inline]
-    fn read_length(r: &mut impl Read) -> io::Result<usize> {
-        let mut length_bytes = 0usize.to_le_bytes();
-        r.read_exact(&mut length_bytes)?;
-        Ok(usize::from_le_bytes(length_bytes))
-    }
-
-    let length = read_length(&mut reader)?;
-    let mut res = Vec::with_capacity(length);
-
-    #[inline]
-    fn read_bytes_starting_with_len(r: &mut impl Read) -> io::Result<Vec<u8>> {
-        let length = read_length(r)?;
-        let mut res = vec![0u8; length];
-        r.read_exact(&mut res)?;
-        Ok(res)
-    }
-
-    // TODO: can we make it so this doesn't run out of memory when the database is huge?
-    //   that could be possibly done by skipping around the file to read the type and name and
-    //   then reading the actual contents (impl Iterator) lazily
-    for _ in 0..length {
-        let typ = read_bytes_starting_with_len(&mut reader)?;
-        let name = read_bytes_starting_with_len(&mut reader)?;
-
-        let vvecs_length = read_length(&mut reader)?;
-        let mut vvecs = Vec::with_capacity(vvecs_length);
-        for _ in 0..vvecs_length {
-            let vecs_length = read_length(&mut reader)?;
-            let mut vecs = Vec::with_capacity(vecs_length);
-            for _ in 0..vecs_length {
-                vecs.push(read_bytes_starting_with_len(&mut reader)?);
-            }
-            vvecs.push(vecs);
-        }
-        let vvecs = vvecs.into_iter();
-
-        res.push((typ, name, vvecs))
-    }
-
-    Ok(res)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -349,5 +342,86 @@ mod tests {
         assert_eq!(restored_t.get(b"y"), Ok(Some(IVec::from(b"20"))));
     }
 
+    #[cfg(feature = "sled_checkpoints")]
+    #[test]
+    fn test_sled_snapshot_ignores_later_writes() {
+        let dir = TempDir::new().unwrap();
+        let sled = Sled::create(dir.path()).unwrap();
+
+        sled.put("tree", b"a", b"1").unwrap();
+        sled.put("tree", b"b", b"2").unwrap();
+        sled.put("tree", b"c", b"3").unwrap();
+
+        let chkp_dir = TempDir::new().unwrap();
+        let restore_dir = TempDir::new().unwrap();
+
+        let materialize = sled.snapshot(chkp_dir.path()).unwrap();
+
+        sled.put("tree", b"a", b"10").unwrap();
+        sled.remove("tree", b"b").unwrap();
+        sled.put("tree", b"d", b"4").unwrap();
+        sled.remove_prefix("tree", b"c".to_vec()).unwrap();
+
+        materialize().unwrap();
+
+        let restored = Sled::restore(restore_dir.path(), chkp_dir.path()).unwrap();
+        let tree = restored.tree("tree").unwrap();
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.get(b"a"), Ok(Some(IVec::from(b"1"))));
+        assert_eq!(tree.get(b"b"), Ok(Some(IVec::from(b"2"))));
+        assert_eq!(tree.get(b"c"), Ok(Some(IVec::from(b"3"))));
+        assert_eq!(tree.get(b"d"), Ok(None));
+    }
+
+    #[cfg(feature = "sled_checkpoints")]
+    #[test]
+    fn test_incremental_sled_checkpoints() {
+        let dir = TempDir::new().unwrap();
+        let sled = Sled::create(&dir.path().join("live")).unwrap();
+        let checkpoints = dir.path().join("checkpoints");
+
+        sled.put("changing", b"a", b"1").unwrap();
+        sled.put("unchanged", b"x", b"10").unwrap();
+        let first = checkpoints.join("checkpoint_op_0");
+        sled.checkpoint(&first).unwrap();
+
+        sled.put("changing", b"a", b"2").unwrap();
+        let second = checkpoints.join("checkpoint_op_1");
+        sled.checkpoint(&second).unwrap();
+
+        // Only the chunk of the changed tree is written again
+        let files = crate::incremental::SharedFiles::read(&second)
+            .unwrap()
+            .unwrap();
+        let owners: Vec<&str> = files.iter().map(|(_, owner)| owner).collect();
+        assert!(owners.contains(&"checkpoint_op_0"));
+        assert!(owners.contains(&"checkpoint_op_1"));
+
+        crate::remove_checkpoint(&first).unwrap();
+        let restored = Sled::restore(&dir.path().join("restored"), &second).unwrap();
+        let changing = restored.db.open_tree(b"changing").unwrap();
+        let unchanged = restored.db.open_tree(b"unchanged").unwrap();
+        assert_eq!(changing.get(b"a"), Ok(Some(IVec::from(b"2"))));
+        assert_eq!(unchanged.get(b"x"), Ok(Some(IVec::from(b"10"))));
+
+        // Chunks are verified against their checksum
+        let (chunk, _) = files
+            .iter()
+            .find(|(_, owner)| *owner == "checkpoint_op_1")
+            .unwrap();
+        fs::write(second.join(chunk), b"garbage").unwrap();
+        assert!(Sled::restore(&dir.path().join("corrupt"), &second).is_err());
+
+        // A write that leaves the tree as it was is still a change
+        sled.put("unchanged", b"x", b"10").unwrap();
+        let third = checkpoints.join("checkpoint_op_2");
+        sled.checkpoint(&third).unwrap();
+        let files = crate::incremental::SharedFiles::read(&third)
+            .unwrap()
+            .unwrap();
+        let owners: Vec<&str> = files.iter().map(|(_, owner)| owner).collect();
+        assert_eq!(owners, vec!["checkpoint_op_1", "checkpoint_op_2"]);
+    }
+
     common_state_tests!(TestDb::new());
 }
@@ -44,7 +44,7 @@ impl ReducerOps for Sled {
 
         // See the make_reducer_merge function in this module. Its result is set as the merging
         // operator for this state.
-        self.tree(&handle.id)?.merge(key, serialized)?;
+        self.merge(&handle.id, &key, &serialized)?;
 
         Ok(())
     }
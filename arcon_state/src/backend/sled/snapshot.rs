@@ -0,0 +1,569 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! Chunked and checksummed snapshots of a sled database
+//!
+//! A snapshot consists of a [MANIFEST] that lists the trees of the database together
+//! with the chunk files that hold their entries. Entries are streamed from each tree
+//! into chunks of roughly [CHUNK_SIZE] bytes, and every chunk is verified against its
+//! CRC32 before it is restored.
+//!
+//! Preparing a snapshot only decides its manifest and takes a copy-on-write [View] of
+//! the changed trees. Their entries are streamed to disk later on while the database is
+//! written again, and the view keeps the previous values of the entries written in between.
+//!
+//! Trees that have not been written since the previous snapshot in the same directory
+//! keep referencing the chunks of that snapshot, see [crate::backend::incremental].
+
+use crate::{
+    error::*,
+    incremental::{self, SharedFiles},
+};
+use sled::{Batch, Db, IVec, Tree};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+};
+
+/// File name of the manifest inside a snapshot directory
+pub(crate) const MANIFEST: &str = "SLED_SNAPSHOT";
+/// Size in bytes after which a chunk is written out
+const CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Chunk {
+    file: String,
+    len: u64,
+    crc: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TreeSnapshot {
+    name: Vec<u8>,
+    /// Version of the tree when it was snapshotted, see [Versions]
+    version: u64,
+    chunks: Vec<Chunk>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Manifest {
+    trees: Vec<TreeSnapshot>,
+}
+
+impl Manifest {
+    fn read(snapshot_path: &Path) -> Result<Option<Manifest>> {
+        let path = snapshot_path.join(MANIFEST);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut manifest = Manifest::default();
+        for line in content.lines().filter(|l| !l.is_empty()) {
+            let fields: Vec<&str> = line.split(' ').collect();
+            match (fields.as_slice(), manifest.trees.last_mut()) {
+                (["tree", name, version], _) => manifest.trees.push(TreeSnapshot {
+                    name: decode_hex(name).with_context(|| corrupt(&path, line))?,
+                    version: version.parse().ok().with_context(|| corrupt(&path, line))?,
+                    chunks: Vec::new(),
+                }),
+                (["chunk", file, len, crc], Some(tree)) => tree.chunks.push(Chunk {
+                    file: (*file).to_owned(),
+                    len: len.parse().ok().with_context(|| corrupt(&path, line))?,
+                    crc: crc.parse().ok().with_context(|| corrupt(&path, line))?,
+                }),
+                _ => return corrupt(&path, line).fail(),
+            }
+        }
+
+        Ok(Some(manifest))
+    }
+
+    fn write(&self, snapshot_path: &Path) -> Result<()> {
+        let mut content = String::new();
+        for tree in &self.trees {
+            content.push_str(&format!(
+                "tree {} {}\n",
+                encode_hex(&tree.name),
+                tree.version
+            ));
+            for chunk in &tree.chunks {
+                content.push_str(&format!(
+                    "chunk {} {} {}\n",
+                    chunk.file, chunk.len, chunk.crc
+                ));
+            }
+        }
+
+        // The manifest is written last, so a snapshot without one is incomplete
+        let tmp = snapshot_path.join(format!("{}.tmp", MANIFEST));
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, snapshot_path.join(MANIFEST))?;
+        Ok(())
+    }
+
+    fn tree(&self, name: &[u8]) -> Option<&TreeSnapshot> {
+        self.trees.iter().find(|t| t.name == name)
+    }
+}
+
+/// Versions of the trees of a database, which tell whether a tree has changed
+///
+/// A tree is stamped with the next value of a counter whenever it is written,
+/// so it keeps the version of a snapshot until it is written again.
+#[derive(Debug, Default)]
+pub(crate) struct Versions {
+    counter: AtomicU64,
+    /// Only locked for writing when a tree is stamped for the first time
+    trees: RwLock<HashMap<Vec<u8>, AtomicU64>>,
+}
+
+impl Versions {
+    /// Continues from the versions of the trees of a restored snapshot
+    pub(crate) fn restored(last: &LastSnapshot) -> Versions {
+        let trees: HashMap<Vec<u8>, AtomicU64> = last
+            .manifest
+            .trees
+            .iter()
+            .map(|tree| (tree.name.clone(), AtomicU64::new(tree.version)))
+            .collect();
+        let counter = last
+            .manifest
+            .trees
+            .iter()
+            .map(|tree| tree.version)
+            .max()
+            .unwrap_or(0);
+        Versions {
+            counter: AtomicU64::new(counter),
+            trees: RwLock::new(trees),
+        }
+    }
+
+    /// Stamps `tree` with a new version before it is written
+    pub(crate) fn bump(&self, tree: &[u8]) {
+        let version = self.next();
+        if let Some(current) = self.trees.read().unwrap().get(tree) {
+            current.fetch_max(version, Ordering::SeqCst);
+            return;
+        }
+        self.trees
+            .write()
+            .unwrap()
+            .entry(tree.to_vec())
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_max(version, Ordering::SeqCst);
+    }
+
+    /// Returns the version of `tree`, stamping trees that have never been written
+    fn get(&self, tree: &[u8]) -> u64 {
+        if let Some(version) = self.trees.read().unwrap().get(tree) {
+            return version.load(Ordering::SeqCst);
+        }
+        let version = self.next();
+        self.trees
+            .write()
+            .unwrap()
+            .entry(tree.to_vec())
+            .or_insert_with(|| AtomicU64::new(version))
+            .load(Ordering::SeqCst)
+    }
+
+    fn next(&self) -> u64 {
+        self.counter.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+/// The last snapshot of a database, which decides what the next snapshot may share
+#[derive(Debug, Clone)]
+pub(crate) struct LastSnapshot {
+    path: PathBuf,
+    manifest: Manifest,
+    files: SharedFiles,
+}
+
+/// Views of the snapshots whose trees are still being streamed
+///
+/// Every write to a tree goes through [Views::preserve] first, so that the views keep
+/// the values that the written entries had when they were taken.
+#[derive(Debug, Default)]
+pub(crate) struct Views {
+    /// Amount of views that have not been dropped yet, which lets writes skip the lock
+    live: Arc<AtomicUsize>,
+    views: Mutex<Vec<Weak<View>>>,
+}
+
+impl Views {
+    /// Creates a view that is counted as live until it is dropped
+    fn create(&self) -> View {
+        self.live.fetch_add(1, Ordering::SeqCst);
+        View {
+            trees: Mutex::default(),
+            live: self.live.clone(),
+        }
+    }
+
+    fn add(&self, view: &Arc<View>) {
+        let mut views = self.views.lock().unwrap();
+        views.retain(|view| view.strong_count() > 0);
+        views.push(Arc::downgrade(view));
+    }
+
+    /// Keeps the current values of `keys` in `tree` for the views that have yet to stream it
+    pub(crate) fn preserve<'k>(
+        &self,
+        tree_name: &[u8],
+        tree: &Tree,
+        keys: impl IntoIterator<Item = &'k [u8]>,
+    ) -> Result<()> {
+        if self.live.load(Ordering::SeqCst) == 0 {
+            return Ok(());
+        }
+
+        let views: Vec<Arc<View>> = self
+            .views
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        if views.is_empty() {
+            return Ok(());
+        }
+
+        let keys: Vec<&[u8]> = keys.into_iter().collect();
+        for view in views {
+            view.preserve(tree_name, tree, &keys)?;
+        }
+        Ok(())
+    }
+}
+
+/// A copy-on-write view of the changed trees of a database at the time of a snapshot
+///
+/// The view only holds the previous values of entries that are written while their tree
+/// has not been streamed yet, so taking it is cheap.
+#[derive(Debug)]
+struct View {
+    /// Previous values per tree that is still to be streamed, `None` if the entry did not
+    /// exist. The flag tells whether the value has already been streamed.
+    trees: Mutex<HashMap<Vec<u8>, HashMap<Vec<u8>, (Option<IVec>, bool)>>>,
+    /// Count of the live views in [Views]
+    live: Arc<AtomicUsize>,
+}
+
+impl Drop for View {
+    fn drop(&mut self) {
+        self.live.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl View {
+    fn preserve(&self, tree_name: &[u8], tree: &Tree, keys: &[&[u8]]) -> Result<()> {
+        let mut trees = self.trees.lock().unwrap();
+        if let Some(previous) = trees.get_mut(tree_name) {
+            for key in keys {
+                if !previous.contains_key(*key) {
+                    previous.insert(key.to_vec(), (tree.get(key)?, false));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the value that `key` had when the view was taken, given its live `value`
+    fn resolve(&self, tree_name: &[u8], key: &[u8], value: IVec) -> Option<IVec> {
+        let mut trees = self.trees.lock().unwrap();
+        match trees.get_mut(tree_name).and_then(|t| t.get_mut(key)) {
+            Some((previous, streamed)) => {
+                *streamed = true;
+                previous.clone()
+            }
+            None => Some(value),
+        }
+    }
+
+    /// Stops preserving `tree_name` and returns the entries that were removed from it
+    /// before they could be streamed
+    fn release(&self, tree_name: &[u8]) -> Vec<(Vec<u8>, IVec)> {
+        let previous = self.trees.lock().unwrap().remove(tree_name);
+        previous
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, (value, streamed))| match (value, streamed) {
+                (Some(value), false) => Some((key, value)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A snapshot whose manifest is decided but whose changed trees are still to be streamed
+pub(crate) struct PreparedSnapshot {
+    path: PathBuf,
+    name: String,
+    db: Db,
+    view: Arc<View>,
+    manifest: Manifest,
+    /// Positions of the trees in the manifest that are streamed into new chunks
+    changed: Vec<usize>,
+    files: SharedFiles,
+}
+
+impl PreparedSnapshot {
+    /// Streams the changed trees into chunks and writes the manifest into the snapshot directory
+    ///
+    /// Returns what the next snapshot may share with this one.
+    pub(crate) fn write(mut self) -> Result<LastSnapshot> {
+        fs::create_dir_all(&self.path)?;
+
+        let mut writer = ChunkWriter::new(&self.path, &self.name);
+        for i in self.changed {
+            let snapshot = &mut self.manifest.trees[i];
+            let tree = self.db.open_tree(&snapshot.name)?;
+            snapshot.chunks = writer.write_tree(&snapshot.name, &tree, &self.view)?;
+            for chunk in &snapshot.chunks {
+                self.files.insert(chunk.file.as_str(), self.name.as_str());
+            }
+        }
+
+        self.files.write(&self.path)?;
+        self.manifest.write(&self.path)?;
+
+        Ok(LastSnapshot {
+            path: self.path,
+            manifest: self.manifest,
+            files: self.files,
+        })
+    }
+}
+
+/// Takes a view of the trees of `db` for a snapshot at `snapshot_path`
+///
+/// Unchanged trees share their chunks with the `last` snapshot if it is in the same directory,
+/// the others are streamed once the snapshot is written.
+pub(crate) fn prepare(
+    db: &Db,
+    versions: &Versions,
+    views: &Views,
+    snapshot_path: &Path,
+    last: Option<&LastSnapshot>,
+) -> Result<PreparedSnapshot> {
+    let last = last.filter(|last| incremental::shares_files(&last.path, snapshot_path));
+    let name = incremental::dir_name(snapshot_path)?;
+    let view = Arc::new(views.create());
+    let mut manifest = Manifest::default();
+    let mut changed = Vec::new();
+    let mut files = SharedFiles::default();
+
+    {
+        let mut view_trees = view.trees.lock().unwrap();
+        for tree_name in db.tree_names() {
+            let version = versions.get(&tree_name);
+
+            let unchanged = last.and_then(|last| {
+                last.manifest
+                    .tree(&tree_name)
+                    .filter(|t| {
+                        t.version == version
+                            && t.chunks.iter().all(|c| last.files.contains(&c.file))
+                    })
+                    .map(|t| (t, &last.files))
+            });
+
+            let snapshot = match unchanged {
+                Some((previous, previous_files)) => {
+                    for chunk in &previous.chunks {
+                        if let Some(owner) = previous_files.owner(&chunk.file) {
+                            files.insert(chunk.file.as_str(), owner);
+                        }
+                    }
+                    previous.clone()
+                }
+                None => {
+                    changed.push(manifest.trees.len());
+                    view_trees.insert(tree_name.to_vec(), HashMap::new());
+                    TreeSnapshot {
+                        name: tree_name.to_vec(),
+                        version,
+                        chunks: Vec::new(),
+                    }
+                }
+            };
+            manifest.trees.push(snapshot);
+        }
+    }
+    views.add(&view);
+
+    Ok(PreparedSnapshot {
+        path: snapshot_path.to_path_buf(),
+        name,
+        db: db.clone(),
+        view,
+        manifest,
+        changed,
+        files,
+    })
+}
+
+/// Restores the trees of the snapshot at `snapshot_path` into `db`
+pub(crate) fn restore(db: &Db, snapshot_path: &Path) -> Result<LastSnapshot> {
+    let manifest = Manifest::read(snapshot_path)?.with_context(|| SledCorruptSnapshot {
+        path: snapshot_path.to_path_buf(),
+        reason: "missing manifest",
+    })?;
+    let files = SharedFiles::read(snapshot_path)?.unwrap_or_default();
+
+    for snapshot in &manifest.trees {
+        let tree = db.open_tree(&snapshot.name)?;
+
+        for chunk in &snapshot.chunks {
+            let path = match files.owner(&chunk.file) {
+                Some(owner) => incremental::resolve(snapshot_path, &chunk.file, owner)?,
+                None => snapshot_path.join(&chunk.file),
+            };
+            let data = fs::read(&path)?;
+            ensure!(
+                data.len() as u64 == chunk.len && crc32fast::hash(&data) == chunk.crc,
+                SledCorruptSnapshot {
+                    path,
+                    reason: "checksum mismatch",
+                }
+            );
+
+            let mut batch = Batch::default();
+            let mut records = &data[..];
+            while !records.is_empty() {
+                let (key, value) =
+                    read_record(&mut records).with_context(|| SledCorruptSnapshot {
+                        path: path.clone(),
+                        reason: "truncated record",
+                    })?;
+                batch.insert(key, value);
+            }
+            tree.apply_batch(batch)?;
+        }
+    }
+
+    Ok(LastSnapshot {
+        path: snapshot_path.to_path_buf(),
+        manifest,
+        files,
+    })
+}
+
+/// Streams the entries of trees into chunk files named `{snapshot}.{n}.chunk`
+struct ChunkWriter<'a> {
+    dir: &'a Path,
+    prefix: &'a str,
+    count: usize,
+    buf: Vec<u8>,
+}
+
+impl<'a> ChunkWriter<'a> {
+    fn new(dir: &'a Path, prefix: &'a str) -> Self {
+        ChunkWriter {
+            dir,
+            prefix,
+            count: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Writes the entries that `tree` had when `view` was taken
+    fn write_tree(&mut self, tree_name: &[u8], tree: &Tree, view: &View) -> Result<Vec<Chunk>> {
+        let mut chunks = Vec::new();
+        for entry in tree.iter() {
+            let (key, value) = entry?;
+            if let Some(value) = view.resolve(tree_name, &key, value) {
+                self.push(&key, &value, &mut chunks)?;
+            }
+        }
+        for (key, value) in view.release(tree_name) {
+            self.push(&key, &value, &mut chunks)?;
+        }
+
+        if !self.buf.is_empty() {
+            chunks.push(self.flush()?);
+        }
+        Ok(chunks)
+    }
+
+    fn push(&mut self, key: &[u8], value: &[u8], chunks: &mut Vec<Chunk>) -> Result<()> {
+        write_record(&mut self.buf, key, value);
+        if self.buf.len() >= CHUNK_SIZE {
+            chunks.push(self.flush()?);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<Chunk> {
+        let file = format!("{}.{}.chunk", self.prefix, self.count);
+        fs::write(self.dir.join(&file), &self.buf)?;
+        let chunk = Chunk {
+            file,
+            len: self.buf.len() as u64,
+            crc: crc32fast::hash(&self.buf),
+        };
+        self.count += 1;
+        self.buf.clear();
+        Ok(chunk)
+    }
+}
+
+#[inline]
+fn write_record(buf: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    buf.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    buf.extend_from_slice(key);
+    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    buf.extend_from_slice(value);
+}
+
+#[inline]
+fn read_record<'a>(records: &mut &'a [u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let key = read_bytes(records)?;
+    let value = read_bytes(records)?;
+    Some((key, value))
+}
+
+#[inline]
+fn read_bytes<'a>(records: &mut &'a [u8]) -> Option<&'a [u8]> {
+    if records.len() < 4 {
+        return None;
+    }
+    let (len, rest) = records.split_at(4);
+    let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    if rest.len() < len {
+        return None;
+    }
+    let (bytes, rest) = rest.split_at(len);
+    *records = rest;
+    Some(bytes)
+}
+
+fn corrupt(path: &Path, line: &str) -> SledCorruptSnapshot<PathBuf, String> {
+    SledCorruptSnapshot {
+        path: path.to_path_buf(),
+        reason: format!("malformed manifest entry {:?}", line),
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -33,9 +33,8 @@ impl VecOps for Sled {
         fixed_bytes::serialize_into(&mut serialized, &1usize)?;
         protobuf::serialize_into(&mut serialized, &value)?;
 
-        let tree = self.tree(&handle.id)?;
         // See the vec_merge function in this module. It is set as the merge operator for every vec state.
-        tree.merge(key, serialized)?;
+        self.merge(&handle.id, &key, &serialized)?;
 
         Ok(())
     }
@@ -151,7 +150,7 @@ impl VecOps for Sled {
         // impl for Vec starts at the end and extends it, so we want the first one
         fixed_bytes::serialize_into(&mut serialized.as_mut_slice(), &len)?;
 
-        self.tree(&handle.id)?.merge(key, serialized)?;
+        self.merge(&handle.id, &key, &serialized)?;
 
         Ok(())
     }
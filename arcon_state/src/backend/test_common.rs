@@ -272,6 +272,15 @@ macro_rules! common_state_tests {
                 aggregator.aggregate(3).unwrap();
 
                 assert_eq!(aggregator.get().unwrap(), "[1, 2, 3]".to_string());
+
+                aggregator.merge(vec![4, 5]).unwrap();
+                assert_eq!(
+                    aggregator.get_accumulator().unwrap(),
+                    Some(vec![1, 2, 3, 4, 5])
+                );
+
+                aggregator.clear().unwrap();
+                assert_eq!(aggregator.get_accumulator().unwrap(), None);
             }
         }
     };
@@ -120,6 +120,13 @@ pub enum ArconStateError {
         source: ::sled::Error,
         backtrace: Backtrace,
     },
+    #[cfg(feature = "sled_checkpoints")]
+    #[snafu(display("Corrupt sled snapshot {}: {}", path.display(), reason))]
+    SledCorruptSnapshot {
+        path: PathBuf,
+        reason: String,
+        backtrace: Backtrace,
+    },
     #[snafu(display("Error : {}", msg))]
     Unknown { msg: String },
 }
@@ -8,7 +8,10 @@ use kompact::{
     prelude::{DeadletterBox, KompactConfig, NetworkConfig},
 };
 use serde::Deserialize;
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 /// Types of modes that `arcon` may run in
 #[derive(Deserialize, Clone, Debug)]
@@ -24,11 +27,29 @@ pub struct ArconConf {
     #[serde(default = "execution_mode_default")]
     pub execution_mode: ExecutionMode,
     /// Base directory for live state backend data
+    ///
+    /// Backends are identified by the state id of their operator, or by the position
+    /// of the operator in the dataflow graph, so it must not be shared with other pipelines.
     #[serde(default = "state_dir_default")]
     pub state_dir: PathBuf,
     /// Base directory for checkpoints
     #[serde(default = "checkpoint_dir_default")]
     pub checkpoint_dir: PathBuf,
+    /// Decides which checkpoints in `checkpoint_dir` are removed once an epoch is committed
+    #[serde(default)]
+    pub checkpoint_retention: RetentionPolicy,
+    /// Whether nodes align on epoch markers before they checkpoint
+    #[serde(default)]
+    pub checkpoint_mode: CheckpointMode,
+    /// Restore the pipeline from the last committed epoch in `checkpoint_dir` on startup
+    #[serde(default)]
+    pub recovery: bool,
+    /// Amount of threads that write checkpoints to `checkpoint_dir` off the control path
+    #[serde(default = "checkpoint_threads_default")]
+    pub checkpoint_threads: usize,
+    /// Time in milliseconds to wait for a triggered savepoint to be written
+    #[serde(default = "savepoint_timeout_default")]
+    pub savepoint_timeout: u64,
     /// Generation interval in milliseconds for Epochs
     #[serde(default = "epoch_interval_default")]
     pub epoch_interval: u64,
@@ -86,6 +107,11 @@ impl Default for ArconConf {
             execution_mode: execution_mode_default(),
             state_dir: state_dir_default(),
             checkpoint_dir: checkpoint_dir_default(),
+            checkpoint_retention: RetentionPolicy::default(),
+            checkpoint_mode: CheckpointMode::default(),
+            recovery: false,
+            checkpoint_threads: checkpoint_threads_default(),
+            savepoint_timeout: savepoint_timeout_default(),
             watermark_interval: watermark_interval_default(),
             epoch_interval: epoch_interval_default(),
             max_key: max_key_default(),
@@ -107,6 +133,65 @@ impl Default for ArconConf {
     }
 }
 
+/// Retention policy for the checkpoints of committed epochs
+///
+/// The checkpoints of the latest committed epoch are never removed,
+/// while all checkpoints are kept if neither limit is set.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RetentionPolicy {
+    /// Keep the checkpoints of at most the last `keep_last` committed epochs
+    #[serde(default)]
+    pub keep_last: Option<usize>,
+    /// Remove checkpoints that are older than `max_age` milliseconds
+    #[serde(default)]
+    pub max_age: Option<u64>,
+}
+
+/// How the nodes of a pipeline take part in a checkpoint
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CheckpointMode {
+    /// Nodes block the channels that have delivered an epoch marker until the
+    /// marker has arrived on all of them and checkpoint once they are aligned
+    Aligned,
+    /// Nodes checkpoint on the first epoch marker and forward it right away
+    ///
+    /// Messages that are received on the other channels before their marker
+    /// are persisted as part of the checkpoint and replayed on restore.
+    /// Operators with a managed parallelism require aligned checkpoints.
+    Unaligned,
+}
+
+impl Default for CheckpointMode {
+    fn default() -> Self {
+        CheckpointMode::Aligned
+    }
+}
+
+impl RetentionPolicy {
+    /// Returns true if the policy never removes any checkpoints
+    pub fn keeps_all(&self) -> bool {
+        self.keep_last.is_none() && self.max_age.is_none()
+    }
+
+    /// Returns the epochs whose checkpoints are superseded once `committed` has been committed
+    ///
+    /// `checkpoints` maps every checkpointed epoch to its age in milliseconds.
+    /// Epochs after `committed` are still in progress and are never superseded.
+    pub(crate) fn superseded(&self, committed: u64, checkpoints: &BTreeMap<u64, u64>) -> Vec<u64> {
+        checkpoints
+            .range(..committed)
+            .rev()
+            .enumerate()
+            .filter(|(newer, (_, age))| {
+                // `newer + 1` epochs, including `committed`, are kept ahead of this one
+                self.keep_last.map_or(false, |keep| newer + 1 >= keep)
+                    || self.max_age.map_or(false, |max_age| **age > max_age)
+            })
+            .map(|(_, (epoch, _))| *epoch)
+            .collect()
+    }
+}
+
 impl ArconConf {
     pub(crate) fn ctrl_system_conf(&self) -> KompactConfig {
         let mut cfg = KompactConfig::default();
@@ -225,6 +310,15 @@ fn buffer_pool_limit_default() -> usize {
     buffer_pool_size_default() * 2
 }
 
+fn checkpoint_threads_default() -> usize {
+    2
+}
+
+fn savepoint_timeout_default() -> u64 {
+    // in milliseconds
+    60000
+}
+
 fn channel_batch_size_default() -> usize {
     248
 }
@@ -299,5 +393,40 @@ mod tests {
         assert_eq!(conf.kompact_threads, kompact_threads_default());
         assert_eq!(conf.kompact_throughput, kompact_throughput_default());
         assert_eq!(conf.kompact_network_host, kompact_network_host_default());
+        assert!(conf.checkpoint_retention.keeps_all());
+    }
+
+    #[test]
+    fn retention_policy_test() {
+        // epoch -> age in milliseconds
+        let checkpoints: BTreeMap<u64, u64> =
+            vec![(1, 5000), (2, 4000), (3, 3000), (4, 2000), (5, 1000)]
+                .into_iter()
+                .collect();
+
+        let policy = RetentionPolicy::default();
+        assert!(policy.superseded(4, &checkpoints).is_empty());
+
+        let policy = RetentionPolicy {
+            keep_last: Some(2),
+            max_age: None,
+        };
+        // Epoch 5 is still in progress
+        assert_eq!(policy.superseded(4, &checkpoints), vec![2, 1]);
+
+        let policy = RetentionPolicy {
+            keep_last: Some(0),
+            max_age: None,
+        };
+        assert_eq!(policy.superseded(4, &checkpoints), vec![3, 2, 1]);
+
+        let policy = RetentionPolicy {
+            keep_last: None,
+            max_age: Some(3500),
+        };
+        assert_eq!(policy.superseded(4, &checkpoints), vec![2, 1]);
+
+        // The latest committed epoch is kept regardless of its age
+        assert_eq!(policy.superseded(1, &checkpoints), Vec::<u64>::new());
     }
 }
@@ -9,7 +9,7 @@ pub mod flight_serde;
 #[allow(dead_code)]
 pub mod partition;
 /// Known Serialisation IDs for Arcon Types
-mod ser_id;
+pub(crate) mod ser_id;
 
 use crate::buffer::event::BufferReader;
 #[cfg(feature = "unsafe_flight")]
@@ -99,6 +99,11 @@ pub enum ArconEvent<A: ArconType> {
     /// A death message
     #[prost(message, tag = "4")]
     Death(String),
+    /// Marks the sender as idle until it sends an element or watermark again
+    ///
+    /// Carries the last watermark of the sender.
+    #[prost(message, tag = "5")]
+    Idle(Watermark),
 }
 
 // The struct below is required because of the peculiarity of prost/protobuf - you cannot have
@@ -111,7 +116,7 @@ pub enum ArconEvent<A: ArconType> {
 #[derive(PMessage, Clone)]
 #[cfg_attr(feature = "arcon_serde", serde(bound = "A: ArconType"))]
 pub struct ArconEventWrapper<A: ArconType> {
-    #[prost(oneof = "ArconEvent::<A>", tags = "1, 2, 3, 4")]
+    #[prost(oneof = "ArconEvent::<A>", tags = "1, 2, 3, 4, 5")]
     inner: Option<ArconEvent<A>>,
 }
 
@@ -248,6 +253,15 @@ impl<A: ArconType> ArconMessage<A> {
             sender,
         }
     }
+    /// Creates an ArconMessage with a single [ArconEvent::Idle] event
+    ///
+    /// This function should only be used for development and test purposes.
+    pub fn idle(timestamp: u64, sender: NodeID) -> ArconMessage<A> {
+        ArconMessage {
+            events: vec![ArconEvent::<A>::Idle(Watermark { timestamp }).into()].into(),
+            sender,
+        }
+    }
     /// Creates an ArconMessage with a single [ArconEvent::Death] event
     ///
     /// This function should only be used for development and test purposes.
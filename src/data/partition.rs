@@ -1,8 +1,9 @@
 use super::NodeID;
 use prost::*;
+use std::sync::Mutex;
 
 /// Keyed state in Arcon is split into Regions.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Region {
     /// A Region may internally further split up the key ranges
     /// Example: ((0, KeyRange(0, 31), (1, KeyRange(32, 64), ....)
@@ -23,6 +24,33 @@ impl Region {
 
         Self { ranges }
     }
+
+    /// Returns the amount of nodes that the Region is split onto
+    pub fn parallelism(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns the node responsible for `key`
+    ///
+    /// The key is expected to be within the key space of the Region.
+    pub fn node_of(&self, key: u64) -> NodeID {
+        let index = match self
+            .ranges
+            .binary_search_by(|(_, range)| range.start.cmp(&key))
+        {
+            Ok(index) => index,
+            Err(index) => index.saturating_sub(1),
+        };
+        self.ranges[index].0
+    }
+
+    /// Returns the key range of `node` if it has one
+    pub fn range_of(&self, node: NodeID) -> Option<&KeyRange> {
+        self.ranges
+            .iter()
+            .find(|(id, _)| *id == node)
+            .map(|(_, range)| range)
+    }
 }
 
 /// A Key Range with a start and end position
@@ -42,4 +70,180 @@ impl KeyRange {
         assert!(start < end, "start range has to be smaller than end range");
         KeyRange { start, end }
     }
+
+    /// Returns true if `key` is within the range
+    pub fn contains(&self, key: u64) -> bool {
+        key >= self.start && key <= self.end
+    }
+}
+
+/// Key-range layout of the node-local state of an Operator
+///
+/// Recorded in the state backend of the Operator so that a checkpoint
+/// can be restored onto a different set of nodes.
+#[derive(Message, PartialEq, Clone)]
+pub struct StateLayout {
+    /// Amount of node instances that keep node-local state in the backend
+    #[prost(uint32)]
+    pub nodes: u32,
+    /// Amount of nodes that the key space is split onto
+    #[prost(uint32)]
+    pub parallelism: u32,
+    /// The highest possible key value
+    #[prost(uint64)]
+    pub max_key: u64,
+}
+
+impl StateLayout {
+    /// Creates the layout of `nodes` node instances that own the key ranges of `region`
+    pub fn new(nodes: usize, region: &Region, max_key: u64) -> Self {
+        StateLayout {
+            nodes: nodes as u32,
+            parallelism: region.parallelism() as u32,
+            max_key,
+        }
+    }
+
+    /// Returns true if no layout has been recorded
+    pub fn is_empty(&self) -> bool {
+        self.nodes == 0
+    }
+}
+
+/// The current [Region] of an Operator whose parallelism is managed by the runtime
+///
+/// It is shared between the NodeManager of the Operator and the Keyed
+/// channel strategies of its upstream nodes. A new Region takes effect right
+/// after the epoch marker it has been scheduled for, which makes every upstream
+/// node switch at the same point of the stream.
+#[derive(Debug)]
+pub struct KeyRouting {
+    /// The highest possible key value
+    max_key: u64,
+    state: Mutex<RoutingState>,
+}
+
+#[derive(Debug)]
+struct RoutingState {
+    /// Region that is currently used to route keys
+    active: Region,
+    /// Region that replaces the active one after the scheduled epoch
+    pending: Option<Region>,
+    /// Epoch after which the pending Region takes effect
+    epoch: Option<u64>,
+}
+
+impl KeyRouting {
+    /// Creates a KeyRouting that starts out with `region`
+    pub fn new(region: Region, max_key: u64) -> Self {
+        KeyRouting {
+            max_key,
+            state: Mutex::new(RoutingState {
+                active: region,
+                pending: None,
+                epoch: None,
+            }),
+        }
+    }
+
+    /// Returns the highest possible key value
+    pub fn max_key(&self) -> u64 {
+        self.max_key
+    }
+
+    /// Returns the Region that is currently in use
+    pub fn region(&self) -> Region {
+        self.state.lock().unwrap().active.clone()
+    }
+
+    /// Returns true if a new Region has been prepared but not yet committed
+    pub fn is_pending(&self) -> bool {
+        self.state.lock().unwrap().pending.is_some()
+    }
+
+    /// Prepares a Region that splits the key space onto `parallelism` nodes
+    ///
+    /// The Region is not used before it has been scheduled for an epoch.
+    pub(crate) fn prepare(&self, parallelism: usize) {
+        let mut state = self.state.lock().unwrap();
+        assert!(state.pending.is_none(), "A rebalance is already pending");
+        state.pending = Some(Region::new(parallelism, self.max_key as usize));
+    }
+
+    /// Schedules the prepared Region to take effect after the marker of `epoch`
+    ///
+    /// Must be called before the epoch is injected into the pipeline.
+    pub(crate) fn schedule(&self, epoch: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.pending.is_some() && state.epoch.is_none() {
+            state.epoch = Some(epoch);
+        }
+    }
+
+    /// Returns the epoch after which the prepared Region takes effect
+    pub(crate) fn scheduled_epoch(&self) -> Option<u64> {
+        self.state.lock().unwrap().epoch
+    }
+
+    /// Returns the Region to switch to if it changes after the marker of `epoch`
+    pub(crate) fn region_after(&self, epoch: u64) -> Option<Region> {
+        let state = self.state.lock().unwrap();
+        match state.epoch {
+            Some(scheduled) if scheduled == epoch => state.pending.clone(),
+            _ => None,
+        }
+    }
+
+    /// Makes the prepared Region the active one and returns it
+    pub(crate) fn commit(&self) -> Region {
+        let mut state = self.state.lock().unwrap();
+        if let Some(region) = state.pending.take() {
+            state.active = region;
+        }
+        state.epoch = None;
+        state.active.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_node_of_test() {
+        let max_key = 256;
+        let region = Region::new(3, max_key);
+        assert_eq!(region.parallelism(), 3);
+
+        for key in 0..max_key as u64 {
+            let node = region.node_of(key);
+            assert!(region.range_of(node).unwrap().contains(key));
+            // Matches the contiguous key ranges of the Keyed channel strategy
+            assert_eq!(node.id as u64, key * 3 / max_key as u64);
+        }
+    }
+
+    #[test]
+    fn key_routing_test() {
+        let routing = KeyRouting::new(Region::new(2, 128), 128);
+        assert!(!routing.is_pending());
+
+        routing.prepare(4);
+        assert!(routing.is_pending());
+        // Nothing changes before the rebalance has been scheduled
+        assert!(routing.region_after(1).is_none());
+
+        routing.schedule(3);
+        routing.schedule(4);
+        assert_eq!(routing.scheduled_epoch(), Some(3));
+        assert!(routing.region_after(2).is_none());
+        assert_eq!(routing.region_after(3).unwrap().parallelism(), 4);
+        assert_eq!(routing.region().parallelism(), 2);
+
+        let region = routing.commit();
+        assert_eq!(region.parallelism(), 4);
+        assert_eq!(routing.region().parallelism(), 4);
+        assert!(!routing.is_pending());
+        assert_eq!(routing.scheduled_epoch(), None);
+    }
 }
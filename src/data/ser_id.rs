@@ -34,3 +34,12 @@ pub const RELIABLE_STRING_ID: SerId = 63;
 #[cfg(feature = "unsafe_flight")]
 pub const UNSAFE_BOOLEAN_ID: SerId = 64;
 pub const RELIABLE_BOOLEAN_ID: SerId = 65;
+
+// Serialisation IDs for Arcon join types
+#[cfg(feature = "unsafe_flight")]
+pub const UNSAFE_JOIN_INPUT_ID: SerId = 66;
+pub const RELIABLE_JOIN_INPUT_ID: SerId = 67;
+
+#[cfg(feature = "unsafe_flight")]
+pub const UNSAFE_JOINED_ID: SerId = 68;
+pub const RELIABLE_JOINED_ID: SerId = 69;
@@ -4,7 +4,13 @@
 use crate::{
     data::{ArconType, StateID},
     index::{ArconState, EMPTY_STATE_ID},
-    stream::{operator::Operator, source::Source, time::ArconTime},
+    pipeline::RestorePoint,
+    stream::{
+        channel::strategy::Partitioner,
+        operator::Operator,
+        source::{ParallelSource, Source},
+        time::{ArconTime, WatermarkStrategy},
+    },
 };
 use hocon::HoconLoader;
 use serde::Deserialize;
@@ -27,7 +33,11 @@ pub enum ParallelismStrategy {
     /// Use a static number of Arcon nodes
     Static(usize),
     /// Tells the runtime to manage the parallelism
-    Managed,
+    ///
+    /// The runtime rebalances the key space of the Operator across a varying amount of
+    /// its nodes within the bounds of the [RebalancingPolicy]. The nodes themselves are
+    /// created up front and are never added or removed at runtime.
+    Managed(RebalancingPolicy),
 }
 
 impl Default for ParallelismStrategy {
@@ -37,6 +47,72 @@ impl Default for ParallelismStrategy {
     }
 }
 
+/// Defines how the runtime rebalances the key ranges of an Operator with [ParallelismStrategy::Managed]
+///
+/// The runtime creates `max_parallelism` nodes for the Operator and changes how many
+/// of them are assigned a key range. Nodes without a key range receive no elements,
+/// but still forward epochs and watermarks, as downstream nodes align on all of them.
+/// The load is observed through the inbound throughput that the nodes with a key range
+/// report every `node_metrics_interval` milliseconds.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RebalancingPolicy {
+    /// Amount of nodes with a key range that the Operator starts with and never goes below
+    #[serde(default = "min_parallelism_default")]
+    pub min_parallelism: usize,
+    /// Amount of nodes that are created, and thereby the most nodes with a key range
+    #[serde(default = "max_parallelism_default")]
+    pub max_parallelism: usize,
+    /// Assign a key range to one more node once the average inbound throughput (events/s) per node exceeds this value
+    #[serde(default = "scale_up_throughput_default")]
+    pub scale_up_throughput: f64,
+    /// Take the key range of one node once the average inbound throughput (events/s) per node falls below this value
+    #[serde(default = "scale_down_throughput_default")]
+    pub scale_down_throughput: f64,
+}
+
+impl Default for RebalancingPolicy {
+    fn default() -> Self {
+        RebalancingPolicy {
+            min_parallelism: min_parallelism_default(),
+            max_parallelism: max_parallelism_default(),
+            scale_up_throughput: scale_up_throughput_default(),
+            scale_down_throughput: scale_down_throughput_default(),
+        }
+    }
+}
+
+impl RebalancingPolicy {
+    /// Returns the amount of nodes with a key range given the current one and the total inbound throughput
+    ///
+    /// The amount changes by at most one node at a time.
+    pub(crate) fn target_parallelism(&self, current: usize, throughput: f64) -> usize {
+        let per_node = throughput / current as f64;
+        if per_node > self.scale_up_throughput && current < self.max_parallelism {
+            current + 1
+        } else if per_node < self.scale_down_throughput && current > self.min_parallelism {
+            current - 1
+        } else {
+            current
+        }
+    }
+}
+
+fn min_parallelism_default() -> usize {
+    1
+}
+
+fn max_parallelism_default() -> usize {
+    num_cpus::get()
+}
+
+fn scale_up_throughput_default() -> f64 {
+    100_000.0
+}
+
+fn scale_down_throughput_default() -> f64 {
+    10_000.0
+}
+
 /// Defines whether a stream is Keyed or Local
 ///
 /// Streams are by default Keyed in Arcon.
@@ -52,6 +128,45 @@ impl Default for StreamKind {
     }
 }
 
+/// Defines how the output of upstream nodes is partitioned
+/// onto the nodes of an Operator.
+///
+/// Elements are by default partitioned by key.
+#[derive(Deserialize, Clone)]
+pub enum Partitioning {
+    /// Node `i` of the upstream operator sends all its output to node `i` of this operator
+    ///
+    /// Requires the upstream operator to have the same parallelism.
+    Forward,
+    /// Distribute batches of elements in a round-robin fashion
+    Rebalance,
+    /// Send every element to all nodes
+    Broadcast,
+    /// Partition elements by their key
+    Keyed,
+    /// Partition elements by their key using a user-defined [Partitioner]
+    #[serde(skip)]
+    Custom(Arc<dyn Partitioner>),
+}
+
+impl Default for Partitioning {
+    fn default() -> Self {
+        Partitioning::Keyed
+    }
+}
+
+impl std::fmt::Debug for Partitioning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Partitioning::Forward => write!(f, "Forward"),
+            Partitioning::Rebalance => write!(f, "Rebalance"),
+            Partitioning::Broadcast => write!(f, "Broadcast"),
+            Partitioning::Keyed => write!(f, "Keyed"),
+            Partitioning::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
 /// Operator Configuration
 ///
 /// Defines how an Operator is to be executed on Arcon.
@@ -61,6 +176,9 @@ pub struct OperatorConf {
     pub parallelism_strategy: ParallelismStrategy,
     /// Defines the type of Stream, by default streams are Keyed in Arcon.
     pub stream_kind: StreamKind,
+    /// Defines how incoming elements are partitioned onto the nodes of the Operator
+    #[serde(default)]
+    pub partitioning: Partitioning,
 }
 
 impl OperatorConf {
@@ -93,12 +211,27 @@ impl<OP: Operator, Backend: arcon_state::Backend> OperatorBuilder<OP, Backend> {
         Arc::new(Backend::create(&state_dir).unwrap())
     }
 
-    pub(crate) fn state_id(&self) -> StateID {
+    pub(crate) fn restore_backend(
+        &self,
+        state_dir: &Path,
+        state_id: &str,
+        restore_point: &RestorePoint,
+    ) -> Arc<Backend> {
+        let backend = restore_point
+            .restore_backend(state_dir, state_id)
+            .expect("Failed to restore Backend");
+        Arc::new(backend)
+    }
+
+    /// Returns the state id of the Operator
+    ///
+    /// Operators without state get an id derived from their `position` in the
+    /// dataflow graph, so that a restored pipeline finds their checkpoints.
+    pub(crate) fn state_id(&self, position: usize) -> StateID {
         let mut state_id = OP::OperatorState::STATE_ID.to_owned();
         if state_id == EMPTY_STATE_ID {
-            // create unique identifier so there is no clash between empty states
-            let unique_id = uuid::Uuid::new_v4().to_string();
-            state_id = format!("{}_{}", state_id, unique_id);
+            // no clash between empty states
+            state_id = format!("{}_{}", state_id, position);
         }
         state_id
     }
@@ -111,6 +244,9 @@ pub type TimestampExtractor<A> = Arc<dyn Fn(&A) -> u64 + Send + Sync>;
 pub struct SourceConf<S: ArconType> {
     pub extractor: Option<TimestampExtractor<S>>,
     pub time: ArconTime,
+    pub watermark_strategy: WatermarkStrategy,
+    /// Time in milliseconds without any output after which the Source is considered idle
+    pub idle_timeout: Option<u64>,
 }
 
 impl<S: ArconType> SourceConf<S> {
@@ -122,6 +258,29 @@ impl<S: ArconType> SourceConf<S> {
     pub fn set_timestamp_extractor(&mut self, f: impl Fn(&S) -> u64 + Send + Sync + 'static) {
         self.extractor = Some(Arc::new(f));
     }
+    /// Set the [WatermarkStrategy] of a Source that uses [ArconTime::Event]
+    pub fn set_watermark_strategy(&mut self, strategy: WatermarkStrategy) {
+        self.watermark_strategy = strategy;
+    }
+    /// Mark a Source as idle after `timeout` milliseconds without any output
+    ///
+    /// Idle sources do not hold back the watermark of downstream nodes until they output again.
+    /// Has no effect on sources whose watermarks follow the system time.
+    pub fn set_idle_timeout(&mut self, timeout: u64) {
+        self.idle_timeout = Some(timeout);
+    }
+
+    /// Returns true if records are timestamped by the Source itself
+    #[inline]
+    pub(crate) fn ingestion_time(&self) -> bool {
+        self.time == ArconTime::Event && self.watermark_strategy == WatermarkStrategy::IngestionTime
+    }
+
+    /// Returns true if records must be timestamped by the extractor
+    #[inline]
+    pub(crate) fn requires_extractor(&self) -> bool {
+        self.time == ArconTime::Event && !self.ingestion_time()
+    }
 }
 
 impl<S: ArconType> Default for SourceConf<S> {
@@ -129,6 +288,8 @@ impl<S: ArconType> Default for SourceConf<S> {
         Self {
             extractor: None,
             time: Default::default(),
+            watermark_strategy: Default::default(),
+            idle_timeout: None,
         }
     }
 }
@@ -143,3 +304,38 @@ pub struct SourceBuilder<S: Source, Backend = DefaultBackend> {
     /// Source Config
     pub conf: SourceConf<S::Data>,
 }
+
+/// Parallel Source Builder
+///
+/// Defines how a [ParallelSource] is constructed and by how many
+/// Source components its splits are read during runtime.
+#[derive(Clone)]
+pub struct ParallelSourceBuilder<P: ParallelSource, Backend = DefaultBackend> {
+    /// ParallelSource Constructor
+    pub constructor: Arc<dyn Fn(Arc<Backend>) -> P + Send + Sync + 'static>,
+    /// Source Config
+    pub conf: SourceConf<<P::Reader as Source>::Data>,
+    /// Number of Source components that read the splits
+    pub parallelism: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebalancing_policy_test() {
+        let policy = RebalancingPolicy {
+            min_parallelism: 1,
+            max_parallelism: 4,
+            scale_up_throughput: 1000.0,
+            scale_down_throughput: 100.0,
+        };
+
+        assert_eq!(policy.target_parallelism(2, 3000.0), 3);
+        assert_eq!(policy.target_parallelism(4, 8000.0), 4);
+        assert_eq!(policy.target_parallelism(2, 1000.0), 2);
+        assert_eq!(policy.target_parallelism(2, 100.0), 1);
+        assert_eq!(policy.target_parallelism(1, 0.0), 1);
+    }
+}
@@ -3,28 +3,38 @@
 
 use crate::{
     buffer::event::PoolInfo,
-    data::{ArconMessage, ArconType, NodeID},
+    conf::CheckpointMode,
+    data::{
+        partition::{KeyRouting, Region},
+        ArconMessage, ArconType, Epoch, NodeID,
+    },
     dataflow::{
-        conf::{OperatorBuilder, ParallelismStrategy, SourceBuilder},
+        conf::{
+            OperatorBuilder, ParallelSourceBuilder, ParallelismStrategy, SourceBuilder, SourceConf,
+        },
         dfg::ChannelKind,
     },
     manager::{
-        node::{NodeManager, NodeManagerPort},
-        source::{SourceManager, SourceManagerPort},
+        node::{NodeManager, NodeManagerPort, Rebalancing},
+        source::{assign_splits, source_positions, SourceManager, SourceManagerPort},
     },
     pipeline::Pipeline,
     stream::{
         channel::{
-            strategy::{forward::Forward, keyed::Keyed, *},
+            strategy::{
+                broadcast::Broadcast, forward::Forward, keyed::Keyed, round_robin::RoundRobin, *,
+            },
             Channel,
         },
         node::{
             source::{SourceEvent, SourceNode},
             Node, NodeState,
         },
-        operator::Operator,
-        source::Source,
-        time::ArconTime,
+        operator::{
+            side_output::{SideOutput, SideOutputs},
+            Operator,
+        },
+        source::{ParallelSource, Source},
     },
 };
 use arcon_state::Backend;
@@ -37,133 +47,295 @@ use kompact::{
 };
 use std::{any::Any, convert::TryInto, sync::Arc};
 
-pub type SourceManagerConstructor = Box<
-    dyn FnOnce(Vec<Arc<dyn Any + Send + Sync>>, ChannelKind, &mut Pipeline) -> ErasedSourceManager,
->;
-pub type SourceConstructor = Box<
+pub type SourceManagerConstructor =
+    Box<dyn FnOnce(String, Vec<ChannelTarget>, &mut Pipeline) -> ErasedSourceManager>;
+pub type ErasedSourceManager = Arc<dyn AbstractComponent<Message = SourceEvent>>;
+pub type NodeManagerConstructor = Box<
     dyn FnOnce(
-        Vec<Arc<dyn Any + Send + Sync>>,
-        ChannelKind,
-        &mut KompactSystem,
-    ) -> Arc<dyn AbstractComponent<Message = SourceEvent>>,
+        Vec<NodeID>,
+        Vec<ChannelTarget>,
+        Vec<SideOutputTarget>,
+        &mut Pipeline,
+    ) -> ErasedComponents,
 >;
-pub type ErasedSourceManager = Arc<dyn AbstractComponent<Message = SourceEvent>>;
-pub type NodeManagerConstructor =
-    Box<dyn FnOnce(Vec<NodeID>, ErasedComponents, ChannelKind, &mut Pipeline) -> ErasedComponents>;
+pub type SideOutputConstructor = Arc<dyn Fn(Vec<ChannelTarget>, u32, PoolInfo, u64) -> SideOutput>;
 
 pub type ErasedComponent = Arc<dyn Any + Send + Sync>;
 pub type ErasedComponents = Vec<ErasedComponent>;
 
+/// A group of downstream components that a node sends its output to
+#[derive(Clone)]
+pub struct ChannelTarget {
+    /// Erased components of the downstream node group
+    pub components: ErasedComponents,
+    /// How events are partitioned across the components
+    pub channel_kind: ChannelKind,
+    /// Offset added to the sender ids so that the downstream nodes
+    /// can tell their incoming channels apart
+    pub sender_offset: u32,
+}
+
+/// A side output of a node group together with its downstream targets
+#[derive(Clone)]
+pub struct SideOutputTarget {
+    /// Id of the [OutputTag](crate::stream::operator::side_output::OutputTag)
+    pub tag: String,
+    /// Constructor of the typed side output
+    pub constructor: SideOutputConstructor,
+    /// Downstream node groups that consume the side output
+    pub targets: Vec<ChannelTarget>,
+}
+
+/// Creates a [ChannelStrategy] for the node with the given local id
+///
+/// If there is more than one target, each batch is delivered to all of them.
 fn channel_strategy<OUT: ArconType>(
-    mut components: ErasedComponents,
-    node_id: NodeID,
+    targets: Vec<ChannelTarget>,
+    node_id: u32,
+    pool_info: PoolInfo,
+    max_key: u64,
+) -> ChannelStrategy<OUT> {
+    let mut strategies: Vec<ChannelStrategy<OUT>> = targets
+        .into_iter()
+        .map(|target| {
+            target_strategy(
+                target.components,
+                node_id,
+                NodeID::new(target.sender_offset + node_id),
+                pool_info.clone(),
+                max_key,
+                target.channel_kind,
+            )
+        })
+        .collect();
+
+    if strategies.len() == 1 {
+        strategies.remove(0)
+    } else {
+        ChannelStrategy::Fanout(strategies)
+    }
+}
+
+fn target_strategy<OUT: ArconType>(
+    components: ErasedComponents,
+    node_index: u32,
+    sender_id: NodeID,
     pool_info: PoolInfo,
     max_key: u64,
     channel_kind: ChannelKind,
 ) -> ChannelStrategy<OUT> {
-    match channel_kind {
-        ChannelKind::Forward => {
-            assert_eq!(components.len(), 1, "Expected a single component target");
-            let target_node = components
-                .remove(0)
+    let mut channels: Vec<Channel<OUT>> = components
+        .into_iter()
+        .map(|component| {
+            let target_node = component
                 .downcast::<Arc<dyn AbstractComponent<Message = ArconMessage<OUT>>>>()
                 .unwrap();
             let actor_ref = target_node.actor_ref().hold().expect("failed to fetch");
-            ChannelStrategy::Forward(Forward::new(Channel::Local(actor_ref), node_id, pool_info))
+            Channel::Local(actor_ref)
+        })
+        .collect();
+
+    match channel_kind {
+        ChannelKind::Forward => {
+            // Node i of the upstream operator is connected to node i of the downstream operator
+            let index = node_index as usize % channels.len();
+            let channel = channels.swap_remove(index);
+            ChannelStrategy::Forward(Forward::new(channel, sender_id, pool_info))
+        }
+        ChannelKind::Broadcast | ChannelKind::RoundRobin if channels.len() == 1 => {
+            ChannelStrategy::Forward(Forward::new(channels.remove(0), sender_id, pool_info))
+        }
+        ChannelKind::Broadcast => {
+            ChannelStrategy::Broadcast(Broadcast::new(channels, sender_id, pool_info))
+        }
+        ChannelKind::RoundRobin => {
+            ChannelStrategy::RoundRobin(RoundRobin::new(channels, sender_id, pool_info))
         }
         ChannelKind::Keyed => {
-            let mut channels = Vec::new();
-            for component in components {
-                let target_node = component
-                    .downcast::<Arc<dyn AbstractComponent<Message = ArconMessage<OUT>>>>()
-                    .unwrap();
-                let actor_ref = target_node.actor_ref().hold().expect("failed to fetch");
-                let channel = Channel::Local(actor_ref);
-                channels.push(channel);
-            }
-            ChannelStrategy::Keyed(Keyed::new(max_key, channels, node_id, pool_info))
+            ChannelStrategy::Keyed(Keyed::new(max_key, channels, sender_id, pool_info))
+        }
+        ChannelKind::Custom(partitioner) => ChannelStrategy::Keyed(Keyed::with_partitioner(
+            partitioner,
+            max_key,
+            channels,
+            sender_id,
+            pool_info,
+        )),
+        ChannelKind::Routed(routing) => {
+            ChannelStrategy::Keyed(Keyed::with_routing(routing, channels, sender_id, pool_info))
         }
         ChannelKind::Console => ChannelStrategy::Console,
         ChannelKind::Mute => ChannelStrategy::Mute,
-        _ => unimplemented!(),
     }
 }
 
+pub(crate) fn side_output_constructor<T: ArconType>() -> SideOutputConstructor {
+    Arc::new(
+        |targets: Vec<ChannelTarget>, node_id: u32, pool_info: PoolInfo, max_key: u64| {
+            // Local references used to forward control events to the downstream nodes
+            let mut refs = Vec::new();
+            for target in &targets {
+                let sender = NodeID::new(target.sender_offset + node_id);
+                for component in &target.components {
+                    let target_node = component
+                        .clone()
+                        .downcast::<Arc<dyn AbstractComponent<Message = ArconMessage<T>>>>()
+                        .unwrap();
+                    let actor_ref = target_node.actor_ref().hold().expect("failed to fetch");
+                    refs.push((actor_ref, sender));
+                }
+            }
+            let strategy = channel_strategy::<T>(targets, node_id, pool_info, max_key);
+            SideOutput::new(strategy, refs)
+        },
+    )
+}
+
 pub(crate) fn source_manager_constructor<S: Source + 'static, B: Backend>(
-    descriptor: String,
     builder: SourceBuilder<S, B>,
-    backend: Arc<B>,
     watermark_interval: u64,
-    time: ArconTime,
 ) -> SourceManagerConstructor {
     Box::new(
-        move |components: Vec<Arc<dyn std::any::Any + Send + Sync>>,
-              channel_kind: ChannelKind,
-              pipeline: &mut Pipeline| {
-            let epoch_manager_ref = pipeline.epoch_manager();
-
-            // TODO: Clean up and handle multiple source components!
-            let source_cons = builder.constructor;
-            let source = source_cons(backend.clone());
-            let pool_info = pipeline.get_pool_info();
-            let max_key = pipeline.conf.max_key;
-            let channel_strategy = channel_strategy(
-                components.clone(),
-                NodeID::new(0),
-                pool_info,
-                max_key,
-                channel_kind,
-            );
-            let source_node = SourceNode::new(source, channel_strategy);
-            let source_node_comp = pipeline.data_system().create(|| source_node);
-
-            pipeline
-                .data_system()
-                .start_notify(&source_node_comp)
-                .wait_timeout(std::time::Duration::from_millis(2000))
-                .expect("");
-
-            let manager = SourceManager::new(
+        move |descriptor: String, targets: Vec<ChannelTarget>, pipeline: &mut Pipeline| {
+            let backend = source_backend::<B>(&descriptor, pipeline);
+            let source = (builder.constructor)(backend.clone());
+            create_source_manager(
                 descriptor,
-                time,
+                vec![source],
+                targets,
                 watermark_interval,
-                epoch_manager_ref,
+                &builder.conf,
                 backend,
-            );
-            let source_manager_comp = pipeline.ctrl_system().create(|| manager);
+                pipeline,
+            )
+        },
+    )
+}
 
-            biconnect_components::<SourceManagerPort, _, _>(
-                &source_manager_comp,
-                &source_node_comp,
+pub(crate) fn parallel_source_manager_constructor<P: ParallelSource, B: Backend>(
+    builder: ParallelSourceBuilder<P, B>,
+    watermark_interval: u64,
+) -> SourceManagerConstructor {
+    Box::new(
+        move |descriptor: String, targets: Vec<ChannelTarget>, pipeline: &mut Pipeline| {
+            let backend = source_backend::<B>(&descriptor, pipeline);
+            let parallel_source = (builder.constructor)(backend.clone());
+
+            // Assign the splits of the input to the Source components
+            let sources: Vec<P::Reader> =
+                assign_splits(parallel_source.splits(), builder.parallelism)
+                    .into_iter()
+                    .map(|splits| parallel_source.reader(splits))
+                    .collect();
+
+            create_source_manager(
+                descriptor,
+                sources,
+                targets,
+                watermark_interval,
+                &builder.conf,
+                backend,
+                pipeline,
             )
+        },
+    )
+}
+
+// Set up directory for the SourceManager and create or restore its Backend
+fn source_backend<B: Backend>(descriptor: &str, pipeline: &mut Pipeline) -> Arc<B> {
+    let state_dir = pipeline.arcon_conf().state_dir.clone();
+    let backend = match &pipeline.restore_point {
+        Some(restore_point) => restore_point
+            .restore_backend(&state_dir, descriptor)
+            .expect("Failed to restore SourceManager Backend"),
+        None => B::create(&state_dir.join(descriptor)).unwrap(),
+    };
+    Arc::new(backend)
+}
+
+// Creates a SourceManager that drives one SourceNode per Source
+fn create_source_manager<S: Source + 'static, B: Backend>(
+    descriptor: String,
+    sources: Vec<S>,
+    targets: Vec<ChannelTarget>,
+    watermark_interval: u64,
+    conf: &SourceConf<S::Data>,
+    backend: Arc<B>,
+    pipeline: &mut Pipeline,
+) -> ErasedSourceManager {
+    let epoch_manager_ref = pipeline.epoch_manager();
+    let positions = source_positions(backend.clone());
+    let manager = SourceManager::new(
+        descriptor,
+        conf.time,
+        watermark_interval,
+        epoch_manager_ref,
+        backend,
+    );
+    let source_manager_comp = pipeline.ctrl_system().create(|| manager);
+
+    let pool_info = pipeline.get_pool_info();
+    let max_key = pipeline.conf.max_key;
+
+    for (index, mut source) in sources.into_iter().enumerate() {
+        // Continue where the restored epoch ended
+        if let Some(position) = positions
+            .get(&(index as u64))
+            .expect("Failed to read Source position")
+        {
+            source.seek(position);
+        }
+
+        // Source i sends with sender id `sender_offset + i` to its targets
+        let channel_strategy = channel_strategy(
+            targets.clone(),
+            index.try_into().unwrap(),
+            pool_info.clone(),
+            max_key,
+        );
+        let source_node = SourceNode::new(
+            index,
+            source,
+            channel_strategy,
+            conf.watermark_strategy,
+            conf.idle_timeout,
+        );
+        let source_node_comp = pipeline.data_system().create(|| source_node);
+
+        biconnect_components::<SourceManagerPort, _, _>(&source_manager_comp, &source_node_comp)
             .expect("failed to biconnect components");
 
-            let source_node_comp_dyn: Arc<dyn AbstractComponent<Message = SourceEvent>> =
-                source_node_comp;
+        pipeline
+            .data_system()
+            .start_notify(&source_node_comp)
+            .wait_timeout(std::time::Duration::from_millis(2000))
+            .expect("Failed to start SourceNode");
 
-            source_manager_comp.on_definition(|cd| {
-                cd.add_source(source_node_comp_dyn);
-            });
+        let source_node_comp_dyn: Arc<dyn AbstractComponent<Message = SourceEvent>> =
+            source_node_comp;
 
-            let source_ref: ActorRefStrong<SourceEvent> =
-                source_manager_comp.actor_ref().hold().expect("fail");
+        source_manager_comp.on_definition(|cd| {
+            cd.add_source(source_node_comp_dyn);
+        });
+    }
 
-            // Set source reference at the EpochManager
-            if let Some(epoch_manager) = &pipeline.epoch_manager {
-                epoch_manager.on_definition(|cd| {
-                    cd.source_manager = Some(source_ref);
-                });
-            }
+    let source_ref: ActorRefStrong<SourceEvent> =
+        source_manager_comp.actor_ref().hold().expect("fail");
 
-            pipeline
-                .ctrl_system()
-                .start_notify(&source_manager_comp)
-                .wait_timeout(std::time::Duration::from_millis(2000))
-                .expect("Failed to start SourceManager");
+    // Add source reference to the EpochManager
+    if let Some(epoch_manager) = &pipeline.epoch_manager {
+        epoch_manager.on_definition(|cd| {
+            cd.source_managers.push(source_ref);
+        });
+    }
 
-            source_manager_comp
-        },
-    )
+    pipeline
+        .ctrl_system()
+        .start_notify(&source_manager_comp)
+        .wait_timeout(std::time::Duration::from_millis(2000))
+        .expect("Failed to start SourceManager");
+
+    source_manager_comp
 }
 
 pub(crate) fn node_manager_constructor<OP: Operator + 'static, B: Backend>(
@@ -171,30 +343,54 @@ pub(crate) fn node_manager_constructor<OP: Operator + 'static, B: Backend>(
     data_system: KompactSystem,
     builder: OperatorBuilder<OP, B>,
     backend: Arc<B>,
+    routing: Option<Arc<KeyRouting>>,
 ) -> NodeManagerConstructor {
     Box::new(
         move |in_channels: Vec<NodeID>,
-              components: ErasedComponents,
-              channel_kind: ChannelKind,
+              targets: Vec<ChannelTarget>,
+              side_targets: Vec<SideOutputTarget>,
               pipeline: &mut Pipeline| {
             let epoch_manager_ref = pipeline.epoch_manager();
+            let start_epoch = pipeline.start_epoch();
 
             // How many instances of this Operator we are initially creating
-            let instances = match builder.conf.parallelism_strategy {
-                ParallelismStrategy::Static(s) => s,
-                _ => panic!("Managed ParallelismStrategy not supported yet"),
+            let instances = match &builder.conf.parallelism_strategy {
+                ParallelismStrategy::Static(s) => *s,
+                // Nodes outside of the current key ranges stay idle until they are needed
+                ParallelismStrategy::Managed(policy) => policy.max_parallelism,
             };
 
             let max_key = pipeline.conf.max_key as usize;
+            let checkpoint_mode = pipeline.conf.checkpoint_mode;
+
+            // Key ranges are only rebalanced once the nodes are aligned on an epoch
+            if let ParallelismStrategy::Managed(_) = &builder.conf.parallelism_strategy {
+                assert_eq!(
+                    checkpoint_mode,
+                    CheckpointMode::Aligned,
+                    "Operators with a managed parallelism require aligned checkpoints"
+                );
+            }
 
             // Define the NodeManager
-            let manager = NodeManager::<OP, B>::new(
+            let mut manager = NodeManager::<OP, B>::new(
                 descriptor.clone(),
                 data_system,
                 epoch_manager_ref,
                 in_channels.clone(),
                 backend.clone(),
-            );
+            )
+            .with_epoch(start_epoch)
+            .with_checkpoint_pool(pipeline.checkpoint_pool.clone())
+            .with_checkpoint_mode(checkpoint_mode);
+
+            if let (ParallelismStrategy::Managed(policy), Some(routing)) =
+                (&builder.conf.parallelism_strategy, &routing)
+            {
+                let rebalancing =
+                    Rebalancing::new(policy.clone(), routing.clone(), builder.constructor.clone());
+                manager = manager.with_rebalancing(rebalancing);
+            }
 
             // Create the actual NodeManager component
             let manager_comp = pipeline.ctrl_system().create(|| manager);
@@ -230,46 +426,95 @@ pub(crate) fn node_manager_constructor<OP: Operator + 'static, B: Backend>(
                 let node_descriptor = format!("{}_{}", descriptor, curr_node_id);
                 let node_id = NodeID::new(curr_node_id.try_into().unwrap());
 
-                let node = Node::new(
+                let mut side_outputs = SideOutputs::default();
+                for side in &side_targets {
+                    let side_output = (side.constructor)(
+                        side.targets.clone(),
+                        node_id.id,
+                        pool_info.clone(),
+                        max_key as u64,
+                    );
+                    side_outputs.insert(side.tag.clone(), side_output);
+                }
+
+                let mut node = Node::new(
                     node_descriptor,
                     channel_strategy(
-                        components.clone(),
-                        node_id,
+                        targets.clone(),
+                        node_id.id,
                         pool_info.clone(),
                         max_key as u64,
-                        channel_kind,
                     ),
                     operator(backend.clone()),
-                    NodeState::new(node_id, in_channels.clone(), backend.clone()),
+                    NodeState::new(node_id, in_channels.clone(), backend.clone())
+                        .with_epoch(start_epoch),
                     backend.clone(),
-                );
+                )
+                .expect("Failed to create Node")
+                .with_side_outputs(side_outputs)
+                .with_checkpoint_mode(checkpoint_mode);
+
+                if let ParallelismStrategy::Managed(_) = &builder.conf.parallelism_strategy {
+                    node = node.with_throughput_reporting();
+                }
 
                 let node_comp = pipeline.data_system().create(|| node);
                 let required_ref: RequiredRef<NodeManagerPort> = node_comp.required_ref();
                 biconnect_components::<NodeManagerPort, _, _>(&manager_comp, &node_comp)
                     .expect("fail");
 
-                let node_comp: Arc<dyn AbstractComponent<Message = ArconMessage<OP::IN>>> =
-                    node_comp;
+                manager_comp.on_definition(|cd| {
+                    // Insert the created Node into the NodeManager
+                    cd.nodes.insert(node_id, (node_comp, required_ref));
+                });
+            }
+
+            // Split or merge the node-local state if the backend was restored
+            // from a checkpoint that was taken with another key-range layout
+            let region = match &routing {
+                Some(routing) => routing.region(),
+                None => Region::new(instances, max_key),
+            };
+            manager_comp
+                .on_definition(|cd| cd.restore_layout(region, max_key as u64))
+                .expect("Failed to restore the state layout of the NodeManager");
+
+            if let Some(restore_point) = &pipeline.restore_point {
+                manager_comp
+                    .on_definition(|cd| cd.replay_commit(Epoch::new(restore_point.epoch())))
+                    .expect("Failed to replay the commit of the restored epoch");
+
+                // Replay the messages that were in flight when the restored epoch was checkpointed
+                let in_flight = restore_point.in_flight_path(&descriptor);
+                manager_comp
+                    .on_definition(|cd| cd.replay_in_flight(&in_flight))
+                    .expect("Failed to replay the in-flight messages of the NodeManager");
+            }
 
+            // Start the Nodes once their restored state is in place
+            let node_comps: Vec<_> = manager_comp
+                .on_definition(|cd| cd.nodes.values().map(|(c, _)| c.clone()).collect());
+            for node_comp in node_comps {
                 pipeline
                     .data_system()
                     .start_notify(&node_comp)
                     .wait_timeout(std::time::Duration::from_millis(2000))
                     .expect("Failed to start Node Component");
-
-                manager_comp.on_definition(|cd| {
-                    // Insert the created Node into the NodeManager
-                    cd.nodes.insert(node_id, (node_comp, required_ref));
-                });
             }
 
             // Fetch all created Nodes on this NodeManager and return them as Erased
             // for the next stage..
+            // Ordered by NodeID so that every upstream node maps partitions to the same nodes
             let nodes: ErasedComponents = manager_comp.on_definition(|cd| {
-                cd.nodes
-                    .values()
-                    .map(|(comp, _)| Arc::new(comp.clone()) as ErasedComponent)
+                let mut nodes: Vec<_> = cd.nodes.iter().collect();
+                nodes.sort_by_key(|(id, _)| **id);
+                nodes
+                    .into_iter()
+                    .map(|(_, (comp, _))| {
+                        let comp: Arc<dyn AbstractComponent<Message = ArconMessage<OP::IN>>> =
+                            comp.clone();
+                        Arc::new(comp) as ErasedComponent
+                    })
                     .collect()
             });
 
@@ -1,4 +1,6 @@
-use super::constructor::*;
+use super::{conf::Partitioning, constructor::*};
+use crate::{data::partition::KeyRouting, stream::channel::strategy::Partitioner};
+use std::sync::Arc;
 
 /// A logical dataflow-graph.
 #[allow(dead_code)]
@@ -29,12 +31,89 @@ impl DFG {
     pub fn get_mut(&mut self, id: &DFGNodeID) -> &mut DFGNode {
         self.graph.get_mut(id.0).unwrap()
     }
+
+    /// Returns the number of nodes in the dataflow graph.
+    pub fn len(&self) -> usize {
+        self.graph.len()
+    }
+
+    /// Returns `true` if the dataflow graph contains no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.graph.is_empty()
+    }
+
+    /// Appends all nodes of `other` into this graph.
+    ///
+    /// Returns the offset that has been added to the [`DFGNodeID`]s of `other`.
+    pub fn merge(&mut self, other: DFG) -> usize {
+        let offset = self.graph.len();
+        for mut node in other.graph {
+            for output in node.ingoing.iter_mut() {
+                output.node.0 += offset;
+            }
+            self.graph.push(node);
+        }
+        offset
+    }
+
+    /// Returns the outgoing edges of every node in the graph.
+    pub fn outgoing_edges(&self) -> Vec<Vec<DFGEdge>> {
+        let mut edges = vec![Vec::new(); self.graph.len()];
+        for (index, node) in self.graph.iter().enumerate() {
+            let mut sender_offset = 0;
+            for upstream in &node.ingoing {
+                edges[upstream.node.0].push(DFGEdge {
+                    target: DFGNodeID(index),
+                    tag: upstream.tag.clone(),
+                    sender_offset,
+                });
+                sender_offset += self.get(&upstream.node).outgoing_channels as u32;
+            }
+        }
+        edges
+    }
+}
+
+/// An edge between two nodes in the dataflow graph.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DFGEdge {
+    /// The downstream node of the edge
+    pub(crate) target: DFGNodeID,
+    /// The side output the edge originates from, or `None` for the main output
+    pub(crate) tag: Option<String>,
+    /// The offset at which the sender ids of the upstream node start
+    /// within the incoming channels of the downstream node
+    pub(crate) sender_offset: u32,
 }
 
 /// The ID of a [`DFGNode`] in the dataflow graph.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct DFGNodeID(pub usize);
 
+/// One of the outputs of a [`DFGNode`].
+///
+/// Every node has a main output and may in addition have tagged side outputs.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DFGOutput {
+    pub(crate) node: DFGNodeID,
+    pub(crate) tag: Option<String>,
+}
+
+impl DFGOutput {
+    /// The main output of a node
+    pub fn main(node: DFGNodeID) -> Self {
+        Self { node, tag: None }
+    }
+
+    /// The side output identified by `tag` of a node
+    pub fn side(node: DFGNodeID, tag: impl Into<String>) -> Self {
+        Self {
+            node,
+            tag: Some(tag.into()),
+        }
+    }
+}
+
 /// A logical node in the dataflow graph.
 #[allow(dead_code)]
 pub struct DFGNode {
@@ -42,8 +121,11 @@ pub struct DFGNode {
     pub(crate) outgoing_channels: usize,
     pub(crate) ingoing_channels: usize,
     /// Ingoing edges to a node.
-    pub(crate) ingoing: Vec<DFGNodeID>,
+    pub(crate) ingoing: Vec<DFGOutput>,
+    /// How the output of the ingoing nodes is partitioned onto this node.
     pub(crate) channel_kind: ChannelKind,
+    /// Side outputs of the node that are consumed by other nodes.
+    pub(crate) side_outputs: Vec<(String, SideOutputConstructor)>,
 }
 
 impl DFGNode {
@@ -51,7 +133,7 @@ impl DFGNode {
         kind: DFGNodeKind,
         outgoing_channels: usize,
         ingoing_channels: usize,
-        ingoing: Vec<DFGNodeID>,
+        ingoing: Vec<DFGOutput>,
     ) -> Self {
         Self {
             kind,
@@ -59,32 +141,41 @@ impl DFGNode {
             ingoing_channels,
             ingoing,
             channel_kind: Default::default(),
+            side_outputs: Vec::new(),
         }
     }
 }
 
 pub enum DFGNodeKind {
-    Source(ChannelKind, SourceManagerConstructor),
+    Source(SourceManagerConstructor),
     Node(NodeManagerConstructor),
 }
 
-#[allow(dead_code)]
-pub enum SourceKind {
-    Single(SourceConstructor),
-    Parallel,
-}
-
-#[derive(Clone, Copy, Debug)]
-#[allow(dead_code)]
+#[derive(Clone)]
 pub enum ChannelKind {
     Forward,
     Broadcast,
     RoundRobin,
     Keyed,
+    Custom(Arc<dyn Partitioner>),
+    /// Keyed partitioning onto an operator whose parallelism is managed by the runtime
+    Routed(Arc<KeyRouting>),
     Console,
     Mute,
 }
 
+impl From<Partitioning> for ChannelKind {
+    fn from(partitioning: Partitioning) -> Self {
+        match partitioning {
+            Partitioning::Forward => ChannelKind::Forward,
+            Partitioning::Rebalance => ChannelKind::RoundRobin,
+            Partitioning::Broadcast => ChannelKind::Broadcast,
+            Partitioning::Keyed => ChannelKind::Keyed,
+            Partitioning::Custom(partitioner) => ChannelKind::Custom(partitioner),
+        }
+    }
+}
+
 impl Default for ChannelKind {
     fn default() -> Self {
         ChannelKind::Keyed
@@ -133,3 +224,63 @@ fn create_dfg() {
     assert_eq!(DFGNodeID(3), node3);
 }
 */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(outgoing_channels: usize, ingoing: Vec<DFGOutput>) -> DFGNode {
+        let cons: NodeManagerConstructor = Box::new(|_, _, _, _| unreachable!());
+        DFGNode::new(DFGNodeKind::Node(cons), outgoing_channels, 0, ingoing)
+    }
+
+    fn edge(target: DFGNodeID, tag: Option<&str>, sender_offset: u32) -> DFGEdge {
+        DFGEdge {
+            target,
+            tag: tag.map(String::from),
+            sender_offset,
+        }
+    }
+
+    #[test]
+    fn merge_and_outgoing_edges_test() {
+        let mut left = DFG::default();
+        let left_node = left.insert(node(2, vec![]));
+
+        let mut right = DFG::default();
+        let right_node = right.insert(node(3, vec![]));
+        let right_child = right.insert(node(4, vec![DFGOutput::main(right_node)]));
+
+        let offset = left.merge(right);
+        assert_eq!(offset, 1);
+        assert_eq!(left.get(&DFGNodeID(2)).ingoing, vec![DFGOutput::main(
+            DFGNodeID(1)
+        )]);
+
+        // Union of left_node and right_child
+        let union_node = left.insert(node(1, vec![
+            DFGOutput::main(left_node),
+            DFGOutput::main(DFGNodeID(right_child.0 + offset)),
+        ]));
+
+        let edges = left.outgoing_edges();
+        assert_eq!(edges[left_node.0], vec![edge(union_node, None, 0)]);
+        assert_eq!(edges[1], vec![edge(DFGNodeID(2), None, 0)]);
+        assert_eq!(edges[2], vec![edge(union_node, None, 2)]);
+        assert!(edges[union_node.0].is_empty());
+    }
+
+    #[test]
+    fn side_output_edges_test() {
+        let mut dfg = DFG::default();
+        let upstream = dfg.insert(node(2, vec![]));
+        let main = dfg.insert(node(1, vec![DFGOutput::main(upstream)]));
+        let side = dfg.insert(node(1, vec![DFGOutput::side(upstream, "late")]));
+
+        let edges = dfg.outgoing_edges();
+        assert_eq!(edges[upstream.0], vec![
+            edge(main, None, 0),
+            edge(side, Some("late"), 0)
+        ]);
+    }
+}
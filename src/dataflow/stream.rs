@@ -2,22 +2,34 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 use crate::{
-    data::{ArconType, NodeID},
+    data::{
+        partition::{KeyRouting, Region},
+        ArconType, NodeID,
+    },
     dataflow::{
-        conf::{OperatorBuilder, ParallelismStrategy},
+        conf::{OperatorBuilder, OperatorConf, ParallelismStrategy, Partitioning},
         constructor::*,
-        dfg::{ChannelKind, DFGNode, DFGNodeID, DFGNodeKind, DFG},
+        dfg::{ChannelKind, DFGNode, DFGNodeID, DFGNodeKind, DFGOutput, DFG},
     },
     pipeline::{AssembledPipeline, Pipeline},
-    stream::operator::Operator,
+    stream::operator::{
+        function::Map,
+        join::{JoinInput, JoinWindow, Joined, WindowJoin},
+        side_output::OutputTag,
+        Operator,
+    },
+    util::ArconFnBounds,
 };
-use std::{marker::PhantomData, sync::Arc};
+use std::{cell::RefCell, marker::PhantomData, rc::Rc, sync::Arc};
 
-#[derive(Default)]
 pub struct Context {
     pub(crate) dfg: DFG,
     pipeline: Pipeline,
     console_output: bool,
+    // Set once the graph of this context has been merged into another context.
+    //
+    // Holds the context that now owns the graph and the offset that was added to its node ids.
+    merged_into: Option<(Rc<RefCell<Context>>, usize)>,
 }
 
 impl Context {
@@ -26,16 +38,41 @@ impl Context {
             dfg: Default::default(),
             pipeline,
             console_output: false,
+            merged_into: None,
         }
     }
 }
 
 /// High-level object representing a sequence of stream transformations.
+///
+/// A `Stream` may be cloned in order to feed several downstream operators.
+/// All clones share the same dataflow graph.
+///
+/// Example
+/// ```no_run
+/// use arcon::prelude::*;
+/// let stream: Stream<u64> = Pipeline::default()
+///     .collection((0..100).collect::<Vec<u64>>(), |conf| {
+///         conf.set_arcon_time(ArconTime::Process);
+///     });
+/// let alerts = stream.clone().operator(OperatorBuilder {
+///     constructor: Arc::new(|_| Filter::new(|x| *x > 90)),
+///     conf: Default::default(),
+/// });
+/// let archive = stream.operator(OperatorBuilder {
+///     constructor: Arc::new(|_| Map::new(|x| x + 1)),
+///     conf: Default::default(),
+/// });
+/// let mut pipeline = alerts.union(archive).to_console().build();
+/// ```
+#[derive(Clone)]
 pub struct Stream<IN: ArconType> {
     _marker: PhantomData<IN>,
-    // ID of the node which outputs this stream.
-    prev_dfg_id: DFGNodeID,
-    ctx: Context,
+    // Outputs of the nodes which make up this stream.
+    //
+    // Contains more than one output if the stream is the result of a union.
+    prev_outputs: Vec<DFGOutput>,
+    ctx: Rc<RefCell<Context>>,
 }
 
 impl<IN: ArconType> Stream<IN> {
@@ -44,50 +81,290 @@ impl<IN: ArconType> Stream<IN> {
     where
         OP: Operator<IN = IN> + 'static,
     {
+        self.resolve();
+        let mut ctx = self.ctx.borrow_mut();
+
         // Set up directory for the operator and create Backend
-        let mut state_dir = self.ctx.pipeline.arcon_conf().state_dir.clone();
-        let state_id = builder.state_id();
-        state_dir.push(state_id.clone());
-        let backend = builder.create_backend(state_dir);
-
-        let outgoing_channels = match builder.conf.parallelism_strategy {
-            ParallelismStrategy::Static(num) => num,
-            _ => unreachable!("Managed Parallelism not Supported yet"),
+        let state_id = builder.state_id(ctx.dfg.len());
+        let backend = match &ctx.pipeline.restore_point {
+            Some(restore_point) => builder.restore_backend(
+                &ctx.pipeline.arcon_conf().state_dir,
+                &state_id,
+                restore_point,
+            ),
+            None => {
+                let mut state_dir = ctx.pipeline.arcon_conf().state_dir.clone();
+                state_dir.push(state_id.clone());
+                builder.create_backend(state_dir)
+            }
         };
 
+        let (outgoing_channels, routing) = match &builder.conf.parallelism_strategy {
+            ParallelismStrategy::Static(num) => (*num, None),
+            ParallelismStrategy::Managed(policy) => {
+                assert!(
+                    matches!(builder.conf.partitioning, Partitioning::Keyed),
+                    "Managed parallelism requires Keyed partitioning"
+                );
+                assert!(
+                    policy.min_parallelism > 0 && policy.min_parallelism <= policy.max_parallelism,
+                    "Managed parallelism requires 0 < min_parallelism <= max_parallelism"
+                );
+                let max_key = ctx.pipeline.conf.max_key;
+                let region = Region::new(policy.min_parallelism, max_key as usize);
+                let routing = Arc::new(KeyRouting::new(region, max_key));
+                (policy.max_parallelism, Some(routing))
+            }
+        };
+        let channel_kind = match &routing {
+            Some(routing) => ChannelKind::Routed(routing.clone()),
+            None => builder.conf.partitioning.clone().into(),
+        };
+
+        if let ChannelKind::Forward = channel_kind {
+            for output in &self.prev_outputs {
+                assert_eq!(
+                    ctx.dfg.get(&output.node).outgoing_channels,
+                    outgoing_channels,
+                    "Forward partitioning requires the upstream operator to have the same parallelism"
+                );
+            }
+        }
+
         let manager_constructor = node_manager_constructor::<OP, _>(
             state_id,
-            self.ctx.pipeline.data_system.clone(),
+            ctx.pipeline.data_system.clone(),
             builder,
             backend,
+            routing,
         );
 
-        let prev_dfg_node = self.ctx.dfg.get_mut(&self.prev_dfg_id);
-        let incoming_channels = prev_dfg_node.outgoing_channels;
+        // The operator receives events from every node that outputs this stream
+        let incoming_channels = self
+            .prev_outputs
+            .iter()
+            .map(|output| ctx.dfg.get(&output.node).outgoing_channels)
+            .sum();
 
-        let next_dfg_id = self.ctx.dfg.insert(DFGNode::new(
+        let mut dfg_node = DFGNode::new(
             DFGNodeKind::Node(manager_constructor),
             outgoing_channels,
             incoming_channels,
-            vec![self.prev_dfg_id],
-        ));
+            self.prev_outputs,
+        );
+        dfg_node.channel_kind = channel_kind;
+        let next_dfg_id = ctx.dfg.insert(dfg_node);
+
+        drop(ctx);
 
-        self.prev_dfg_id = next_dfg_id;
         Stream {
             _marker: PhantomData,
-            prev_dfg_id: self.prev_dfg_id,
+            prev_outputs: vec![DFGOutput::main(next_dfg_id)],
             ctx: self.ctx,
         }
     }
 
+    /// Merge this stream with `other` into a single stream
+    ///
+    /// The next operator that is added to the returned stream receives
+    /// events from both streams. Watermarks and epochs are aligned across
+    /// all of its incoming channels.
+    ///
+    /// Note that both streams must have been created from the same [`Pipeline`].
+    ///
+    /// Example
+    /// ```no_run
+    /// use arcon::prelude::*;
+    /// let pipeline = Pipeline::default();
+    /// let stream_one: Stream<u64> = pipeline
+    ///     .clone()
+    ///     .collection((0..50).collect::<Vec<u64>>(), |conf| {
+    ///         conf.set_arcon_time(ArconTime::Process);
+    ///     });
+    /// let stream_two: Stream<u64> = pipeline
+    ///     .collection((50..100).collect::<Vec<u64>>(), |conf| {
+    ///         conf.set_arcon_time(ArconTime::Process);
+    ///     });
+    /// let stream: Stream<u64> = stream_one.union(stream_two);
+    /// ```
+    pub fn union(mut self, mut other: Stream<IN>) -> Stream<IN> {
+        self.resolve();
+        other.resolve();
+
+        if !Rc::ptr_eq(&self.ctx, &other.ctx) {
+            // Move the graph of `other` into our context and redirect
+            // any remaining streams that still refer to the old context.
+            let mut other_ctx = other.ctx.borrow_mut();
+            let mut ctx = self.ctx.borrow_mut();
+            let offset = ctx.dfg.merge(std::mem::take(&mut other_ctx.dfg));
+            ctx.console_output |= other_ctx.console_output;
+            other_ctx.merged_into = Some((self.ctx.clone(), offset));
+            drop(other_ctx);
+            drop(ctx);
+            other.resolve();
+        }
+
+        self.prev_outputs.extend(other.prev_outputs);
+
+        Stream {
+            _marker: PhantomData,
+            prev_outputs: self.prev_outputs,
+            ctx: self.ctx,
+        }
+    }
+
+    /// Join this stream with `other` on key within event-time windows
+    ///
+    /// Elements of both streams are keyed using `key_fn_left` and `key_fn_right`.
+    /// Every pair of elements that share a key and fall into the same window
+    /// is emitted as a [`Joined`] once the watermark passes the end of that window.
+    /// The join operator is configured through `conf`.
+    ///
+    /// Note that both streams must have been created from the same [`Pipeline`].
+    ///
+    /// Example
+    /// ```no_run
+    /// use arcon::prelude::*;
+    /// let pipeline = Pipeline::default();
+    /// let orders: Stream<u64> = pipeline
+    ///     .clone()
+    ///     .collection((0..50).collect::<Vec<u64>>(), |conf| {
+    ///         conf.set_timestamp_extractor(|x: &u64| *x);
+    ///     });
+    /// let payments: Stream<u32> = pipeline
+    ///     .collection((0..50).collect::<Vec<u32>>(), |conf| {
+    ///         conf.set_timestamp_extractor(|x: &u32| *x as u64);
+    ///     });
+    /// let joined: Stream<Joined<u64, u32>> = orders.join(
+    ///     payments,
+    ///     |order: &u64| *order,
+    ///     |payment: &u32| *payment as u64,
+    ///     JoinWindow::tumbling(10, 0),
+    ///     OperatorConf::default(),
+    /// );
+    /// ```
+    pub fn join<R: ArconType>(
+        self,
+        other: Stream<R>,
+        key_fn_left: impl Fn(&IN) -> u64 + ArconFnBounds,
+        key_fn_right: impl Fn(&R) -> u64 + ArconFnBounds,
+        window: JoinWindow,
+        conf: OperatorConf,
+    ) -> Stream<Joined<IN, R>> {
+        let left = self.operator(OperatorBuilder {
+            constructor: Arc::new(move |_| {
+                let key_fn = key_fn_left.clone();
+                Map::new(move |data: IN| JoinInput::left(key_fn(&data), data))
+            }),
+            conf: OperatorConf::default(),
+        });
+        let right = other.operator(OperatorBuilder {
+            constructor: Arc::new(move |_| {
+                let key_fn = key_fn_right.clone();
+                Map::new(move |data: R| JoinInput::right(key_fn(&data), data))
+            }),
+            conf: OperatorConf::default(),
+        });
+
+        left.union(right).operator(OperatorBuilder {
+            constructor: Arc::new(move |backend| WindowJoin::new(backend, window)),
+            conf,
+        })
+    }
+
+    /// Returns the side output identified by `tag` of the operator that produces this stream
+    ///
+    /// Elements are routed to the side output through [OperatorContext::output_to](crate::stream::operator::OperatorContext::output_to).
+    ///
+    /// Example
+    /// ```no_run
+    /// use arcon::prelude::*;
+    ///
+    /// struct Parse {
+    ///     malformed: OutputTag<String>,
+    ///     state: (),
+    /// }
+    ///
+    /// impl Operator for Parse {
+    ///     type IN = String;
+    ///     type OUT = u64;
+    ///     type TimerState = ArconNever;
+    ///     type OperatorState = ();
+    ///
+    ///     fn handle_element(
+    ///         &mut self,
+    ///         element: ArconElement<String>,
+    ///         mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ///     ) -> OperatorResult<()> {
+    ///         match element.data.parse::<u64>() {
+    ///             Ok(data) => ctx.output(ArconElement::new(data)),
+    ///             Err(_) => ctx.output_to(&self.malformed, element),
+    ///         }
+    ///         Ok(())
+    ///     }
+    ///     arcon::ignore_timeout!();
+    ///     arcon::ignore_persist!();
+    ///     fn state(&mut self) -> &mut Self::OperatorState {
+    ///         &mut self.state
+    ///     }
+    /// }
+    ///
+    /// let malformed = OutputTag::<String>::new("malformed");
+    /// let tag = malformed.clone();
+    /// let stream: Stream<u64> = Pipeline::default()
+    ///     .collection(vec![String::from("1"), String::from("x")], |conf| {
+    ///         conf.set_arcon_time(ArconTime::Process);
+    ///     })
+    ///     .operator(OperatorBuilder {
+    ///         constructor: Arc::new(move |_| Parse {
+    ///             malformed: tag.clone(),
+    ///             state: (),
+    ///         }),
+    ///         conf: Default::default(),
+    ///     });
+    /// let errors: Stream<String> = stream.side_output(&malformed);
+    /// ```
+    pub fn side_output<T: ArconType>(&self, tag: &OutputTag<T>) -> Stream<T> {
+        let mut stream = self.clone();
+        stream.resolve();
+
+        let node_id = match stream.prev_outputs.as_slice() {
+            [DFGOutput { node, tag: None }] => *node,
+            _ => {
+                panic!("Side outputs may only be taken from a stream produced by a single operator")
+            }
+        };
+
+        {
+            let mut ctx = stream.ctx.borrow_mut();
+            let dfg_node = ctx.dfg.get_mut(&node_id);
+            assert!(
+                matches!(dfg_node.kind, DFGNodeKind::Node(_)),
+                "Sources do not have side outputs"
+            );
+            if !dfg_node.side_outputs.iter().any(|(id, _)| id == tag.id()) {
+                dfg_node
+                    .side_outputs
+                    .push((tag.id().to_string(), side_output_constructor::<T>()));
+            }
+        }
+
+        Stream {
+            _marker: PhantomData,
+            prev_outputs: vec![DFGOutput::side(node_id, tag.id())],
+            ctx: stream.ctx,
+        }
+    }
+
     /// Will make sure the most downstream Node will print its result to the console
     #[allow(clippy::wrong_self_convention)]
     pub fn to_console(mut self) -> Stream<IN> {
-        self.ctx.console_output = true;
+        self.resolve();
+        self.ctx.borrow_mut().console_output = true;
 
         Stream {
             _marker: PhantomData,
-            prev_dfg_id: self.prev_dfg_id,
+            prev_outputs: self.prev_outputs,
             ctx: self.ctx,
         }
     }
@@ -97,59 +374,127 @@ impl<IN: ArconType> Stream<IN> {
     /// Returns a [`AssembledPipeline`] where all runtime components
     /// have been conneted and started.
     ///
+    /// The whole dataflow graph is built, including branches that
+    /// were split off from this stream through [`Clone`].
+    ///
     /// Note that this method only builds the pipeline. In order
     /// to start it, see the following [method](AssembledPipeline::start).
     pub fn build(mut self) -> AssembledPipeline {
-        let mut target_nodes: Option<Vec<Arc<dyn std::any::Any + Send + Sync>>> = None;
+        self.resolve();
+        let (dfg, mut pipeline, console_output) = {
+            let mut ctx = self.ctx.borrow_mut();
+            (
+                std::mem::take(&mut ctx.dfg),
+                ctx.pipeline.clone(),
+                ctx.console_output,
+            )
+        };
+        assert!(!dfg.is_empty(), "The pipeline has already been built");
+
+        let outgoing_edges = dfg.outgoing_edges();
+        // The partitioning of an edge is decided by its downstream node
+        let input_kinds: Vec<ChannelKind> =
+            dfg.graph.iter().map(|n| n.channel_kind.clone()).collect();
+        let mut constructed: Vec<Option<ErasedComponents>> = vec![None; dfg.len()];
+
+        // Sources are named by their position in the graph
+        let total_sources = dfg
+            .graph
+            .iter()
+            .filter(|n| matches!(n.kind, DFGNodeKind::Source(_)))
+            .count();
+        let mut source_index = total_sources;
+
+        // Nodes are always inserted after their ingoing nodes, so walking the graph
+        // backwards guarantees that all downstream nodes have been constructed.
+        for (index, dfg_node) in dfg.graph.into_iter().enumerate().rev() {
+            let mut targets: Vec<ChannelTarget> = Vec::new();
+            let mut side_targets: Vec<SideOutputTarget> = dfg_node
+                .side_outputs
+                .into_iter()
+                .map(|(tag, constructor)| SideOutputTarget {
+                    tag,
+                    constructor,
+                    targets: Vec::new(),
+                })
+                .collect();
+
+            for edge in &outgoing_edges[index] {
+                let target = ChannelTarget {
+                    components: constructed[edge.target.0]
+                        .clone()
+                        .expect("downstream node has not been constructed"),
+                    channel_kind: input_kinds[edge.target.0].clone(),
+                    sender_offset: edge.sender_offset,
+                };
+                match &edge.tag {
+                    Some(tag) => side_targets
+                        .iter_mut()
+                        .find(|side| &side.tag == tag)
+                        .expect("side output has not been registered")
+                        .targets
+                        .push(target),
+                    None => targets.push(target),
+                }
+            }
+
+            if targets.is_empty() {
+                let channel_kind = if console_output {
+                    ChannelKind::Console
+                } else {
+                    ChannelKind::Mute
+                };
+                targets.push(ChannelTarget {
+                    components: vec![],
+                    channel_kind,
+                    sender_offset: 0,
+                });
+            }
 
-        for dfg_node in self.ctx.dfg.graph.into_iter().rev() {
             match dfg_node.kind {
-                DFGNodeKind::Source(channel_kind, source_manager_cons) => {
-                    let nodes = target_nodes.take().unwrap();
-                    let source_manager =
-                        source_manager_cons(nodes, channel_kind, &mut self.ctx.pipeline);
+                DFGNodeKind::Source(source_manager_cons) => {
+                    source_index -= 1;
+                    let descriptor = format!("source_manager_{}", source_index);
+                    let source_manager = source_manager_cons(descriptor, targets, &mut pipeline);
 
-                    self.ctx.pipeline.source_manager = Some(source_manager);
+                    pipeline.source_managers.push(source_manager);
                 }
                 DFGNodeKind::Node(manager_cons) => {
-                    let (channel_kind, components) = {
-                        match target_nodes {
-                            Some(comps) => (dfg_node.channel_kind, comps),
-                            None => (
-                                if self.ctx.console_output {
-                                    ChannelKind::Console
-                                } else {
-                                    ChannelKind::Mute
-                                },
-                                vec![],
-                            ),
-                        }
-                    };
-
                     // Create expected incoming channels ids
                     let in_channels: Vec<NodeID> = (0..dfg_node.ingoing_channels)
                         .map(|i| NodeID::new(i as u32))
                         .collect();
 
-                    let nodes = manager_cons(
-                        in_channels,
-                        components,
-                        channel_kind,
-                        &mut self.ctx.pipeline,
-                    );
+                    let nodes = manager_cons(in_channels, targets, side_targets, &mut pipeline);
 
-                    target_nodes = Some(nodes);
+                    constructed[index] = Some(nodes);
                 }
             }
         }
-        AssembledPipeline::new(self.ctx.pipeline)
+        AssembledPipeline::new(pipeline)
     }
 
     pub(crate) fn new(ctx: Context) -> Self {
         Self {
             _marker: PhantomData,
-            prev_dfg_id: DFGNodeID(0),
-            ctx,
+            prev_outputs: vec![DFGOutput::main(DFGNodeID(0))],
+            ctx: Rc::new(RefCell::new(ctx)),
+        }
+    }
+
+    // Follow the context in case its graph has been merged into another one
+    fn resolve(&mut self) {
+        loop {
+            let merged_into = self.ctx.borrow().merged_into.clone();
+            match merged_into {
+                Some((ctx, offset)) => {
+                    for output in self.prev_outputs.iter_mut() {
+                        output.node.0 += offset;
+                    }
+                    self.ctx = ctx;
+                }
+                None => break,
+            }
         }
     }
 }
@@ -29,16 +29,25 @@ pub struct TimerEvent<E: Value> {
     timeout_millis: u64,
     #[prost(message, required, tag = "3")]
     payload: E,
+    /// Key of the element that was processed when the timer was scheduled
+    #[prost(uint64, tag = "4")]
+    key: u64,
 }
 
 impl<E: Value> TimerEvent<E> {
-    fn new(time_when_scheduled: u64, timeout_millis: u64, payload: E) -> Self {
+    fn new(time_when_scheduled: u64, timeout_millis: u64, payload: E, key: u64) -> Self {
         TimerEvent {
             time_when_scheduled,
             timeout_millis,
             payload,
+            key,
         }
     }
+
+    /// Returns the key that the timer belongs to
+    pub fn key(&self) -> u64 {
+        self.key
+    }
 }
 
 /// An Index for Stream Timers
@@ -46,6 +55,11 @@ impl<E: Value> TimerEvent<E> {
 /// The Index utilises the [QuadWheelWithOverflow] data structure
 /// in order to manage the timers. The remaining state is kept in
 /// other indexes such as Map/Value.
+///
+/// Timers are identified by their id, which is unique among the scheduled timers.
+/// Since the wheel does not support removal, cancelled and rescheduled timers
+/// leave their wheel entry behind, which is ignored once it expires. The wheel is
+/// rebuilt from the scheduled timers once the stale entries outnumber them.
 pub struct Timer<K, V, B>
 where
     K: Key + Eq + Hash,
@@ -55,8 +69,17 @@ where
     timer: QuadWheelWithOverflow<K>,
     timeouts: EagerHashTable<K, TimerEvent<V>, B>,
     time_handle: ActiveHandle<B, ValueState<u64>>,
+    /// Key that newly scheduled timers belong to
+    current_key: u64,
+    /// Number of entries in the wheel
+    wheel_entries: usize,
+    /// Number of entries in the wheel that belong to cancelled or rescheduled timers
+    stale_entries: usize,
 }
 
+/// Minimum number of stale entries before the wheel is rebuilt
+const MIN_STALE_ENTRIES: usize = 1024;
+
 impl<K, V, B> Timer<K, V, B>
 where
     K: Key + Eq + Hash,
@@ -77,6 +100,9 @@ where
             timer: QuadWheelWithOverflow::default(),
             timeouts: EagerHashTable::new(timeouts_id, backend),
             time_handle,
+            current_key: 0,
+            wheel_entries: 0,
+            stale_entries: 0,
         };
 
         // replay and insert back if any exists
@@ -97,6 +123,21 @@ where
             {
                 panic!("A timeout has expired during replay: {:?}", f);
             }
+            self.wheel_entries += 1;
+        }
+    }
+
+    /// Marks the wheel entry of a cancelled or rescheduled timer as stale
+    ///
+    /// Rebuilds the wheel from the scheduled timers if most of its entries are stale.
+    fn add_stale_entry(&mut self) {
+        self.stale_entries += 1;
+        let live_entries = self.wheel_entries.saturating_sub(self.stale_entries);
+        if self.stale_entries >= MIN_STALE_ENTRIES && self.stale_entries > live_entries {
+            self.timer = QuadWheelWithOverflow::default();
+            self.wheel_entries = 0;
+            self.stale_entries = 0;
+            self.replay_events();
         }
     }
 
@@ -119,7 +160,7 @@ where
     }
 
     #[inline(always)]
-    pub fn tick_and_collect(&mut self, mut time_left: u32, res: &mut Vec<V>) -> Result<()> {
+    pub fn tick_and_collect(&mut self, mut time_left: u32, res: &mut Vec<(u64, V)>) -> Result<()> {
         while time_left > 0 {
             match self.timer.can_skip() {
                 Skip::Empty => {
@@ -143,8 +184,10 @@ where
                 }
                 Skip::None => {
                     for e in self.timer.tick() {
-                        if let Some(entry) = self.take_entry(e) {
-                            res.push(entry);
+                        self.wheel_entries -= 1;
+                        match self.take_entry(e) {
+                            Some(entry) => res.push(entry),
+                            None => self.stale_entries = self.stale_entries.saturating_sub(1),
                         }
                     }
                     self.add_time(1u64)?;
@@ -157,13 +200,55 @@ where
 
     // Lookup id, remove from storage, and return Executable action
     #[inline(always)]
-    fn take_entry(&mut self, id: K) -> Option<V> {
+    fn take_entry(&mut self, id: K) -> Option<(u64, V)> {
+        // Entries expire on the tick that advances the time to their deadline
+        let tick_time = self.current_time().unwrap() + 1;
+        let event = self.timeouts.get(&id).expect("could not get timeout")?;
+        if event.time_when_scheduled + event.timeout_millis > tick_time {
+            // The timer has been rescheduled to a later time
+            return None;
+        }
         self.timeouts
             .remove(&id)
-            .expect("no timeout found for id") // this wouldn't necessarily be an error anymore if we add a cancellation API at some point
-            .map(|e| e.payload)
+            .expect("could not remove timeout")
+            .map(|e| (e.key, e.payload))
+    }
+
+    /// Returns the earliest time at which a scheduled timer may expire
+    ///
+    /// Returns `None` if no timer is scheduled.
+    pub fn next_deadline(&self) -> Result<Option<u64>> {
+        let time = self.current_time()?;
+        // A timer expires on the tick after the skipped time
+        let deadline = match self.timer.can_skip() {
+            Skip::Empty => None,
+            Skip::None => Some(time + 1),
+            Skip::Millis(skip_ms) => Some(time + skip_ms as u64 + 1),
+        };
+        Ok(deadline)
+    }
+
+    /// Returns true if a timer with the given id is scheduled
+    #[inline]
+    pub fn has_timer(&self, id: &K) -> Result<bool> {
+        self.timeouts.contains(id)
+    }
+
+    /// Cancels the timer with the given id and returns its entry
+    ///
+    /// Returns `None` if no such timer is scheduled.
+    #[inline]
+    pub fn cancel_timer(&mut self, id: &K) -> Result<Option<V>> {
+        let event = self.timeouts.remove(id)?;
+        if event.is_some() {
+            self.add_stale_entry();
+        }
+        Ok(event.map(|e| e.payload))
     }
 
+    /// Schedules a timer that expires after `delay`
+    ///
+    /// A timer that is scheduled with the id of a scheduled timer replaces it.
     #[inline(always)]
     pub fn schedule_after(&mut self, id: K, delay: u64, entry: V) -> Result<(), V> {
         match self
@@ -171,9 +256,15 @@ where
             .insert_with_delay(id.clone(), Duration::from_millis(delay))
         {
             Ok(_) => {
+                self.wheel_entries += 1;
+                let rescheduled = self.timeouts.contains(&id).unwrap_or(false);
                 // TODO: fix map_err
-                let event = TimerEvent::new(self.current_time().unwrap(), delay, entry);
+                let event =
+                    TimerEvent::new(self.current_time().unwrap(), delay, entry, self.current_key);
                 let _ = self.timeouts.put(id, event);
+                if rescheduled {
+                    self.add_stale_entry();
+                }
                 Ok(())
             }
             Err(TimerError::Expired(_)) => Err(entry),
@@ -181,6 +272,10 @@ where
         }
     }
 
+    /// Schedules a timer that expires at `time`
+    ///
+    /// A timer that is scheduled with the id of a scheduled timer replaces it,
+    /// which is how timers are rescheduled.
     #[inline]
     pub fn schedule_at(&mut self, id: K, time: u64, entry: V) -> Result<(), V> {
         let curr_time = self.current_time().unwrap();
@@ -195,6 +290,13 @@ where
 
     #[inline]
     pub fn advance_to(&mut self, ts: u64) -> Result<Vec<V>> {
+        let timeouts = self.advance_to_keyed(ts)?;
+        Ok(timeouts.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    /// Advances the time to `ts` and returns the expired timers together with their keys
+    #[inline]
+    pub fn advance_to_keyed(&mut self, ts: u64) -> Result<Vec<(u64, V)>> {
         let mut res = Vec::new();
         let curr_time = self.current_time().unwrap();
         if ts < curr_time {
@@ -204,6 +306,11 @@ where
 
         let mut time_left = ts - curr_time;
         while time_left > std::u32::MAX as u64 {
+            if let Skip::Empty = self.timer.can_skip() {
+                // Nothing can expire, e.g., when a source has ended and the time moves to u64::MAX
+                self.set_time(ts)?;
+                return Ok(res);
+            }
             self.tick_and_collect(std::u32::MAX, &mut res)?;
             time_left -= std::u32::MAX as u64;
         }
@@ -211,6 +318,34 @@ where
         self.tick_and_collect(time_left as u32, &mut res)?;
         Ok(res)
     }
+
+    /// Removes all scheduled timers and returns them
+    ///
+    /// Used to move timers between nodes when the key ranges of an operator change.
+    pub fn drain(&mut self) -> Result<Vec<(K, TimerEvent<V>)>> {
+        let mut events = Vec::new();
+        for res in self.timeouts.iter()? {
+            events.push(res?);
+        }
+        for (id, _) in &events {
+            self.timeouts.remove(id)?;
+        }
+        self.timer = QuadWheelWithOverflow::default();
+        self.wheel_entries = 0;
+        self.stale_entries = 0;
+        Ok(events)
+    }
+
+    /// Schedules a timer that was drained from another Timer
+    ///
+    /// Returns the key and payload of the timer if it has already expired.
+    pub fn restore(&mut self, id: K, event: TimerEvent<V>) -> Result<(), (u64, V)> {
+        let key = event.key;
+        let time = event.time_when_scheduled + event.timeout_millis;
+        self.current_key = key;
+        self.schedule_at(id, time, event.payload)
+            .map_err(|payload| (key, payload))
+    }
 }
 
 impl<K, V, B> IndexOps for Timer<K, V, B>
@@ -223,7 +358,9 @@ where
         self.timeouts.persist()?;
         Ok(())
     }
-    fn set_key(&mut self, _: u64) {}
+    fn set_key(&mut self, key: u64) {
+        self.current_key = key;
+    }
     #[cfg(feature = "arcon_arrow")]
     fn arrow_table(&mut self) -> Result<Option<ArrowTable>> {
         Ok(None)
@@ -249,5 +386,103 @@ mod tests {
         let evs = timer.advance_to(2000).unwrap();
         assert_eq!(evs.len(), 1);
     }
+
+    #[test]
+    fn timer_advance_to_end_test() {
+        let backend = Arc::new(temp_backend());
+        let mut timer: Timer<u64, u64, _> = Timer::new("end_timer", backend);
+
+        timer.schedule_at(1, 1000, 10).unwrap();
+        assert_eq!(timer.advance_to(u64::MAX).unwrap(), vec![10]);
+        assert_eq!(timer.current_time().unwrap(), u64::MAX);
+        assert!(timer.schedule_at(2, 2000, 20).is_err());
+    }
+
+    #[test]
+    fn timer_drain_restore_test() {
+        let backend = Arc::new(temp_backend());
+        let mut timer: Timer<u64, u64, _> = Timer::new("drain_timer", backend.clone());
+        let mut other: Timer<u64, u64, _> = Timer::new("other_timer", backend);
+
+        timer.set_key(7);
+        timer.schedule_at(1, 1000, 10).unwrap();
+        timer.set_key(8);
+        timer.schedule_at(2, 2000, 20).unwrap();
+
+        let mut events = timer.drain().unwrap();
+        events.sort_by_key(|(id, _)| *id);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].1.key(), 7);
+        assert!(timer.advance_to(3000).unwrap().is_empty());
+
+        other.advance_to(1500).unwrap();
+        let mut expired = Vec::new();
+        for (id, event) in events {
+            if let Err(timeout) = other.restore(id, event) {
+                expired.push(timeout);
+            }
+        }
+        assert_eq!(expired, vec![(7, 10)]);
+        assert_eq!(other.advance_to_keyed(2500).unwrap(), vec![(8, 20)]);
+    }
+
+    #[test]
+    fn timer_cancel_reschedule_test() {
+        let backend = Arc::new(temp_backend());
+        let mut timer: Timer<u64, u64, _> = Timer::new("cancel_timer", backend);
+
+        timer.schedule_at(1, 1000, 10).unwrap();
+        timer.schedule_at(2, 1000, 20).unwrap();
+        assert!(timer.has_timer(&1).unwrap());
+        assert_eq!(timer.cancel_timer(&1).unwrap(), Some(10));
+        assert!(!timer.has_timer(&1).unwrap());
+        assert_eq!(timer.cancel_timer(&1).unwrap(), None);
+
+        // Rescheduled to a later and to an earlier time
+        timer.schedule_at(2, 2000, 21).unwrap();
+        timer.schedule_at(3, 1500, 30).unwrap();
+        timer.schedule_at(3, 500, 31).unwrap();
+
+        assert_eq!(timer.advance_to(600).unwrap(), vec![31]);
+        assert!(timer.advance_to(1800).unwrap().is_empty());
+        assert!(timer.has_timer(&2).unwrap());
+        assert_eq!(timer.advance_to(2000).unwrap(), vec![21]);
+        assert!(!timer.has_timer(&2).unwrap());
+    }
+    #[test]
+    fn timer_reschedule_compaction_test() {
+        let backend = Arc::new(temp_backend());
+        let mut timer: Timer<u64, u64, _> = Timer::new("compaction_timer", backend);
+
+        timer.schedule_at(1, 100, 10).unwrap();
+        for i in 0..10 * MIN_STALE_ENTRIES as u64 {
+            timer.schedule_at(2, 1000 + i, i).unwrap();
+            if i % 2 == 0 {
+                assert_eq!(timer.cancel_timer(&2).unwrap(), Some(i));
+            }
+        }
+        // The wheel holds the two scheduled timers and at most a bounded number of stale entries
+        assert!(timer.wheel_entries <= 2 + 2 * MIN_STALE_ENTRIES);
+        assert_eq!(timer.wheel_entries - timer.stale_entries, 2);
+
+        let last = 10 * MIN_STALE_ENTRIES as u64 - 1;
+        assert_eq!(timer.advance_to(1000 + last).unwrap(), vec![10, last]);
+        assert_eq!(timer.wheel_entries - timer.stale_entries, 0);
+    }
+
+    #[test]
+    fn timer_next_deadline_test() {
+        let backend = Arc::new(temp_backend());
+        let mut timer: Timer<u64, u64, _> = Timer::new("deadline_timer", backend);
+
+        assert_eq!(timer.next_deadline().unwrap(), None);
+        timer.schedule_at(1, 1000, 10).unwrap();
+        timer.schedule_at(2, 40, 20).unwrap();
+        assert_eq!(timer.next_deadline().unwrap(), Some(40));
+        assert_eq!(timer.advance_to(40).unwrap(), vec![20]);
+        assert!(timer.next_deadline().unwrap().unwrap() <= 1000);
+        assert_eq!(timer.advance_to(1000).unwrap(), vec![10]);
+        assert_eq!(timer.next_deadline().unwrap(), None);
+    }
     // TODO: more elaborate tests
 }
@@ -130,22 +130,37 @@ pub mod prelude {
     };
     */
     pub use crate::{
-        conf::ArconConf,
+        conf::{ArconConf, CheckpointMode, RetentionPolicy},
         data::{ArconElement, ArconNever, ArconType, StateID, VersionId},
         dataflow::conf::{
-            OperatorBuilder, OperatorConf, ParallelismStrategy, SourceConf, StreamKind,
+            OperatorBuilder, OperatorConf, ParallelSourceBuilder, ParallelismStrategy,
+            Partitioning, RebalancingPolicy, SourceConf, StreamKind,
         },
         manager::snapshot::Snapshot,
-        pipeline::{AssembledPipeline, Pipeline, Stream},
+        pipeline::{AssembledPipeline, Pipeline, Savepoint, Stream},
         stream::{
+            channel::strategy::Partitioner,
             operator::{
                 function::{Filter, FlatMap, Map, MapInPlace},
-                sink::local_file::LocalFileSink,
-                window::{AppenderWindow, IncrementalWindow, WindowAssigner},
+                join::{JoinInput, JoinSide, JoinWindow, Joined, WindowJoin},
+                side_output::OutputTag,
+                sink::{
+                    encoder::{CsvEncoder, Encoder, ProtobufEncoder},
+                    local_file::LocalFileSink,
+                    rolling_file::{RollingFileSink, RollingPolicy},
+                    transactional::{TransactionalSink, TwoPhaseCommitSink},
+                },
+                window::{
+                    AppenderWindow, CountEvictor, CountTrigger, CountWindowAssigner, Evictor,
+                    IncrementalWindow, Trigger, TriggerResult, WindowAssigner,
+                },
                 Operator, OperatorContext,
             },
-            source::collection::CollectionSource,
-            time::ArconTime,
+            source::{
+                collection::{CollectionSource, ParallelCollection},
+                ParallelSource,
+            },
+            time::{ArconTime, WatermarkStrategy},
         },
         Arcon, ArconState,
     };
@@ -153,6 +168,9 @@ pub mod prelude {
     #[cfg(feature = "kafka")]
     pub use crate::stream::operator::sink::kafka::KafkaSink;
 
+    #[cfg(feature = "arcon_serde")]
+    pub use crate::stream::operator::sink::encoder::JsonLinesEncoder;
+
     pub use arcon_error::{arcon_err, arcon_err_kind, ArconResult, OperatorResult};
 
     #[doc(hidden)]
@@ -0,0 +1,87 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use std::{
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex,
+    },
+    thread,
+};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Key of the tasks that copy or remove checkpoints of committed epochs
+///
+/// Running them one at a time keeps a savepoint from copying a checkpoint
+/// that the retention policy removes in the meantime.
+pub(crate) const COMMITTED_CHECKPOINTS: &str = "_committed_checkpoints";
+
+/// A pool of threads that materializes checkpoints off the control path
+///
+/// Backends take a fast snapshot while their nodes are aligned on an epoch
+/// and the checkpoint files are then written by the pool.
+///
+/// The managers only hold a pool once the pipeline has handed them one, and they
+/// run the same tasks on the control path otherwise.
+pub(crate) struct CheckpointPool {
+    workers: Vec<Mutex<Sender<Task>>>,
+}
+
+impl CheckpointPool {
+    pub(crate) fn new(threads: usize) -> Self {
+        let workers = (0..threads.max(1))
+            .map(|index| {
+                let (tx, rx) = channel::<Task>();
+                thread::Builder::new()
+                    .name(format!("arcon-checkpoint-{}", index))
+                    .spawn(move || {
+                        for task in rx {
+                            task();
+                        }
+                    })
+                    .expect("Failed to spawn checkpoint thread");
+                Mutex::new(tx)
+            })
+            .collect();
+
+        CheckpointPool { workers }
+    }
+
+    /// Runs `task` on the pool
+    ///
+    /// Tasks with the same `key` run one at a time in the order they were submitted,
+    /// as an incremental checkpoint may depend on the files of the previous one.
+    pub(crate) fn submit(&self, key: &str, task: impl FnOnce() + Send + 'static) {
+        let worker = fxhash::hash(key) % self.workers.len();
+        // The threads only exit once the pool is dropped
+        let _ = self.workers[worker].lock().unwrap().send(Box::new(task));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc::channel, Arc};
+
+    #[test]
+    fn checkpoint_pool_order_test() {
+        let pool = CheckpointPool::new(4);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (done_tx, done_rx) = channel();
+
+        for i in 0..100 {
+            let order = order.clone();
+            let done_tx = done_tx.clone();
+            pool.submit("op", move || {
+                order.lock().unwrap().push(i);
+                done_tx.send(()).unwrap();
+            });
+        }
+
+        for _ in 0..100 {
+            done_rx.recv().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), (0..100).collect::<Vec<_>>());
+    }
+}
@@ -4,21 +4,37 @@
 #[cfg(feature = "arcon_arrow")]
 use crate::manager::query::{QueryManagerMsg, QueryManagerPort};
 use crate::{
-    data::{Epoch, StateID},
+    data::{partition::KeyRouting, Epoch, StateID},
+    manager::checkpoint::{CheckpointPool, COMMITTED_CHECKPOINTS},
+    pipeline::{record_commit, Savepoint},
     stream::node::source::SourceEvent,
 };
+use arcon_error::*;
 use kompact::prelude::*;
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{mpsc::Sender, Arc},
+};
 
-#[derive(Debug)]
+/// Sent once every state id has acknowledged the checkpoint of an epoch
+#[derive(Debug, Clone)]
 pub struct EpochCommit(pub Epoch);
 
 #[derive(Debug)]
 pub enum EpochEvent {
     /// Acknowledgement that `StateID` has committed a checkpoint for epoch `Epoch`
     Ack(StateID, Epoch),
+    /// `StateID` failed to write its checkpoint for epoch `Epoch`, which is then never committed
+    Failed(StateID, Epoch),
     Register(StateID),
     Halt,
+    /// Switch to the prepared key ranges of a managed operator with the next epoch
+    Rebalance(Arc<KeyRouting>),
+    /// Inject an epoch right away and write a savepoint to `PathBuf` once it is committed
+    Savepoint(PathBuf, Sender<ArconResult<Savepoint>>),
+    /// Drop the pending savepoint to `PathBuf`, e.g., after its caller has timed out
+    CancelSavepoint(PathBuf),
 }
 
 /// Component that injects epoch makers into an Arcon Pipeline
@@ -30,8 +46,10 @@ pub struct EpochManager {
     next_epoch: u64,
     /// Interval in millis to schedule injection timer
     epoch_interval: u64,
-    /// Reference to the SourceManager
-    pub(crate) source_manager: Option<ActorRefStrong<SourceEvent>>,
+    /// References to the SourceManagers of the pipeline
+    pub(crate) source_managers: Vec<ActorRefStrong<SourceEvent>>,
+    /// Number of SourceManagers that have signalled the end of their stream
+    halted_sources: usize,
     /// Kompact Timer
     epoch_timeout: Option<ScheduledTimer>,
     /// Set of known state ids the EpochManager expects acknowledgements from
@@ -42,8 +60,16 @@ pub struct EpochManager {
     last_committed_epoch: u64,
     /// Set of Acks for a commit process
     epoch_acks: HashSet<(StateID, Epoch)>,
+    /// Epochs of which a checkpoint could not be written
+    failed_epochs: HashSet<u64>,
+    /// Key routings that switch to their prepared key ranges with the next injected epoch
+    pending_rebalances: Vec<Arc<KeyRouting>>,
+    /// Savepoints that are written once their epoch has been committed
+    pending_savepoints: Vec<(u64, PathBuf, Sender<ArconResult<Savepoint>>)>,
     /// Actor Reference to the SnapshotManager
     snapshot_manager: ActorRefStrong<EpochCommit>,
+    /// Pool that writes savepoints
+    checkpoint_pool: Option<Arc<CheckpointPool>>,
     #[cfg(feature = "arcon_arrow")]
     query_manager_port: RequiredPort<QueryManagerPort>,
 }
@@ -55,24 +81,40 @@ impl EpochManager {
             next_epoch: 0,
             known_state_ids: HashSet::new(),
             epoch_acks: HashSet::new(),
+            failed_epochs: HashSet::new(),
+            pending_rebalances: Vec::new(),
+            pending_savepoints: Vec::new(),
             last_committed_epoch: 0,
             ongoing_epoch_commit: 0,
             epoch_interval,
             snapshot_manager,
-            source_manager: None,
+            checkpoint_pool: None,
+            source_managers: Vec::new(),
+            halted_sources: 0,
             epoch_timeout: None,
             #[cfg(feature = "arcon_arrow")]
             query_manager_port: RequiredPort::uninitialised(),
         }
     }
+    /// Lets savepoints be written by `pool`
+    pub(crate) fn with_checkpoint_pool(mut self, pool: Arc<CheckpointPool>) -> Self {
+        self.checkpoint_pool = Some(pool);
+        self
+    }
+    /// Continue with `epoch` as the next epoch, e.g., after a restore from a savepoint
+    pub(crate) fn resume_from(&mut self, epoch: u64) {
+        self.next_epoch = epoch;
+        self.ongoing_epoch_commit = epoch;
+        self.last_committed_epoch = epoch.saturating_sub(1);
+    }
     fn handle_timeout(&mut self, timeout_id: ScheduledTimer) -> Handled {
         match self.epoch_timeout {
             Some(ref timeout) if *timeout == timeout_id => {
-                if let Some(source_manager) = &self.source_manager {
-                    source_manager.tell(SourceEvent::Epoch(Epoch::new(self.next_epoch)));
-                    self.next_epoch += 1;
-                } else {
+                if self.source_managers.is_empty() {
                     error!(self.ctx.log(), "SourceManager was never set");
+                } else {
+                    self.inject_epoch(Epoch::new(self.next_epoch));
+                    self.next_epoch += 1;
                 }
                 Handled::Ok
             }
@@ -83,23 +125,75 @@ impl EpochManager {
             } // can happen during restart or teardown
         }
     }
+    /// Sends an epoch marker to all SourceManagers of the pipeline
+    fn inject_epoch(&mut self, epoch: Epoch) {
+        // Scheduled before the marker is sent so that every upstream node
+        // observes the new key ranges when it forwards the marker.
+        for routing in self.pending_rebalances.drain(..) {
+            routing.schedule(epoch.epoch);
+        }
+
+        for source_manager in &self.source_managers {
+            source_manager.tell(SourceEvent::Epoch(epoch));
+        }
+    }
+    /// Commits the ongoing epoch, and the ones after it, once all state ids have acked them
+    ///
+    /// Failed epochs are skipped, since a later epoch covers everything they would have.
+    fn commit_acked_epochs(&mut self) {
+        loop {
+            let epoch = Epoch::new(self.ongoing_epoch_commit);
+            if self.failed_epochs.remove(&epoch.epoch) {
+                error!(
+                    self.ctx.log(),
+                    "Skipping commit of failed epoch {:?}", epoch
+                );
+                self.ongoing_epoch_commit = epoch.epoch + 1;
+                self.fail_savepoints(epoch.epoch);
+                self.epoch_acks.retain(|(_, e)| e.epoch > epoch.epoch);
+                continue;
+            }
+
+            let acks = self.epoch_acks.iter().filter(|(_, e)| *e == epoch).count();
+            if acks < self.known_state_ids.len() {
+                return;
+            }
+
+            self.last_committed_epoch = epoch.epoch;
+            self.ongoing_epoch_commit = epoch.epoch + 1;
+            // Recorded before the SnapshotManager may remove older checkpoints
+            self.record_commit(epoch.epoch);
+            self.complete_savepoints(epoch.epoch);
+            self.snapshot_manager.tell(EpochCommit(epoch));
+            #[cfg(feature = "arcon_arrow")]
+            self.query_manager_port
+                .trigger(QueryManagerMsg::EpochCommit(epoch.epoch));
+            self.epoch_acks.retain(|(_, e)| e.epoch > epoch.epoch);
+        }
+    }
     fn handle_epoch_event(&mut self, event: EpochEvent) {
         match event {
             EpochEvent::Ack(state_id, epoch) => {
                 // verify the state_id
                 if self.known_state_ids.contains(&state_id) {
-                    // Make sure the epoch is for the ongoing checkpoint
-                    if epoch.epoch == self.ongoing_epoch_commit {
+                    // Checkpoints are written asynchronously, so acks of later
+                    // epochs may arrive before the ongoing one is committed
+                    if epoch.epoch >= self.ongoing_epoch_commit {
                         self.epoch_acks.insert((state_id, epoch));
-                        if self.epoch_acks.len() == self.known_state_ids.len() {
-                            self.last_committed_epoch = epoch.epoch;
-                            self.ongoing_epoch_commit = epoch.epoch + 1;
-                            self.snapshot_manager.tell(EpochCommit(epoch));
-                            #[cfg(feature = "arcon_arrow")]
-                            self.query_manager_port
-                                .trigger(QueryManagerMsg::EpochCommit(epoch.epoch));
-                            self.epoch_acks.clear();
-                        }
+                        self.commit_acked_epochs();
+                    }
+                } else {
+                    info!(
+                        self.ctx.log(),
+                        "Ignoring EpochEvent from unknown StateID {}", state_id
+                    );
+                }
+            }
+            EpochEvent::Failed(state_id, epoch) => {
+                if self.known_state_ids.contains(&state_id) {
+                    if epoch.epoch >= self.ongoing_epoch_commit {
+                        self.failed_epochs.insert(epoch.epoch);
+                        self.commit_acked_epochs();
                     }
                 } else {
                     info!(
@@ -117,20 +211,124 @@ impl EpochManager {
                 }
             }
             EpochEvent::Halt => {
-                if let Some(source_manager) = &self.source_manager {
+                if self.source_managers.is_empty() {
+                    error!(self.ctx.log(), "SourceManager was never set");
+                    return;
+                }
+
+                self.halted_sources += 1;
+
+                // Wait until every source has reached the end of its stream
+                if self.halted_sources == self.source_managers.len() {
                     // Send a final epoch marker before revoking the timer
                     info!(
                         self.ctx.log(),
                         "EpochManager sending final epoch marker {:?}", self.next_epoch
                     );
-                    source_manager.tell(SourceEvent::Epoch(Epoch::new(self.next_epoch)));
+                    self.inject_epoch(Epoch::new(self.next_epoch));
                     if let Some(timeout) = self.epoch_timeout.take() {
                         self.cancel_timer(timeout);
                     }
-                } else {
-                    error!(self.ctx.log(), "SourceManager was never set");
                 }
             }
+            EpochEvent::Rebalance(routing) => {
+                self.pending_rebalances.push(routing);
+            }
+            EpochEvent::Savepoint(path, reply) => {
+                if self.source_managers.is_empty()
+                    || self.halted_sources == self.source_managers.len()
+                {
+                    let _ = reply.send(arcon_err!(
+                        "Savepoints can only be taken of a running pipeline"
+                    ));
+                    return;
+                }
+
+                let epoch = Epoch::new(self.next_epoch);
+                info!(self.ctx.log(), "Triggering savepoint at epoch {:?}", epoch);
+                self.inject_epoch(epoch);
+                self.next_epoch += 1;
+                self.pending_savepoints.push((epoch.epoch, path, reply));
+            }
+            EpochEvent::CancelSavepoint(path) => {
+                let pending = self.pending_savepoints.len();
+                self.pending_savepoints
+                    .retain(|(_, savepoint_path, _)| *savepoint_path != path);
+                if self.pending_savepoints.len() < pending {
+                    warn!(self.ctx.log(), "Cancelled savepoint at {:?}", path);
+                }
+            }
+        }
+    }
+    fn checkpoint_dir(&self) -> Option<PathBuf> {
+        self.ctx.config()["checkpoint_dir"]
+            .as_string()
+            .map(PathBuf::from)
+    }
+    /// Persists the committed `epoch` so that a restarted pipeline can recover from it
+    fn record_commit(&mut self, epoch: u64) {
+        match self.checkpoint_dir() {
+            Some(dir) => {
+                if let Err(err) = record_commit(&dir, epoch) {
+                    error!(
+                        self.ctx.log(),
+                        "Failed to record commit of epoch {}: {}", epoch, err
+                    );
+                }
+            }
+            None => error!(self.ctx.log(), "Failed to fetch checkpoint_dir from Config"),
+        }
+    }
+    /// Writes the savepoints whose epoch is covered by the committed `epoch`
+    fn complete_savepoints(&mut self, epoch: u64) {
+        if self.pending_savepoints.is_empty() {
+            return;
+        }
+
+        let checkpoint_dir = self.checkpoint_dir();
+        let (completed, pending): (Vec<_>, Vec<_>) = self
+            .pending_savepoints
+            .drain(..)
+            .partition(|(savepoint_epoch, _, _)| *savepoint_epoch <= epoch);
+        self.pending_savepoints = pending;
+
+        for (savepoint_epoch, path, reply) in completed {
+            let dir = match &checkpoint_dir {
+                Some(dir) => dir.clone(),
+                None => {
+                    let _ = reply.send(arcon_err!("Failed to fetch checkpoint_dir from Config"));
+                    continue;
+                }
+            };
+            let state_ids: Vec<StateID> = self.known_state_ids.iter().cloned().collect();
+            let log = self.ctx.log().clone();
+            let write = move || {
+                let result = Savepoint::write(path, savepoint_epoch, &dir, state_ids.iter());
+                if let Ok(savepoint) = &result {
+                    info!(log, "Completed savepoint at {:?}", savepoint.path());
+                }
+                let _ = reply.send(result);
+            };
+
+            match &self.checkpoint_pool {
+                Some(pool) => pool.submit(COMMITTED_CHECKPOINTS, write),
+                None => write(),
+            }
+        }
+    }
+    /// Fails the savepoints that were waiting for the failed `epoch`
+    fn fail_savepoints(&mut self, epoch: u64) {
+        let (failed, pending): (Vec<_>, Vec<_>) = self
+            .pending_savepoints
+            .drain(..)
+            .partition(|(savepoint_epoch, _, _)| *savepoint_epoch == epoch);
+        self.pending_savepoints = pending;
+
+        for (_, _, reply) in failed {
+            let _ = reply.send(arcon_err!(
+                "Savepoint failed as the checkpoint of epoch {} could not be written",
+                epoch
+            ));
         }
     }
 }
@@ -10,6 +10,8 @@ pub mod source;
 
 pub mod snapshot;
 
+pub(crate) mod checkpoint;
+
 pub mod endpoint;
 #[cfg(feature = "arcon_arrow")]
 pub mod query;
@@ -8,28 +8,78 @@ use crate::index::ArconState;
 #[cfg(feature = "arcon_arrow")]
 use crate::manager::query::{QueryManagerMsg, QueryManagerPort, TableRegistration};
 use crate::{
-    data::{ArconMessage, Epoch, NodeID, StateID, Watermark},
-    index::{HashTable, IndexOps, LocalValue, StateConstructor, ValueIndex, EMPTY_STATE_ID},
+    conf::CheckpointMode,
+    data::{
+        partition::{KeyRouting, Region, StateLayout},
+        Epoch, NodeID, StateID, Watermark,
+    },
+    dataflow::conf::RebalancingPolicy,
+    index::{
+        HashTable, IndexOps, LocalValue, StateConstructor, Timer, TimerEvent, ValueIndex,
+        EMPTY_STATE_ID,
+    },
     manager::{
-        epoch::EpochEvent,
+        checkpoint::CheckpointPool,
+        epoch::{EpochCommit, EpochEvent},
         snapshot::{Snapshot, SnapshotEvent, SnapshotManagerPort},
     },
-    stream::operator::Operator,
+    pipeline::in_flight_dir,
+    stream::{
+        node::{timer_id, Node},
+        operator::Operator,
+        time::ArconTime,
+    },
 };
 use arcon_error::*;
 use arcon_macros::ArconState;
-use arcon_state::Backend;
+use arcon_state::{data::Value, Backend, Materialize};
 use fxhash::FxHashMap;
 use kompact::{component::AbstractComponent, prelude::*};
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, fs, path::Path, sync::Arc};
 
 #[cfg(feature = "metrics")]
 use crate::stream::node::NodeMetrics;
 
-pub type AbstractNode<IN> = (
-    Arc<dyn AbstractComponent<Message = ArconMessage<IN>>>,
-    RequiredRef<NodeManagerPort>,
-);
+pub type AbstractNode<OP, B> = (Arc<Component<Node<OP, B>>>, RequiredRef<NodeManagerPort>);
+
+/// Constructor used to create fresh Operator instances when rebalancing
+pub type OperatorConstructor<OP, B> = Arc<dyn Fn(Arc<B>) -> OP + Send + Sync + 'static>;
+
+/// Key-range rebalancing of an Operator with a managed parallelism
+///
+/// The nodes of the Operator are fixed, only the amount of them that own a key range changes.
+pub(crate) struct Rebalancing<OP, B>
+where
+    OP: Operator + 'static,
+    B: Backend,
+{
+    /// Bounds and thresholds for the amount of nodes with a key range
+    policy: RebalancingPolicy,
+    /// Key ranges shared with the upstream channel strategies
+    routing: Arc<KeyRouting>,
+    operator: OperatorConstructor<OP, B>,
+    /// Latest reported inbound throughput per node with a key range
+    throughput: FxHashMap<NodeID, f64>,
+}
+
+impl<OP, B> Rebalancing<OP, B>
+where
+    OP: Operator + 'static,
+    B: Backend,
+{
+    pub(crate) fn new(
+        policy: RebalancingPolicy,
+        routing: Arc<KeyRouting>,
+        operator: OperatorConstructor<OP, B>,
+    ) -> Self {
+        Rebalancing {
+            policy,
+            routing,
+            operator,
+            throughput: FxHashMap::default(),
+        }
+    }
+}
 
 #[cfg(feature = "metrics")]
 #[derive(Debug, Clone)]
@@ -47,11 +97,17 @@ pub struct CheckpointRequest {
     pub(crate) id: NodeID,
     /// Which Epoch the request is for
     pub(crate) epoch: Epoch,
+    /// Watermark of the Node when it checkpointed the epoch
+    pub(crate) watermark: Watermark,
 }
 
 impl CheckpointRequest {
-    pub fn new(id: NodeID, epoch: Epoch) -> Self {
-        Self { id, epoch }
+    pub fn new(id: NodeID, epoch: Epoch, watermark: Watermark) -> Self {
+        Self {
+            id,
+            epoch,
+            watermark,
+        }
     }
 }
 
@@ -61,10 +117,30 @@ pub enum CheckpointResponse {
     NoAction,
 }
 
+/// Sent to a NodeManager once a part of the checkpoint of an epoch has been written
+#[derive(Debug)]
+pub struct CheckpointCompletion {
+    epoch: Epoch,
+    part: CheckpointPart,
+    path: String,
+    result: ArconResult<()>,
+}
+
+/// The parts that the checkpoint of an epoch consists of
+#[derive(Debug)]
+enum CheckpointPart {
+    /// The snapshot of the backend
+    Backend,
+    /// The in-flight messages of a Node in an unaligned checkpoint
+    InFlight(NodeID),
+}
+
 /// Enum representing events that the Manager may send back to a Node
 #[derive(Clone, Debug)]
 pub enum NodeEvent {
     CheckpointResponse(CheckpointResponse),
+    /// The epoch has been committed by all operators of the pipeline
+    EpochCommit(Epoch),
 }
 
 /// Enum representing events that a Node may send to its manager
@@ -76,6 +152,10 @@ pub enum NodeManagerEvent {
     Watermark(NodeID, Watermark),
     Epoch(NodeID, Epoch),
     Checkpoint(CheckpointRequest),
+    /// Inbound throughput (events/s) of a Node with a managed parallelism
+    Throughput(NodeID, f64),
+    /// Serialized in-flight messages of a Node for the unaligned checkpoint of an epoch
+    InFlight(NodeID, Epoch, Vec<u8>),
 }
 
 /// A [kompact] port for bidirectional communication between a Node and its NodeManager
@@ -91,6 +171,8 @@ pub struct NodeManagerState<B: Backend> {
     epochs: HashTable<NodeID, Epoch, B>,
     current_watermark: LocalValue<Watermark, B>,
     current_epoch: LocalValue<Epoch, B>,
+    /// Key-range layout of the node-local state
+    layout: LocalValue<StateLayout, B>,
     #[ephemeral]
     checkpoint_acks: HashSet<(NodeID, Epoch)>,
 }
@@ -102,7 +184,8 @@ impl<B: Backend> StateConstructor for NodeManagerState<B> {
             watermarks: HashTable::with_capacity("_watermarks", backend.clone(), 64, 64),
             epochs: HashTable::with_capacity("_epochs", backend.clone(), 64, 64),
             current_watermark: LocalValue::new("_curr_watermark", backend.clone()),
-            current_epoch: LocalValue::new("_curr_epoch", backend),
+            current_epoch: LocalValue::new("_curr_epoch", backend.clone()),
+            layout: LocalValue::new("_state_layout", backend),
             checkpoint_acks: HashSet::new(),
         }
     }
@@ -155,12 +238,20 @@ where
     /// Monotonically increasing Node ID index
     node_index: u32,
     /// Active Nodes on this NodeManager
-    pub(crate) nodes: FxHashMap<NodeID, AbstractNode<OP::IN>>,
+    pub(crate) nodes: FxHashMap<NodeID, AbstractNode<OP, B>>,
     /// State Backend used to persist data
     backend: Arc<B>,
     /// Internal manager state
     manager_state: NodeManagerState<B>,
     latest_snapshot: Option<Snapshot>,
+    /// Set if the parallelism of the Operator is managed by the runtime
+    rebalancing: Option<Rebalancing<OP, B>>,
+    /// Pool that writes checkpoints
+    checkpoint_pool: Option<Arc<CheckpointPool>>,
+    /// Whether the nodes record in-flight messages as part of their checkpoints
+    checkpoint_mode: CheckpointMode,
+    /// Amount of written checkpoint parts per epoch that has not been acknowledged yet
+    written_parts: FxHashMap<u64, usize>,
 }
 
 impl<OP, B> NodeManager<OP, B>
@@ -192,11 +283,268 @@ where
             manager_state: NodeManagerState::new(backend.clone()),
             backend,
             latest_snapshot: None,
+            rebalancing: None,
+            checkpoint_pool: None,
+            checkpoint_mode: CheckpointMode::Aligned,
+            written_parts: FxHashMap::default(),
+        }
+    }
+
+    /// Lets the NodeManager rebalance the key ranges of the Operator at runtime
+    ///
+    /// `max_node_parallelism` nodes are expected to be added to the NodeManager,
+    /// where only the nodes of the current [Region](crate::data::partition::Region)
+    /// receive elements.
+    pub(crate) fn with_rebalancing(mut self, rebalancing: Rebalancing<OP, B>) -> Self {
+        self.node_parallelism = rebalancing.routing.region().parallelism();
+        self.max_node_parallelism = rebalancing.policy.max_parallelism;
+        self.rebalancing = Some(rebalancing);
+        self
+    }
+
+    /// Lets checkpoints be written by `pool` after the nodes have resumed processing
+    pub(crate) fn with_checkpoint_pool(mut self, pool: Arc<CheckpointPool>) -> Self {
+        self.checkpoint_pool = Some(pool);
+        self
+    }
+
+    /// Sets how the nodes of the NodeManager take part in checkpoints
+    pub(crate) fn with_checkpoint_mode(mut self, mode: CheckpointMode) -> Self {
+        self.checkpoint_mode = mode;
+        self
+    }
+
+    /// Sets the first epoch to checkpoint, e.g., after a restore from a savepoint
+    pub(crate) fn with_epoch(mut self, epoch: Epoch) -> Self {
+        self.manager_state
+            .current_epoch
+            .put(epoch)
+            .expect("Failed to set the epoch of the NodeManager");
+        self
+    }
+
+    /// Records the inbound throughput of a node and decides whether to rebalance
+    ///
+    /// A decision is made once every node with a key range has reported since the last one.
+    /// Nodes without a key range receive no elements, so their reports are ignored.
+    fn observe_throughput(&mut self, id: NodeID, throughput: f64) {
+        let rebalancing = match &mut self.rebalancing {
+            Some(rebalancing) => rebalancing,
+            None => return,
+        };
+        if rebalancing.routing.region().range_of(id).is_none() {
+            return;
+        }
+
+        rebalancing.throughput.insert(id, throughput);
+        if rebalancing.throughput.len() < self.node_parallelism || rebalancing.routing.is_pending()
+        {
+            return;
+        }
+
+        let total: f64 = rebalancing.throughput.drain().map(|(_, t)| t).sum();
+        let target = rebalancing
+            .policy
+            .target_parallelism(self.node_parallelism, total);
+
+        if target != self.node_parallelism {
+            info!(
+                self.ctx.log(),
+                "Rebalancing {} from {} to {} nodes at an inbound throughput of {:.2} events/s",
+                self.state_id,
+                self.node_parallelism,
+                target,
+                total
+            );
+            rebalancing.routing.prepare(target);
+            // The EpochManager decides with which epoch the new key ranges take effect
+            self.epoch_manager
+                .tell(EpochEvent::Rebalance(rebalancing.routing.clone()));
+        }
+    }
+
+    /// Applies a rebalance that was scheduled for `epoch`
+    ///
+    /// Called once every node has completed the checkpoint of `epoch` and before
+    /// they resume processing. The upstream nodes send elements after the epoch
+    /// marker according to the new key ranges, so the timers of all nodes are
+    /// reassigned by key and every node continues with a fresh Operator instance.
+    fn rebalance(&mut self, epoch: Epoch) -> ArconResult<()> {
+        let rebalancing = match &self.rebalancing {
+            Some(rebalancing) if rebalancing.routing.scheduled_epoch() == Some(epoch.epoch) => {
+                rebalancing
+            }
+            _ => return Ok(()),
+        };
+
+        let region = match rebalancing.routing.region_after(epoch.epoch) {
+            Some(region) => region,
+            None => return arcon_err!("No key ranges scheduled for epoch {:?}", epoch),
+        };
+        let max_key = rebalancing.routing.max_key();
+
+        let mut timers = Vec::new();
+        let mut processing_timers = Vec::new();
+        for (node, _) in self.nodes.values() {
+            let (event, processing) = node.on_definition(|cd| -> ArconResult<_> {
+                Ok((
+                    cd.drain_timers(ArconTime::Event)?,
+                    cd.drain_timers(ArconTime::Process)?,
+                ))
+            })?;
+            timers.extend(event);
+            processing_timers.extend(processing);
+        }
+        let mut timers = assign_timers(timers, &region, max_key);
+        let mut processing_timers = assign_timers(processing_timers, &region, max_key);
+
+        for (id, (node, _)) in &self.nodes {
+            let node_timers = timers.remove(id).unwrap_or_default();
+            let node_processing_timers = processing_timers.remove(id).unwrap_or_default();
+            let operator = (rebalancing.operator)(self.backend.clone());
+            node.on_definition(|cd| cd.rebalance(operator, node_timers, node_processing_timers))?;
+        }
+
+        let region = rebalancing.routing.commit();
+        info!(
+            self.ctx.log(),
+            "Rebalanced {} onto {} nodes at epoch {:?}",
+            self.state_id,
+            region.parallelism(),
+            epoch
+        );
+        self.node_parallelism = region.parallelism();
+        // Reports from before the rebalance do not reflect the new key ranges
+        if let Some(rebalancing) = &mut self.rebalancing {
+            rebalancing.throughput.clear();
+        }
+        self.record_layout(&region, max_key)
+    }
+
+    /// Reassigns the node-local state of a restored backend to the current nodes
+    ///
+    /// Keyed state of the Operator is shared by all of its nodes through the backend,
+    /// while timers are kept per node together with the key they belong to. If the
+    /// backend was checkpointed with another key-range layout, e.g., a different
+    /// parallelism, the timers of all checkpointed nodes are split or merged across
+    /// the current nodes according to `region`.
+    ///
+    /// The per-channel state of the nodes is restored from the watermark and epoch that
+    /// each node checkpointed. With another layout, every node continues from the lowest
+    /// checkpointed watermark, so that no node considers elements late that it has not seen.
+    /// Timers that expire while being reassigned are fired once the nodes are started.
+    ///
+    /// Must be called once all nodes have been added and before they are started.
+    pub(crate) fn restore_layout(&mut self, region: Region, max_key: u64) -> ArconResult<()> {
+        let layout = StateLayout::new(self.nodes.len(), &region, max_key);
+        let previous = match self.manager_state.layout.get()? {
+            Some(previous) => previous.into_owned(),
+            None => StateLayout::default(),
+        };
+
+        if previous.is_empty() {
+            return self.record_layout(&region, max_key);
+        }
+
+        let mut checkpointed = Vec::with_capacity(previous.nodes as usize);
+        for index in 0..previous.nodes {
+            let id = NodeID::new(index);
+            let watermark = self.manager_state.watermarks.get(&id)?.copied();
+            let epoch = self.manager_state.epochs.get(&id)?.copied();
+            if let (Some(watermark), Some(epoch)) = (watermark, epoch) {
+                checkpointed.push((id, watermark, epoch));
+            }
+        }
+        let lowest = checkpointed
+            .iter()
+            .map(|(_, w, e)| (*w, *e))
+            .min_by_key(|(w, _)| *w);
+
+        for (id, (node, _)) in &self.nodes {
+            let restored = match checkpointed.iter().find(|(node_id, _, _)| node_id == id) {
+                Some((_, watermark, epoch)) if previous == layout => Some((*watermark, *epoch)),
+                _ => lowest,
+            };
+            if let Some((watermark, epoch)) = restored {
+                node.on_definition(|cd| cd.restore_node_state(watermark, epoch));
+            }
+        }
+
+        if previous != layout {
+            for time in [ArconTime::Event, ArconTime::Process].iter().copied() {
+                let mut timers = Vec::new();
+                for (node, _) in self.nodes.values() {
+                    timers.extend(node.on_definition(|cd| cd.drain_timers(time))?);
+                }
+
+                // Nodes of the checkpoint that do not exist anymore
+                for index in self.nodes.len() as u32..previous.nodes {
+                    let descriptor = format!("{}_{}", self.state_id, index);
+                    let mut timer: Timer<u64, OP::TimerState, B> =
+                        Timer::new(timer_id(&descriptor, time), self.backend.clone());
+                    timers.extend(timer.drain()?);
+                }
+
+                for (id, node_timers) in assign_timers(timers, &region, max_key) {
+                    if let Some((node, _)) = self.nodes.get(&id) {
+                        node.on_definition(|cd| cd.add_timers(time, node_timers))?;
+                    }
+                }
+            }
+
+            info!(
+                self.ctx.log(),
+                "Restored {} from {} onto {} nodes", self.state_id, previous.nodes, layout.nodes
+            );
+        }
+
+        self.record_layout(&region, max_key)
+    }
+
+    /// Lets the nodes process the in-flight messages of the unaligned checkpoint in `dir`
+    ///
+    /// Must be called once all nodes have been added and before they receive any events.
+    pub(crate) fn replay_in_flight(&mut self, dir: &Path) -> ArconResult<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+
+        for (id, (node, _)) in &self.nodes {
+            let path = dir.join(id.id.to_string());
+            if path.is_file() {
+                let messages = fs::read(&path).ctx("Failed to read in-flight messages")?;
+                node.on_definition(|cd| cd.replay_in_flight(&messages))?;
+            }
         }
+
+        Ok(())
     }
 
+    /// Lets the nodes commit the restored `epoch` before they receive any events
+    ///
+    /// Output that was pending to be committed when the epoch was checkpointed, e.g.,
+    /// the pre-committed transactions of a sink, is committed again.
+    pub(crate) fn replay_commit(&mut self, epoch: Epoch) -> ArconResult<()> {
+        for (node, _) in self.nodes.values() {
+            node.on_definition(|cd| cd.replay_commit(epoch))?;
+        }
+        Ok(())
+    }
+
+    /// Records the current key-range layout so that it is part of the next checkpoint
+    fn record_layout(&mut self, region: &Region, max_key: u64) -> ArconResult<()> {
+        let layout = StateLayout::new(self.nodes.len(), region, max_key);
+        self.manager_state.layout.put(layout)?;
+        self.manager_state.layout.persist()?;
+        Ok(())
+    }
+
+    /// Takes a snapshot of the backend for the current epoch
+    ///
+    /// Only the snapshot is taken before the nodes resume processing. The returned
+    /// checkpoint is written afterwards by [Self::write_checkpoint].
     #[inline]
-    fn checkpoint(&mut self) -> ArconResult<()> {
+    fn checkpoint(&mut self) -> ArconResult<(Epoch, String, Materialize)> {
         if let Some(base_dir) = &self.ctx.config()["checkpoint_dir"].as_string() {
             let curr_epoch = match self.manager_state.current_epoch().get()? {
                 Some(v) => v.as_ref().epoch,
@@ -210,41 +558,184 @@ where
                 epoch = curr_epoch,
             );
 
-            self.backend.checkpoint(checkpoint_dir.as_ref())?;
+            // The watermarks and epochs of the nodes are restored together with the backend
+            self.manager_state.persist()?;
 
-            // Send snapshot to SnapshotManager
-            if self.has_snapshot_state() {
-                let snapshot = Snapshot::new(
-                    std::any::type_name::<B>().to_string(),
-                    curr_epoch,
-                    checkpoint_dir.clone(),
-                );
+            let materialize = self.backend.snapshot(checkpoint_dir.as_ref())?;
 
-                self.snapshot_manager_port.trigger(SnapshotEvent::Snapshot(
-                    self.state_id.clone(),
-                    snapshot.clone(),
-                ));
+            // bump epoch
+            self.manager_state.current_epoch().rmw(|e| {
+                e.epoch += 1;
+            })?;
 
-                self.latest_snapshot = Some(snapshot);
+            Ok((Epoch::new(curr_epoch), checkpoint_dir, materialize))
+        } else {
+            arcon_err!("Failed to fetch checkpoint_dir from Config")
+        }
+    }
+
+    /// Writes a checkpoint taken by [Self::checkpoint] on the checkpoint pool, if there is one,
+    /// after which the epoch is acknowledged
+    fn write_checkpoint(
+        &mut self,
+        (epoch, checkpoint_dir, materialize): (Epoch, String, Materialize),
+    ) -> ArconResult<()> {
+        self.write_part(epoch, CheckpointPart::Backend, checkpoint_dir, move || {
+            materialize().map_err(Into::into)
+        })
+    }
+
+    /// Writes the in-flight messages that a node recorded for an unaligned checkpoint
+    fn checkpoint_in_flight(
+        &mut self,
+        id: NodeID,
+        epoch: Epoch,
+        messages: Vec<u8>,
+    ) -> ArconResult<()> {
+        let base_dir = match self.ctx.config()["checkpoint_dir"].as_string() {
+            Some(base_dir) => base_dir,
+            None => return arcon_err!("Failed to fetch checkpoint_dir from Config"),
+        };
+
+        let dir = Path::new(&base_dir).join(in_flight_dir(&self.state_id, epoch.epoch));
+        let path = dir.join(id.id.to_string());
+        let display = path.display().to_string();
+
+        self.write_part(epoch, CheckpointPart::InFlight(id), display, move || {
+            fs::create_dir_all(&dir).ctx("Failed to create in-flight directory")?;
+            fs::write(&path, messages).ctx("Failed to write in-flight messages")?;
+            Ok(())
+        })
+    }
+
+    /// Writes a part of the checkpoint of `epoch` on the checkpoint pool, if there is one
+    fn write_part(
+        &mut self,
+        epoch: Epoch,
+        part: CheckpointPart,
+        path: String,
+        write: impl FnOnce() -> ArconResult<()> + Send + 'static,
+    ) -> ArconResult<()> {
+        match &self.checkpoint_pool {
+            Some(pool) => {
+                let manager = self.actor_ref();
+                pool.submit(&self.state_id, move || {
+                    let result = write();
+                    manager.tell(CheckpointCompletion {
+                        epoch,
+                        part,
+                        path,
+                        result,
+                    });
+                });
+                Ok(())
+            }
+            None => {
+                let result = write();
+                self.complete_checkpoint(CheckpointCompletion {
+                    epoch,
+                    part,
+                    path,
+                    result,
+                })
             }
+        }
+    }
+
+    /// Acknowledges the epoch of a checkpoint once all of its parts have been written
+    ///
+    /// An unaligned checkpoint also consists of the in-flight messages of every node.
+    fn complete_checkpoint(&mut self, completion: CheckpointCompletion) -> ArconResult<()> {
+        let CheckpointCompletion {
+            epoch,
+            part,
+            path,
+            result,
+        } = completion;
+
+        if let Err(err) = result {
+            // The epoch can no longer be acknowledged, so it is never committed
+            self.written_parts.remove(&epoch.epoch);
+            self.epoch_manager
+                .tell(EpochEvent::Failed(self.state_id.clone(), epoch));
+            return arcon_err!(
+                "Failed to write {:?} checkpoint {} of epoch {:?}: {}",
+                part,
+                path,
+                epoch,
+                err
+            );
+        }
+
+        if let CheckpointPart::Backend = part {
+            self.complete_backend_checkpoint(epoch, &path)?;
+        }
+
+        let expected = match self.checkpoint_mode {
+            CheckpointMode::Aligned => 1,
+            CheckpointMode::Unaligned => 1 + self.nodes.len(),
+        };
+        let written = self.written_parts.entry(epoch.epoch).or_insert(0);
+        *written += 1;
+        if *written < expected {
+            return Ok(());
+        }
+        self.written_parts.remove(&epoch.epoch);
+
+        // Send Ack to EpochManager
+        self.epoch_manager
+            .tell(EpochEvent::Ack(self.state_id.clone(), epoch));
 
-            // Send Ack to EpochManager
-            self.epoch_manager.tell(EpochEvent::Ack(
+        debug!(
+            self.ctx.log(),
+            "Completed the Checkpoint of epoch {:?}", epoch
+        );
+        Ok(())
+    }
+
+    /// Hands the written checkpoint of the backend to the SnapshotManager
+    fn complete_backend_checkpoint(
+        &mut self,
+        epoch: Epoch,
+        checkpoint_dir: &str,
+    ) -> ArconResult<()> {
+        // Send snapshot to SnapshotManager
+        if self.has_snapshot_state() {
+            let snapshot = Snapshot::new(
+                std::any::type_name::<B>().to_string(),
+                epoch.epoch,
+                checkpoint_dir.to_owned(),
+            );
+
+            self.snapshot_manager_port.trigger(SnapshotEvent::Snapshot(
                 self.state_id.clone(),
-                Epoch::new(curr_epoch),
+                snapshot.clone(),
             ));
 
-            // bump epoch
-            self.manager_state.current_epoch().rmw(|e| {
-                e.epoch += 1;
-            })?;
+            self.latest_snapshot = Some(snapshot);
+        }
 
-            debug!(
-                self.ctx.log(),
-                "Completed a Checkpoint to path {}", checkpoint_dir
-            );
-        } else {
-            return arcon_err!("Failed to fetch checkpoint_dir from Config");
+        debug!(
+            self.ctx.log(),
+            "Completed a Checkpoint to path {}", checkpoint_dir
+        );
+
+        #[cfg(feature = "arcon_arrow")]
+        {
+            if OP::OperatorState::has_tables() {
+                if let Some(snapshot) = &self.latest_snapshot {
+                    let mut state = OP::OperatorState::restore(snapshot.clone())?;
+                    for table in state.tables() {
+                        let imut = ImmutableTable::from(table);
+                        let registration = TableRegistration {
+                            epoch: epoch.epoch,
+                            table: imut,
+                        };
+                        self.query_manager_port
+                            .trigger(QueryManagerMsg::TableRegistration(registration));
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -277,11 +768,19 @@ where
                         self.manager_state
                             .checkpoint_acks
                             .insert((request.id, request.epoch));
+                        self.manager_state
+                            .watermarks
+                            .put(request.id, request.watermark)?;
+                        self.manager_state.epochs.put(request.id, request.epoch)?;
 
                         if self.manager_state.checkpoint_acks.len() == self.nodes.len() {
-                            self.checkpoint()?;
+                            let checkpoint = self.checkpoint()?;
                             self.manager_state.checkpoint_acks.clear();
 
+                            // Change the key ranges before the nodes resume processing
+                            self.rebalance(request.epoch)?;
+
+                            // The nodes resume right after the snapshot has been taken
                             for (_, port_ref) in self.nodes.values() {
                                 self.data_system.trigger_i(
                                     NodeEvent::CheckpointResponse(CheckpointResponse::NoAction),
@@ -289,29 +788,21 @@ where
                                 );
                             }
 
-                            #[cfg(feature = "arcon_arrow")]
-                            {
-                                if OP::OperatorState::has_tables() {
-                                    if let Some(snapshot) = &self.latest_snapshot {
-                                        let mut state =
-                                            OP::OperatorState::restore(snapshot.clone())?;
-                                        for table in state.tables() {
-                                            let imut = ImmutableTable::from(table);
-                                            let registration = TableRegistration {
-                                                epoch: epoch.epoch,
-                                                table: imut,
-                                            };
-                                            self.query_manager_port.trigger(
-                                                QueryManagerMsg::TableRegistration(registration),
-                                            );
-                                        }
-                                    }
-                                }
-                            }
+                            self.write_checkpoint(checkpoint)?;
                         }
                     }
                 }
             }
+            NodeManagerEvent::InFlight(id, epoch, messages) => {
+                if self.nodes.contains_key(&id) {
+                    self.checkpoint_in_flight(id, epoch, messages)?;
+                }
+            }
+            NodeManagerEvent::Throughput(id, throughput) => {
+                if self.nodes.contains_key(&id) {
+                    self.observe_throughput(id, throughput);
+                }
+            }
             #[cfg(feature = "metrics")]
             NodeManagerEvent::Metrics(_, _) => {}
         }
@@ -319,6 +810,23 @@ where
     }
 }
 
+/// Assigns timers to the nodes of `region` by the key they belong to
+fn assign_timers<V: Value>(
+    timers: Vec<(u64, TimerEvent<V>)>,
+    region: &Region,
+    max_key: u64,
+) -> FxHashMap<NodeID, Vec<(u64, TimerEvent<V>)>> {
+    let mut assigned: FxHashMap<NodeID, Vec<_>> = FxHashMap::default();
+    for (id, event) in timers {
+        let owner = region.node_of(event.key() % max_key);
+        assigned
+            .entry(owner)
+            .or_insert_with(Vec::new)
+            .push((id, event));
+    }
+    assigned
+}
+
 impl<OP, B> ComponentLifecycle for NodeManager<OP, B>
 where
     OP: Operator + 'static,
@@ -344,8 +852,12 @@ where
     OP: Operator + 'static,
     B: Backend,
 {
-    fn handle(&mut self, _: Never) -> Handled {
-        unreachable!("can't be instantiated!");
+    fn handle(&mut self, commit: EpochCommit) -> Handled {
+        for (_, port_ref) in self.nodes.values() {
+            self.data_system
+                .trigger_i(NodeEvent::EpochCommit(commit.0), &port_ref);
+        }
+        Handled::Ok
     }
 }
 
@@ -402,11 +914,52 @@ where
     OP: Operator + 'static,
     B: Backend,
 {
-    type Message = Never;
-    fn receive_local(&mut self, _: Self::Message) -> Handled {
+    type Message = CheckpointCompletion;
+    fn receive_local(&mut self, msg: Self::Message) -> Handled {
+        if let Err(err) = self.complete_checkpoint(msg) {
+            error!(self.ctx.log(), "{}", err);
+        }
         Handled::Ok
     }
     fn receive_network(&mut self, _: NetMessage) -> Handled {
         unreachable!();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_backend;
+
+    #[test]
+    fn assign_timers_test() {
+        let backend = Arc::new(temp_backend());
+        let max_key = 128;
+
+        // Timers of two nodes that are restored onto three nodes
+        let mut timers = Vec::new();
+        for index in 0..2u64 {
+            let mut timer: Timer<u64, u64, _> =
+                Timer::new(format!("_node_{}_timer", index), backend.clone());
+            for key in (index * 64)..(index * 64 + 64) {
+                timer.set_key(key);
+                timer.schedule_at(key, 1000 + key, key).unwrap();
+            }
+            timers.extend(timer.drain().unwrap());
+        }
+
+        let region = Region::new(3, max_key as usize);
+        let assigned = assign_timers(timers, &region, max_key);
+        assert_eq!(assigned.len(), 3);
+
+        let mut total = 0;
+        for (node, node_timers) in assigned {
+            let range = region.range_of(node).unwrap();
+            for (_, event) in &node_timers {
+                assert!(range.contains(event.key()));
+            }
+            total += node_timers.len();
+        }
+        assert_eq!(total, max_key as usize);
+    }
+}
@@ -1,11 +1,25 @@
 // Copyright (c) 2020, KTH Royal Institute of Technology.
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use super::epoch::EpochCommit;
-use crate::data::{Epoch, StateID};
+use super::{
+    checkpoint::{CheckpointPool, COMMITTED_CHECKPOINTS},
+    epoch::EpochCommit,
+};
+use crate::{
+    conf::RetentionPolicy,
+    data::{Epoch, StateID},
+    pipeline::checkpoint_epoch,
+};
+use arcon_error::*;
 use fxhash::FxHashMap;
 use kompact::prelude::*;
-use std::{collections::HashSet, sync::mpsc::Sender};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+    time::SystemTime,
+};
 
 #[derive(Debug, Clone)]
 pub enum SnapshotEvent {
@@ -34,7 +48,8 @@ impl Snapshot {
 pub struct SnapshotManagerPort;
 
 impl Port for SnapshotManagerPort {
-    type Indication = Never;
+    /// Committed epochs are passed on to the NodeManagers
+    type Indication = EpochCommit;
     type Request = SnapshotEvent;
 }
 
@@ -59,10 +74,20 @@ pub struct SnapshotManager {
     pub(crate) channels: FxHashMap<StateID, Sender<Snapshot>>,
     /// A map of component subscribers per State ID
     pub(crate) subscribers: FxHashMap<StateID, Vec<ActorRefStrong<Snapshot>>>,
+    /// Directory that the checkpoints of the pipeline are written to
+    checkpoint_dir: PathBuf,
+    /// Decides which checkpoints are removed after an epoch has been committed
+    retention: RetentionPolicy,
+    /// Commit time of every committed epoch whose checkpoints are still kept
+    ///
+    /// Unset until the checkpoints of a previous run have been looked up on the first commit.
+    commit_times: Option<BTreeMap<u64, SystemTime>>,
+    /// Pool that removes superseded checkpoints
+    checkpoint_pool: Option<Arc<CheckpointPool>>,
 }
 
 impl SnapshotManager {
-    pub fn new() -> Self {
+    pub fn new(checkpoint_dir: PathBuf, retention: RetentionPolicy) -> Self {
         Self {
             ctx: ComponentContext::uninitialised(),
             manager_port: ProvidedPort::uninitialised(),
@@ -72,9 +97,19 @@ impl SnapshotManager {
             channels: FxHashMap::default(),
             subscribers: FxHashMap::default(),
             last_epoch_committed: 0,
+            checkpoint_dir,
+            retention,
+            commit_times: None,
+            checkpoint_pool: None,
         }
     }
 
+    /// Lets superseded checkpoints be removed by `pool`
+    pub(crate) fn with_checkpoint_pool(mut self, pool: Arc<CheckpointPool>) -> Self {
+        self.checkpoint_pool = Some(pool);
+        self
+    }
+
     fn handle_epoch_commit(&mut self, commit: EpochCommit) {
         let epoch = commit.0;
         self.last_epoch_committed = epoch.epoch;
@@ -96,7 +131,105 @@ impl SnapshotManager {
             // insert snapshot map into the committed catalog
             self.committed_catalog.insert(epoch, snapshot_map);
         }
+
+        // Let the nodes commit the output of the epoch
+        self.manager_port.trigger(EpochCommit(epoch));
+
+        if !self.retention.keeps_all() {
+            if let Err(err) = self.remove_superseded(epoch.epoch) {
+                error!(
+                    self.ctx.log(),
+                    "Failed to remove superseded checkpoints {}", err
+                );
+            }
+        }
+    }
+
+    /// Removes the checkpoints and catalog entries that the retention policy no longer keeps
+    fn remove_superseded(&mut self, committed: u64) -> ArconResult<()> {
+        let now = SystemTime::now();
+        if self.commit_times.is_none() {
+            // Checkpoints that a previous run left behind count as committed right now
+            let previous = list_checkpoints(&self.checkpoint_dir)
+                .ctx("Failed to list checkpoints")?
+                .into_iter()
+                .filter(|(epoch, _)| *epoch < committed)
+                .map(|(epoch, _)| (epoch, now))
+                .collect();
+            self.commit_times = Some(previous);
+        }
+        let commit_times = self.commit_times.get_or_insert_with(BTreeMap::new);
+        commit_times.insert(committed, now);
+
+        let ages = commit_times
+            .iter()
+            .map(|(epoch, commit_time)| {
+                let age = now
+                    .duration_since(*commit_time)
+                    .map(|age| age.as_millis() as u64)
+                    .unwrap_or(0);
+                (*epoch, age)
+            })
+            .collect();
+
+        let superseded: HashSet<u64> = self
+            .retention
+            .superseded(committed, &ages)
+            .into_iter()
+            .collect();
+        if superseded.is_empty() {
+            return Ok(());
+        }
+        for epoch in &superseded {
+            commit_times.remove(epoch);
+            self.committed_catalog.remove(&Epoch::new(*epoch));
+            debug!(
+                self.ctx.log(),
+                "Removing superseded checkpoints of epoch {}", epoch
+            );
+        }
+
+        // Incremental checkpoints hand over the files that later epochs still share
+        let checkpoint_dir = self.checkpoint_dir.clone();
+        let remove = move || -> ArconResult<()> {
+            for (epoch, dir) in
+                list_checkpoints(&checkpoint_dir).ctx("Failed to list checkpoints")?
+            {
+                if superseded.contains(&epoch) {
+                    arcon_state::remove_checkpoint(&dir)?;
+                }
+            }
+            Ok(())
+        };
+        match &self.checkpoint_pool {
+            Some(pool) => {
+                let log = self.ctx.log().clone();
+                pool.submit(COMMITTED_CHECKPOINTS, move || {
+                    if let Err(err) = remove() {
+                        error!(log, "Failed to remove superseded checkpoints {}", err);
+                    }
+                });
+                Ok(())
+            }
+            None => remove(),
+        }
+    }
+}
+
+/// Lists the checkpoint directories in `checkpoint_dir` along with their epoch, oldest first
+fn list_checkpoints(checkpoint_dir: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let mut checkpoints = Vec::new();
+    for entry in fs::read_dir(checkpoint_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(epoch) = entry.file_name().to_str().and_then(checkpoint_epoch) {
+            checkpoints.push((epoch, entry.path()));
+        }
     }
+    checkpoints.sort();
+    Ok(checkpoints)
 }
 
 impl Actor for SnapshotManager {
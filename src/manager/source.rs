@@ -3,17 +3,22 @@
 
 use super::epoch::EpochEvent;
 use crate::{
-    data::StateID,
+    data::{Epoch, StateID},
+    index::EagerHashTable,
     stream::{node::source::SourceEvent, time::ArconTime},
 };
+use arcon_error::*;
 use arcon_state::Backend;
+use fxhash::FxHashMap;
 use kompact::{component::AbstractComponent, prelude::*};
 use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub enum SourceManagerEvent {
-    /// Signal the end of a Source Stream
-    End,
+    /// Signal the end of the Source Stream of the source with the given index
+    End(usize),
+    /// Report the position of the source with the given index when it forwarded an epoch
+    Epoch(usize, Epoch, Option<u64>),
 }
 
 pub struct SourceManagerPort;
@@ -43,8 +48,14 @@ pub(crate) struct SourceManager<B: Backend> {
     /// May contain more than 1 component if the source supports parallelism
     pub(crate) sources: Vec<Arc<dyn AbstractComponent<Message = SourceEvent>>>,
     pub source_refs: Vec<ActorRefStrong<SourceEvent>>,
+    /// Tracks which source components have reached the end of their input
+    ended: Vec<bool>,
     /// A shared backend for sources
-    _backend: Arc<B>,
+    backend: Arc<B>,
+    /// Checkpointed positions of the source components by their index
+    positions: EagerHashTable<u64, u64, B>,
+    /// Positions reported for epochs that not all source components have reached yet
+    epoch_positions: FxHashMap<Epoch, Vec<(usize, Option<u64>)>>,
     /// Reference to the EpochManager
     epoch_manager: ActorRefStrong<EpochEvent>,
 }
@@ -66,7 +77,10 @@ impl<B: Backend> SourceManager<B> {
             state_id,
             sources: Vec::new(),
             source_refs: Vec::new(),
-            _backend: backend,
+            ended: Vec::new(),
+            positions: source_positions(backend.clone()),
+            epoch_positions: FxHashMap::default(),
+            backend,
             epoch_manager,
         }
     }
@@ -75,6 +89,35 @@ impl<B: Backend> SourceManager<B> {
         let source_ref = source.actor_ref().hold().expect("failed to fetch ref");
         self.sources.push(source);
         self.source_refs.push(source_ref);
+        self.ended.push(false);
+    }
+
+    /// Checkpoints the positions of all source components for `epoch`
+    fn checkpoint(
+        &mut self,
+        epoch: Epoch,
+        positions: Vec<(usize, Option<u64>)>,
+    ) -> ArconResult<()> {
+        for (index, position) in positions {
+            if let Some(position) = position {
+                self.positions.put(index as u64, position)?;
+            }
+        }
+
+        if let Some(base_dir) = &self.ctx.config()["checkpoint_dir"].as_string() {
+            let checkpoint_dir = format!(
+                "{}/checkpoint_{id}_{epoch}",
+                base_dir,
+                id = self.state_id,
+                epoch = epoch.epoch,
+            );
+            self.backend.checkpoint(checkpoint_dir.as_ref())?;
+            self.epoch_manager
+                .tell(EpochEvent::Ack(self.state_id.clone(), epoch));
+            Ok(())
+        } else {
+            arcon_err!("Failed to fetch checkpoint_dir from Config")
+        }
     }
 
     fn handle_watermark_timeout(&mut self, timeout_id: ScheduledTimer) -> Handled {
@@ -96,12 +139,34 @@ impl<B: Backend> SourceManager<B> {
     }
 }
 
+/// Index of the checkpointed source positions in the backend of a SourceManager
+pub(crate) fn source_positions<B: Backend>(backend: Arc<B>) -> EagerHashTable<u64, u64, B> {
+    EagerHashTable::new("_source_positions", backend)
+}
+
+/// Assigns splits to `parallelism` source components in a round-robin fashion
+///
+/// Some components receive no splits if there are fewer splits than components.
+pub(crate) fn assign_splits<T>(splits: Vec<T>, parallelism: usize) -> Vec<Vec<T>> {
+    assert!(parallelism > 0, "parallelism must be greater than 0");
+    let mut assignments: Vec<Vec<T>> = (0..parallelism).map(|_| Vec::new()).collect();
+    for (i, split) in splits.into_iter().enumerate() {
+        assignments[i % parallelism].push(split);
+    }
+    assignments
+}
+
 impl<B: Backend> ComponentLifecycle for SourceManager<B> {
     fn on_start(&mut self) -> Handled {
         info!(
             self.ctx.log(),
             "Started SourceManager for {}", self.state_id,
         );
+
+        // Source positions are part of the checkpoint of every epoch
+        self.epoch_manager
+            .tell(EpochEvent::Register(self.state_id.clone()));
+
         Handled::Ok
     }
     fn on_stop(&mut self) -> Handled {
@@ -142,10 +207,46 @@ where
 {
     fn handle(&mut self, event: SourceManagerEvent) -> Handled {
         match event {
-            SourceManagerEvent::End => {
-                self.epoch_manager.tell(EpochEvent::Halt);
+            SourceManagerEvent::End(index) => {
+                self.ended[index] = true;
+                // Only halt once every source component has consumed its input
+                if self.ended.iter().all(|ended| *ended) {
+                    info!(
+                        self.ctx.log(),
+                        "All sources of {} have ended", self.state_id
+                    );
+                    self.epoch_manager.tell(EpochEvent::Halt);
+                }
+            }
+            SourceManagerEvent::Epoch(index, epoch, position) => {
+                let positions = self.epoch_positions.entry(epoch).or_insert_with(Vec::new);
+                positions.push((index, position));
+
+                if positions.len() == self.sources.len() {
+                    let positions = self.epoch_positions.remove(&epoch).unwrap_or_default();
+                    if let Err(err) = self.checkpoint(epoch, positions) {
+                        error!(
+                            self.ctx.log(),
+                            "Failed to checkpoint {} for {:?}: {:?}", self.state_id, epoch, err
+                        );
+                    }
+                }
             }
         }
         Handled::Ok
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assign_splits_test() {
+        let assignments = assign_splits((0..5).collect::<Vec<u32>>(), 3);
+        assert_eq!(assignments, vec![vec![0, 3], vec![1, 4], vec![2]]);
+
+        let assignments = assign_splits(vec![0u32], 3);
+        assert_eq!(assignments, vec![vec![0], vec![], vec![]]);
+    }
+}
@@ -1,13 +1,22 @@
 // Copyright (c) 2020, KTH Royal Institute of Technology.
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use super::Pipeline;
-use crate::{index::ArconState, manager::snapshot::Snapshot, stream::node::source::SourceEvent};
+use super::{Pipeline, Savepoint};
+use crate::{
+    index::ArconState,
+    manager::{epoch::EpochEvent, snapshot::Snapshot},
+    stream::node::source::SourceEvent,
+};
+use arcon_error::*;
 use kompact::{component::AbstractComponent, prelude::ActorRefFactory};
-use std::sync::{
-    mpsc,
-    mpsc::{Receiver, Sender},
-    Arc,
+use std::{
+    path::PathBuf,
+    sync::{
+        mpsc,
+        mpsc::{Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    time::Duration,
 };
 
 /// A [`Pipeline`] that has been fully assembled
@@ -37,12 +46,13 @@ impl AssembledPipeline {
             "The AssembledPipeline has already been started"
         );
 
-        // Send start message to manager component
-        match &self.pipeline.source_manager {
-            Some(source_manager) => {
-                source_manager.actor_ref().tell(SourceEvent::Start);
-            }
-            None => panic!("Something went wrong, no source manager has been created!"),
+        if self.pipeline.source_managers.is_empty() {
+            panic!("Something went wrong, no source manager has been created!");
+        }
+
+        // Send start message to manager components
+        for source_manager in &self.pipeline.source_managers {
+            source_manager.actor_ref().tell(SourceEvent::Start);
         }
 
         // Start epoch manager to begin the injection of epochs into the pipeline.
@@ -57,6 +67,47 @@ impl AssembledPipeline {
         self.start_flag = true;
     }
 
+    /// Takes a savepoint of the running pipeline and writes it to `path`
+    ///
+    /// An epoch is injected right away and the checkpoints of all operators
+    /// for that epoch are copied into `path` once the epoch has been committed.
+    /// The returned [Savepoint] may be passed to [Pipeline::restore_from].
+    ///
+    /// Note that this blocks the current thread until the savepoint has been written,
+    /// or until `savepoint_timeout` of the pipeline's [ArconConf](crate::conf::ArconConf) expires.
+    pub fn trigger_savepoint(&mut self, path: impl Into<PathBuf>) -> ArconResult<Savepoint> {
+        assert!(
+            self.start_flag,
+            "The AssembledPipeline has not been started"
+        );
+
+        let epoch_manager = match &self.pipeline.epoch_manager {
+            Some(epoch_manager) => epoch_manager,
+            None => return arcon_err!("Savepoints require a pipeline with an EpochManager"),
+        };
+
+        let path = path.into();
+        let (tx, rx) = mpsc::channel();
+        epoch_manager
+            .actor_ref()
+            .tell(EpochEvent::Savepoint(path.clone(), tx));
+
+        let timeout = Duration::from_millis(self.pipeline.conf.savepoint_timeout);
+        match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => {
+                // Drop the pending savepoint so that a late epoch commit does not write it
+                epoch_manager
+                    .actor_ref()
+                    .tell(EpochEvent::CancelSavepoint(path.clone()));
+                arcon_err!("Savepoint at {:?} timed out after {:?}", path, timeout)
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                arcon_err!("EpochManager stopped before the savepoint was written")
+            }
+        }
+    }
+
     /// Awaits termination from the pipeline
     ///
     /// Note that this blocks the current thread
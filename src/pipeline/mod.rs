@@ -6,13 +6,18 @@ use crate::manager::query::{QueryManager, QUERY_MANAGER_NAME};
 use crate::{
     buffer::event::PoolInfo,
     conf::{ArconConf, ExecutionMode},
+    data::Epoch,
     dataflow::{
-        conf::{DefaultBackend, SourceBuilder, SourceConf},
-        constructor::source_manager_constructor,
+        conf::{DefaultBackend, ParallelSourceBuilder, SourceBuilder, SourceConf},
+        constructor::{
+            parallel_source_manager_constructor, source_manager_constructor,
+            SourceManagerConstructor,
+        },
         dfg::*,
         stream::Context,
     },
     manager::{
+        checkpoint::CheckpointPool,
         endpoint::{EndpointManager, ENDPOINT_MANAGER_NAME},
         epoch::{EpochEvent, EpochManager},
         snapshot::SnapshotManager,
@@ -20,7 +25,9 @@ use crate::{
     prelude::*,
     stream::{
         node::source::SourceEvent,
-        source::{local_file::LocalFileSource, Source},
+        source::{
+            collection::ParallelCollection, local_file::LocalFileSource, ParallelSource, Source,
+        },
     },
 };
 use arcon_allocator::Allocator;
@@ -28,9 +35,14 @@ use kompact::{component::AbstractComponent, prelude::KompactSystem};
 use std::sync::{Arc, Mutex};
 
 mod assembled;
+mod recovery;
+mod savepoint;
 
 pub use crate::dataflow::stream::Stream;
 pub use assembled::AssembledPipeline;
+pub(crate) use recovery::{record_commit, RestorePoint};
+pub use savepoint::Savepoint;
+pub(crate) use savepoint::{checkpoint_epoch, in_flight_dir};
 
 /// A Pipeline is the starting point of all Arcon applications.
 /// It contains all necessary runtime components, configuration,
@@ -67,12 +79,16 @@ pub struct Pipeline {
     pub(crate) conf: ArconConf,
     /// Arcon allocator for this pipeline
     pub(crate) allocator: Arc<Mutex<Allocator>>,
-    /// SourceManager component for this pipeline
-    pub(crate) source_manager: Option<Arc<dyn AbstractComponent<Message = SourceEvent>>>,
+    /// SourceManager components for this pipeline
+    pub(crate) source_managers: Vec<Arc<dyn AbstractComponent<Message = SourceEvent>>>,
     /// EpochManager component for this pipeline
     pub(crate) epoch_manager: Option<Arc<Component<EpochManager>>>,
     /// SnapshotManager component for this pipeline
     pub(crate) snapshot_manager: Arc<Component<SnapshotManager>>,
+    /// Checkpoints that the backends of the pipeline are restored from
+    pub(crate) restore_point: Option<RestorePoint>,
+    /// Threads that write the checkpoints of the NodeManagers
+    pub(crate) checkpoint_pool: Arc<CheckpointPool>,
     endpoint_manager: Arc<Component<EndpointManager>>,
     #[cfg(feature = "arcon_arrow")]
     pub(crate) query_manager: Arc<Component<QueryManager>>,
@@ -89,7 +105,9 @@ impl Pipeline {
     /// Creates a new Pipeline using the given ArconConf
     fn new(conf: ArconConf) -> Self {
         let allocator = Arc::new(Mutex::new(Allocator::new(conf.allocator_capacity)));
-        let (ctrl_system, data_system, snapshot_manager, epoch_manager) = Self::setup(&conf);
+        let checkpoint_pool = Arc::new(CheckpointPool::new(conf.checkpoint_threads));
+        let (ctrl_system, data_system, snapshot_manager, epoch_manager) =
+            Self::setup(&conf, &checkpoint_pool);
         let endpoint_manager = ctrl_system.create(EndpointManager::new);
         #[cfg(feature = "arcon_arrow")]
         let query_manager = ctrl_system.create(QueryManager::new);
@@ -122,18 +140,28 @@ impl Pipeline {
                 .wait_expect(timeout, "Registration never completed.");
         }
 
-        Self {
+        let pipeline = Self {
             ctrl_system,
             data_system,
             conf,
             allocator,
             snapshot_manager,
             epoch_manager,
-            source_manager: None,
+            source_managers: Vec::new(),
+            restore_point: None,
+            checkpoint_pool,
             endpoint_manager,
             #[cfg(feature = "arcon_arrow")]
             query_manager,
+        };
+
+        // Continue from the last committed epoch of a previous run
+        if pipeline.conf.recovery {
+            if let Some(restore_point) = RestorePoint::recover(&pipeline.conf.checkpoint_dir) {
+                return pipeline.restore(restore_point);
+            }
         }
+        pipeline
     }
 
     /// Creates a new Pipeline using the given ArconConf
@@ -141,10 +169,40 @@ impl Pipeline {
         Self::new(conf)
     }
 
+    /// Starts the pipeline from a [Savepoint]
+    ///
+    /// Every Operator backend is restored from the checkpoint of its state id in the savepoint,
+    /// and building the pipeline fails if the savepoint lacks the checkpoint of a state id.
+    /// Epochs continue after the epoch of the savepoint.
+    ///
+    /// Example
+    /// ```no_run
+    /// use arcon::prelude::*;
+    /// let savepoint = Savepoint::open("/tmp/arcon_savepoint").unwrap();
+    /// let stream: Stream<u64> = Pipeline::default()
+    ///     .restore_from(savepoint)
+    ///     .collection((0..100).collect::<Vec<u64>>(), |conf| {
+    ///         conf.set_arcon_time(ArconTime::Process);
+    ///     });
+    /// ```
+    pub fn restore_from(self, savepoint: Savepoint) -> Self {
+        self.restore(RestorePoint::Savepoint(savepoint))
+    }
+
+    // Internal helper for restoring the pipeline and continuing with the epoch after the restore point
+    fn restore(mut self, restore_point: RestorePoint) -> Self {
+        if let Some(epoch_manager) = &self.epoch_manager {
+            epoch_manager.on_definition(|cd| cd.resume_from(restore_point.epoch() + 1));
+        }
+        self.restore_point = Some(restore_point);
+        self
+    }
+
     /// Helper function to set up internals of the pipeline
     #[allow(clippy::type_complexity)]
     fn setup(
         arcon_conf: &ArconConf,
+        checkpoint_pool: &Arc<CheckpointPool>,
     ) -> (
         KompactSystem,
         KompactSystem,
@@ -160,18 +218,24 @@ impl Pipeline {
             .build()
             .expect("KompactSystem");
 
-        let snapshot_manager = ctrl_system.create(SnapshotManager::new);
-
-        let epoch_manager =
-            match arcon_conf.execution_mode {
-                ExecutionMode::Local => {
-                    let snapshot_manager_ref = snapshot_manager.actor_ref().hold().expect("fail");
-                    Some(ctrl_system.create(|| {
-                        EpochManager::new(arcon_conf.epoch_interval, snapshot_manager_ref)
-                    }))
-                }
-                ExecutionMode::Distributed => None,
-            };
+        let snapshot_manager = ctrl_system.create(|| {
+            SnapshotManager::new(
+                arcon_conf.checkpoint_dir.clone(),
+                arcon_conf.checkpoint_retention.clone(),
+            )
+            .with_checkpoint_pool(checkpoint_pool.clone())
+        });
+
+        let epoch_manager = match arcon_conf.execution_mode {
+            ExecutionMode::Local => {
+                let snapshot_manager_ref = snapshot_manager.actor_ref().hold().expect("fail");
+                Some(ctrl_system.create(|| {
+                    EpochManager::new(arcon_conf.epoch_interval, snapshot_manager_ref)
+                        .with_checkpoint_pool(checkpoint_pool.clone())
+                }))
+            }
+            ExecutionMode::Distributed => None,
+        };
 
         let timeout = std::time::Duration::from_millis(500);
 
@@ -186,31 +250,64 @@ impl Pipeline {
     /// Create a non-parallel data source
     ///
     /// Returns a [`Stream`] object that users may execute transformations on.
+    ///
+    /// Multiple sources may feed the same dataflow graph by creating them
+    /// on clones of the Pipeline and merging them with [`Stream::union`].
     pub fn source<S>(self, builder: SourceBuilder<S>) -> Stream<S::Data>
     where
         S: Source,
     {
         assert_ne!(
-            builder.conf.time == ArconTime::Event,
+            builder.conf.requires_extractor(),
             builder.conf.extractor.is_none(),
             "Cannot use ArconTime::Event without specifying a timestamp extractor"
         );
 
-        let mut state_dir = self.arcon_conf().state_dir.clone();
-        state_dir.push("source_manager");
-        let backend = Arc::new(DefaultBackend::create(&state_dir).unwrap());
-        let time = builder.conf.time;
-        let manager_constructor = source_manager_constructor::<S, _>(
-            String::from("source_manager"),
+        let manager_constructor = source_manager_constructor::<S, DefaultBackend>(
             builder,
-            backend,
             self.arcon_conf().watermark_interval,
-            time,
         );
+        self.source_stream(manager_constructor, 1)
+    }
+
+    /// Add a parallel source to the pipeline
+    ///
+    /// The splits of the [ParallelSource] are assigned to `builder.parallelism`
+    /// Source components that read them concurrently.
+    pub fn parallel_source<P>(
+        self,
+        builder: ParallelSourceBuilder<P>,
+    ) -> Stream<<P::Reader as Source>::Data>
+    where
+        P: ParallelSource,
+    {
+        assert_ne!(
+            builder.conf.requires_extractor(),
+            builder.conf.extractor.is_none(),
+            "Cannot use ArconTime::Event without specifying a timestamp extractor"
+        );
+        assert!(
+            builder.parallelism > 0,
+            "A parallel source requires a parallelism of at least 1"
+        );
+
+        let parallelism = builder.parallelism;
+        let manager_constructor = parallel_source_manager_constructor::<P, DefaultBackend>(
+            builder,
+            self.arcon_conf().watermark_interval,
+        );
+        self.source_stream(manager_constructor, parallelism)
+    }
+
+    // Internal helper for adding a source node to the dataflow graph
+    fn source_stream<T: ArconType>(
+        self,
+        manager_constructor: SourceManagerConstructor,
+        outgoing_channels: usize,
+    ) -> Stream<T> {
         let mut ctx = Context::new(self);
-        let kind = DFGNodeKind::Source(Default::default(), manager_constructor);
+        let kind = DFGNodeKind::Source(manager_constructor);
         let incoming_channels = 0; // sources have 0 incoming channels..
-        let outgoing_channels = 1; // TODO
         let dfg_node = DFGNode::new(kind, outgoing_channels, incoming_channels, vec![]);
         ctx.dfg.insert(dfg_node);
         Stream::new(ctx)
@@ -282,6 +379,44 @@ impl Pipeline {
         self.source(builder)
     }
 
+    /// Creates a bounded data Stream using a Collection that is read in parallel
+    ///
+    /// The collection is divided into `parallelism` splits, each read by its own Source component.
+    ///
+    /// Example
+    /// ```
+    /// use arcon::prelude::*;
+    /// let stream: Stream<u64> = Pipeline::default()
+    ///     .parallel_collection((0..100).collect::<Vec<u64>>(), 4, |conf| {
+    ///         conf.set_arcon_time(ArconTime::Process);
+    ///     });
+    /// ```
+    pub fn parallel_collection<I, A>(
+        self,
+        i: I,
+        parallelism: usize,
+        f: impl FnOnce(&mut SourceConf<A>),
+    ) -> Stream<A>
+    where
+        I: Into<Vec<A>>,
+        A: ArconType,
+    {
+        let collection = i.into();
+        let mut conf = SourceConf::default();
+        f(&mut conf);
+
+        let conf_copy = conf.clone();
+
+        let builder = ParallelSourceBuilder {
+            constructor: Arc::new(move |_| {
+                ParallelCollection::new(collection.clone(), parallelism, conf.clone())
+            }),
+            conf: conf_copy,
+            parallelism,
+        };
+        self.parallel_source(builder)
+    }
+
     // Internal helper for creating PoolInfo for a ChannelStrategy
     pub(crate) fn get_pool_info(&self) -> PoolInfo {
         PoolInfo::new(
@@ -311,6 +446,14 @@ impl Pipeline {
         &self.conf
     }
 
+    /// The first epoch of the pipeline
+    pub(crate) fn start_epoch(&self) -> Epoch {
+        match &self.restore_point {
+            Some(restore_point) => Epoch::new(restore_point.epoch() + 1),
+            None => Epoch::new(0),
+        }
+    }
+
     pub(crate) fn epoch_manager(&self) -> ActorRefStrong<EpochEvent> {
         if let Some(epoch_manager) = &self.epoch_manager {
             epoch_manager
@@ -0,0 +1,135 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use super::{savepoint::in_flight_dir, Savepoint};
+use arcon_error::*;
+use arcon_state::Backend;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// File in the checkpoint directory that holds the last globally committed epoch
+const COMMITTED_EPOCH_FILE: &str = "COMMITTED";
+
+/// Checkpoints that the backends of a pipeline are restored from
+#[derive(Debug, Clone)]
+pub(crate) enum RestorePoint {
+    /// A user-triggered savepoint
+    Savepoint(Savepoint),
+    /// The last committed epoch in the checkpoint directory of a previous run
+    Checkpoint { epoch: u64, checkpoint_dir: PathBuf },
+}
+
+impl RestorePoint {
+    /// Looks for the last committed epoch in `checkpoint_dir`
+    pub(crate) fn recover(checkpoint_dir: &Path) -> Option<RestorePoint> {
+        committed_epoch(checkpoint_dir).map(|epoch| RestorePoint::Checkpoint {
+            epoch,
+            checkpoint_dir: checkpoint_dir.to_path_buf(),
+        })
+    }
+
+    /// Returns the epoch that is restored
+    pub(crate) fn epoch(&self) -> u64 {
+        match self {
+            RestorePoint::Savepoint(savepoint) => savepoint.epoch(),
+            RestorePoint::Checkpoint { epoch, .. } => *epoch,
+        }
+    }
+
+    /// Creates the backend of `state_id` under `state_dir` from the restore point
+    ///
+    /// Fails if the restore point has no checkpoint of `state_id`.
+    pub(crate) fn restore_backend<B: Backend>(
+        &self,
+        state_dir: &Path,
+        state_id: &str,
+    ) -> ArconResult<B> {
+        match self {
+            RestorePoint::Savepoint(savepoint) => savepoint.restore_backend(state_dir, state_id),
+            RestorePoint::Checkpoint {
+                epoch,
+                checkpoint_dir,
+            } => {
+                let live_path = state_dir.join(state_id);
+                let checkpoint = checkpoint_dir.join(format!("checkpoint_{}_{}", state_id, epoch));
+                if !checkpoint.is_dir() {
+                    return arcon_err!("No checkpoint of state id {} at epoch {}", state_id, epoch);
+                }
+
+                if live_path.exists() {
+                    fs::remove_dir_all(&live_path).ctx("Failed to clear live state")?;
+                }
+                Ok(B::restore(&live_path, &checkpoint)?)
+            }
+        }
+    }
+
+    /// Returns the directory with the in-flight messages of `state_id`
+    ///
+    /// The directory only exists if the epoch was checkpointed unaligned.
+    pub(crate) fn in_flight_path(&self, state_id: &str) -> PathBuf {
+        match self {
+            RestorePoint::Savepoint(savepoint) => savepoint.in_flight_path(state_id),
+            RestorePoint::Checkpoint {
+                epoch,
+                checkpoint_dir,
+            } => checkpoint_dir.join(in_flight_dir(state_id, *epoch)),
+        }
+    }
+}
+
+/// Records `epoch` as the last globally committed epoch in `checkpoint_dir`
+pub(crate) fn record_commit(checkpoint_dir: &Path, epoch: u64) -> io::Result<()> {
+    // Written to a temporary file first so that a crash never leaves a partial record
+    let tmp = checkpoint_dir.join(format!("{}.tmp", COMMITTED_EPOCH_FILE));
+    fs::create_dir_all(checkpoint_dir)?;
+    fs::write(&tmp, epoch.to_string())?;
+    fs::rename(&tmp, checkpoint_dir.join(COMMITTED_EPOCH_FILE))
+}
+
+/// Returns the last globally committed epoch in `checkpoint_dir`, if any
+pub(crate) fn committed_epoch(checkpoint_dir: &Path) -> Option<u64> {
+    fs::read_to_string(checkpoint_dir.join(COMMITTED_EPOCH_FILE))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{IndexOps, LocalValue, ValueIndex};
+    use arcon_state::Sled;
+    use std::sync::Arc;
+
+    #[test]
+    fn recover_committed_epoch_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_dir = dir.path().join("checkpoints");
+        assert!(RestorePoint::recover(&checkpoint_dir).is_none());
+
+        let backend = Arc::new(Sled::create(&dir.path().join("live")).unwrap());
+        let mut value: LocalValue<u64, Sled> = LocalValue::new("value", backend.clone());
+        for epoch in 0..2 {
+            value.put(epoch + 10).unwrap();
+            value.persist().unwrap();
+            backend
+                .checkpoint(&checkpoint_dir.join(format!("checkpoint_op_{}", epoch)))
+                .unwrap();
+        }
+
+        // Epoch 1 was checkpointed but never committed
+        record_commit(&checkpoint_dir, 0).unwrap();
+        let restore_point = RestorePoint::recover(&checkpoint_dir).unwrap();
+        assert_eq!(restore_point.epoch(), 0);
+
+        let state_dir = dir.path().join("restored");
+        let restored: Sled = restore_point.restore_backend(&state_dir, "op").unwrap();
+        assert!(restored.was_restored());
+        let value: LocalValue<u64, Sled> = LocalValue::new("value", Arc::new(restored));
+        assert_eq!(value.get().unwrap().unwrap().into_owned(), 10);
+    }
+}
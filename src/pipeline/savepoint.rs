@@ -0,0 +1,194 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::data::StateID;
+use arcon_error::*;
+use arcon_state::{Backend, Config};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A self-contained copy of the checkpoints of a pipeline at a given epoch
+///
+/// Savepoints are created using [AssembledPipeline::trigger_savepoint](super::AssembledPipeline::trigger_savepoint)
+/// and a pipeline is started from one through [Pipeline::restore_from](super::Pipeline::restore_from).
+/// The savepoint directory holds one sub directory per state id with the checkpoint of its backend.
+/// In-flight messages of unaligned checkpoints are kept in a separate `_inflight` directory.
+#[derive(Debug, Clone)]
+pub struct Savepoint {
+    epoch: u64,
+    path: PathBuf,
+}
+
+impl Savepoint {
+    /// Opens an existing savepoint at `path`
+    pub fn open(path: impl Into<PathBuf>) -> ArconResult<Savepoint> {
+        let path = path.into();
+        let mut epoch = None;
+
+        for state_dir in fs::read_dir(&path).ctx("Failed to read savepoint directory")? {
+            let state_dir = state_dir.ctx("Failed to read savepoint directory")?;
+            if !state_dir.path().is_dir() {
+                continue;
+            }
+
+            for checkpoint in fs::read_dir(state_dir.path()).ctx("Failed to read savepoint")? {
+                let name = checkpoint.ctx("Failed to read savepoint")?.file_name();
+                match (name.to_str().and_then(checkpoint_epoch), epoch) {
+                    (Some(e), None) => epoch = Some(e),
+                    (Some(e), Some(prev)) if e == prev => (),
+                    _ => {
+                        return arcon_err!(
+                            "Savepoint {:?} has an invalid checkpoint {:?}",
+                            path,
+                            name
+                        )
+                    }
+                }
+            }
+        }
+
+        match epoch {
+            Some(epoch) => Ok(Savepoint { epoch, path }),
+            None => arcon_err!("Savepoint {:?} does not contain any checkpoints", path),
+        }
+    }
+
+    /// Returns the epoch the savepoint was taken at
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the directory of the savepoint
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Copies the checkpoints of `epoch` from `checkpoint_dir` into a new savepoint at `path`
+    pub(crate) fn write<'a>(
+        path: PathBuf,
+        epoch: u64,
+        checkpoint_dir: &Path,
+        state_ids: impl Iterator<Item = &'a StateID>,
+    ) -> ArconResult<Savepoint> {
+        if path.exists() {
+            return arcon_err!("Savepoint path {:?} already exists", path);
+        }
+
+        for state_id in state_ids {
+            let checkpoint = format!("checkpoint_{}_{}", state_id, epoch);
+            let target = path.join(state_id).join(&checkpoint);
+            arcon_state::copy_checkpoint(&checkpoint_dir.join(&checkpoint), &target)?;
+
+            let in_flight = checkpoint_dir.join(in_flight_dir(state_id, epoch));
+            if in_flight.is_dir() {
+                let target = path
+                    .join(IN_FLIGHT_DIR)
+                    .join(in_flight_dir(state_id, epoch));
+                arcon_state::copy_checkpoint(&in_flight, &target)?;
+            }
+        }
+
+        Ok(Savepoint { epoch, path })
+    }
+
+    /// Creates the backend of `state_id` under `state_dir` from the savepoint
+    ///
+    /// Fails if the savepoint has no checkpoint of `state_id`, e.g., as the
+    /// pipeline has changed since the savepoint was taken.
+    pub(crate) fn restore_backend<B: Backend>(
+        &self,
+        state_dir: &Path,
+        state_id: &str,
+    ) -> ArconResult<B> {
+        let checkpoints = self.path.join(state_id);
+        if !checkpoints.is_dir() {
+            return arcon_err!(
+                "Savepoint {:?} has no checkpoint of state id {}",
+                self.path,
+                state_id
+            );
+        }
+
+        let config = Config {
+            live_state_base_path: state_dir.to_path_buf(),
+            checkpoints_base_path: checkpoints,
+            backend_ids: vec![state_id.to_owned()],
+        };
+        Ok(B::restore_or_create(&config, state_id.to_owned())?)
+    }
+
+    /// Returns the directory with the in-flight messages of `state_id`
+    pub(crate) fn in_flight_path(&self, state_id: &str) -> PathBuf {
+        self.path
+            .join(IN_FLIGHT_DIR)
+            .join(in_flight_dir(state_id, self.epoch))
+    }
+}
+
+/// Sub directory of a savepoint that holds the in-flight messages of unaligned checkpoints
+const IN_FLIGHT_DIR: &str = "_inflight";
+
+/// Returns the name of the directory with the in-flight messages of `state_id` at `epoch`
+///
+/// The directory holds one file per node with the messages that the node received
+/// on its other channels after it checkpointed on the first epoch marker.
+pub(crate) fn in_flight_dir(state_id: &str, epoch: u64) -> String {
+    format!("inflight_{}_{}", state_id, epoch)
+}
+
+/// Parses the epoch out of a `checkpoint_{state_id}_{epoch}` or `inflight_{state_id}_{epoch}` directory name
+pub(crate) fn checkpoint_epoch(name: &str) -> Option<u64> {
+    let name = name
+        .strip_prefix("checkpoint_")
+        .or_else(|| name.strip_prefix("inflight_"))?;
+    name.rsplit('_').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::{IndexOps, LocalValue, ValueIndex};
+    use arcon_state::Sled;
+    use std::sync::Arc;
+
+    #[test]
+    fn savepoint_restore_test() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint_dir = dir.path().join("checkpoints");
+        let state_id: StateID = "counter_state".to_owned();
+
+        let backend = Arc::new(Sled::create(&dir.path().join("live")).unwrap());
+        let mut value: LocalValue<u64, Sled> = LocalValue::new("value", backend.clone());
+        value.put(42).unwrap();
+        value.persist().unwrap();
+        backend
+            .checkpoint(&checkpoint_dir.join(format!("checkpoint_{}_3", state_id)))
+            .unwrap();
+        // In-flight messages of an unaligned checkpoint
+        let in_flight = checkpoint_dir.join(in_flight_dir(&state_id, 3));
+        fs::create_dir_all(&in_flight).unwrap();
+        fs::write(in_flight.join("0"), b"messages").unwrap();
+
+        let path = dir.path().join("savepoint");
+        Savepoint::write(path.clone(), 3, &checkpoint_dir, std::iter::once(&state_id)).unwrap();
+        assert!(Savepoint::write(path.clone(), 3, &checkpoint_dir, std::iter::empty()).is_err());
+
+        let savepoint = Savepoint::open(path).unwrap();
+        assert_eq!(savepoint.epoch(), 3);
+        let in_flight = savepoint.in_flight_path(&state_id);
+        assert_eq!(fs::read(in_flight.join("0")).unwrap(), b"messages");
+        assert_eq!(checkpoint_epoch("inflight_counter_state_3"), Some(3));
+
+        let state_dir = dir.path().join("restored");
+        let restored: Sled = savepoint.restore_backend(&state_dir, &state_id).unwrap();
+        let value: LocalValue<u64, Sled> = LocalValue::new("value", Arc::new(restored));
+        assert_eq!(value.get().unwrap().unwrap().into_owned(), 42);
+
+        // Unknown state ids are not restored
+        assert!(savepoint
+            .restore_backend::<Sled>(&state_dir, "other")
+            .is_err());
+    }
+}
@@ -3,11 +3,18 @@
 
 use crate::{
     buffer::event::{BufferPool, BufferWriter, PoolInfo},
-    data::{ArconEvent, ArconEventWrapper, ArconMessage, ArconType, NodeID},
-    stream::channel::{strategy::send, Channel},
+    data::{
+        partition::{KeyRouting, Region},
+        ArconEvent, ArconEventWrapper, ArconMessage, ArconType, NodeID,
+    },
+    stream::channel::{
+        strategy::{send, Partitioner},
+        Channel,
+    },
 };
 use fxhash::FxHashMap;
 use kompact::prelude::{ComponentDefinition, SerError};
+use std::sync::Arc;
 
 /// A Channel Strategy for Keyed Data Streams
 ///
@@ -30,6 +37,12 @@ where
     buffer_map: FxHashMap<usize, (Channel<A>, BufferWriter<ArconEventWrapper<A>>)>,
     /// Struct holding information regarding the BufferPool
     _pool_info: PoolInfo,
+    /// User-defined partitioner that replaces the contiguous key ranges
+    partitioner: Option<Arc<dyn Partitioner>>,
+    /// Key ranges of a downstream operator whose parallelism is managed by the runtime
+    ///
+    /// Channel `i` is used for the keys that [Region] assigns to NodeID `i`.
+    routing: Option<(Arc<KeyRouting>, Region)>,
 }
 
 impl<A> Keyed<A>
@@ -70,6 +83,63 @@ where
             sender_id,
             buffer_map,
             _pool_info: pool_info,
+            partitioner: None,
+            routing: None,
+        }
+    }
+
+    /// Creates a Keyed strategy that uses a [Partitioner] to place keys
+    pub fn with_partitioner(
+        partitioner: Arc<dyn Partitioner>,
+        max_key: u64,
+        channels: Vec<Channel<A>>,
+        sender_id: NodeID,
+        pool_info: PoolInfo,
+    ) -> Keyed<A> {
+        let mut keyed = Keyed::new(max_key, channels, sender_id, pool_info);
+        keyed.partitioner = Some(partitioner);
+        keyed
+    }
+
+    /// Creates a Keyed strategy that places keys according to a shared [KeyRouting]
+    ///
+    /// There is one channel per node of the downstream operator, while only the nodes
+    /// of the current [Region] receive elements. A new Region is picked up right after
+    /// the epoch marker it is scheduled for.
+    pub fn with_routing(
+        routing: Arc<KeyRouting>,
+        channels: Vec<Channel<A>>,
+        sender_id: NodeID,
+        pool_info: PoolInfo,
+    ) -> Keyed<A> {
+        let max_key = routing.max_key();
+        let mut keyed = Keyed::new(max_key, channels, sender_id, pool_info);
+        let region = routing.region();
+        keyed.routing = Some((routing, region));
+        keyed
+    }
+
+    #[inline]
+    fn partition(&self, key: u64) -> usize {
+        if let Some((_, region)) = &self.routing {
+            return region.node_of(key % self.max_key).id as usize;
+        }
+
+        match &self.partitioner {
+            Some(partitioner) => {
+                let index = partitioner.partition(key, self.key_ranges as usize);
+                assert!(
+                    index < self.key_ranges as usize,
+                    "Partitioner returned an out of bounds partition"
+                );
+                index
+            }
+            None => {
+                // Get key placement
+                let key = key % self.max_key;
+                // Calculate which key range index is responsible for this key
+                (key * self.key_ranges / self.max_key) as usize
+            }
         }
     }
 
@@ -80,10 +150,7 @@ where
     {
         match &event {
             ArconEvent::Element(element) => {
-                // Get key placement
-                let key = element.data.get_key() % self.max_key;
-                // Calculate which key range index is responsible for this key
-                let index = (key * self.key_ranges / self.max_key) as usize;
+                let index = self.partition(element.data.get_key());
 
                 if let Some((chan, buffer)) = self.buffer_map.get_mut(&index) {
                     if let Some(e) = buffer.push(event.into()) {
@@ -119,6 +186,15 @@ where
                     }
                 }
                 self.flush(source);
+
+                // Elements after the epoch marker are placed according to the new key ranges
+                if let ArconEvent::Epoch(epoch) = &event {
+                    if let Some((routing, region)) = &mut self.routing {
+                        if let Some(next) = routing.region_after(epoch.epoch) {
+                            *region = next;
+                        }
+                    }
+                }
             }
         }
     }
@@ -239,4 +315,54 @@ mod tests {
         }
         pipeline.shutdown();
     }
+
+    #[test]
+    fn custom_partitioner_test() {
+        let mut pipeline = Pipeline::default();
+        let pool_info = pipeline.get_pool_info();
+        let system = pipeline.data_system();
+
+        let total_msgs = 100;
+
+        let mut channels: Vec<Channel<Input>> = Vec::new();
+        let mut comps: Vec<Arc<crate::prelude::Component<DebugNode<Input>>>> = Vec::new();
+
+        for _i in 0..2 {
+            let comp = system.create(DebugNode::<Input>::new);
+            system.start(&comp);
+            let actor_ref: ActorRefStrong<ArconMessage<Input>> =
+                comp.actor_ref().hold().expect("failed to fetch");
+            channels.push(Channel::Local(actor_ref));
+            comps.push(comp);
+        }
+
+        // Route every key to the last partition
+        let partitioner = Arc::new(|_key: u64, partitions: usize| partitions - 1);
+        let mut channel_strategy = ChannelStrategy::Keyed(Keyed::with_partitioner(
+            partitioner,
+            256,
+            channels,
+            NodeID::new(1),
+            pool_info,
+        ));
+
+        let comp = &comps[0];
+        comp.on_definition(|cd| {
+            for i in 0..total_msgs {
+                let elem = ArconElement::new(Input { id: i });
+                channel_strategy.add(ArconEvent::Element(elem), cd);
+            }
+            channel_strategy.flush(cd);
+        });
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        comps[0].on_definition(|cd| {
+            assert!(cd.data.is_empty());
+        });
+        comps[1].on_definition(|cd| {
+            assert_eq!(cd.data.len(), total_msgs as usize);
+        });
+        pipeline.shutdown();
+    }
 }
@@ -19,6 +19,23 @@ pub mod keyed;
 #[allow(dead_code)]
 pub mod round_robin;
 
+/// A user-defined function that maps the key of an element onto one of the downstream nodes
+///
+/// The same key must always be mapped to the same partition.
+pub trait Partitioner: Send + Sync {
+    /// Returns the index of the partition for `key`, which must be less than `partitions`
+    fn partition(&self, key: u64, partitions: usize) -> usize;
+}
+
+impl<F> Partitioner for F
+where
+    F: Fn(u64, usize) -> usize + Send + Sync,
+{
+    fn partition(&self, key: u64, partitions: usize) -> usize {
+        self(key, partitions)
+    }
+}
+
 /// A `ChannelStrategy` defines a strategy of how messages are sent downstream
 ///
 /// Common strategies include (one-to-one)[forward::Forward] and (one-to-many)[broadcast::Broadcast]
@@ -29,13 +46,13 @@ where
     /// Send messages to a single Component
     Forward(forward::Forward<A>),
     /// Broadcasts the message to a Vec of `Channels`
-    #[allow(dead_code)]
     Broadcast(broadcast::Broadcast<A>),
     /// Partition data to a set of `Channels` based on keyed hash
     Keyed(keyed::Keyed<A>),
     /// Send messages to a Vec of `Channels` in a Round Robin fashion
-    #[allow(dead_code)]
     RoundRobin(round_robin::RoundRobin<A>),
+    /// Delivers each event to several downstream node groups
+    Fanout(Vec<ChannelStrategy<A>>),
     /// A strategy that prints to the console
     Console,
     /// A strategy that simply does nothing
@@ -54,6 +71,14 @@ where
             ChannelStrategy::Broadcast(s) => s.add(event, source),
             ChannelStrategy::Keyed(s) => s.add(event, source),
             ChannelStrategy::RoundRobin(s) => s.add(event, source),
+            ChannelStrategy::Fanout(strategies) => {
+                if let Some((last, rest)) = strategies.split_last_mut() {
+                    for s in rest {
+                        s.add(event.clone(), source);
+                    }
+                    last.add(event, source);
+                }
+            }
             ChannelStrategy::Console => {
                 println!("{:?}", event);
             }
@@ -62,13 +87,17 @@ where
     }
     /// Flush batch of events out
     #[inline]
-    #[cfg(test)]
     pub(crate) fn flush(&mut self, source: &impl ComponentDefinition) {
         match self {
             ChannelStrategy::Forward(s) => s.flush(source),
             ChannelStrategy::Broadcast(s) => s.flush(source),
             ChannelStrategy::Keyed(s) => s.flush(source),
             ChannelStrategy::RoundRobin(s) => s.flush(source),
+            ChannelStrategy::Fanout(strategies) => {
+                for s in strategies {
+                    s.flush(source);
+                }
+            }
             ChannelStrategy::Console => (),
             ChannelStrategy::Mute => (),
         }
@@ -82,6 +111,9 @@ where
             ChannelStrategy::Broadcast(s) => s.num_channels(),
             ChannelStrategy::Keyed(s) => s.num_channels(),
             ChannelStrategy::RoundRobin(s) => s.num_channels(),
+            ChannelStrategy::Fanout(strategies) => {
+                strategies.iter().map(|s| s.num_channels()).sum()
+            }
             ChannelStrategy::Console => 0,
             ChannelStrategy::Mute => 0,
         }
@@ -116,8 +148,15 @@ fn send<A: ArconType>(
 
 #[cfg(test)]
 pub mod tests {
+    use super::{Channel, *};
+    use crate::{
+        data::{ArconElement, NodeID},
+        pipeline::Pipeline,
+        stream::{channel::strategy::forward::Forward, node::debug::DebugNode},
+    };
     #[cfg(feature = "unsafe_flight")]
     use abomonation_derive::*;
+    use kompact::prelude::*;
 
     #[cfg_attr(feature = "arcon_serde", derive(serde::Serialize, serde::Deserialize))]
     #[cfg_attr(feature = "unsafe_flight", derive(Abomonation))]
@@ -127,4 +166,53 @@ pub mod tests {
         #[prost(uint32, tag = "1")]
         pub id: u32,
     }
+
+    #[test]
+    fn fanout_local_test() {
+        let mut pipeline = Pipeline::default();
+        let pool_info = pipeline.get_pool_info();
+        let system = pipeline.data_system();
+
+        let total_msgs: u64 = 10;
+
+        let mut strategies: Vec<ChannelStrategy<Input>> = Vec::new();
+        let mut comps = Vec::new();
+
+        for i in 0..3 {
+            let comp = system.create(DebugNode::<Input>::new);
+            system.start(&comp);
+            let actor_ref: ActorRefStrong<ArconMessage<Input>> =
+                comp.actor_ref().hold().expect("failed to fetch");
+            strategies.push(ChannelStrategy::Forward(Forward::new(
+                Channel::Local(actor_ref),
+                NodeID::new(i),
+                pool_info.clone(),
+            )));
+            comps.push(comp);
+        }
+
+        let mut channel_strategy = ChannelStrategy::Fanout(strategies);
+        assert_eq!(channel_strategy.num_channels(), 3);
+
+        // take one comp as channel source
+        // just for testing...
+        let comp = &comps[0];
+        comp.on_definition(|cd| {
+            for _i in 0..total_msgs {
+                let elem = ArconElement::new(Input { id: 1 });
+                channel_strategy.add(ArconEvent::Element(elem), cd);
+            }
+            channel_strategy.flush(cd);
+        });
+
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        // Every downstream group should receive all messages
+        for comp in comps {
+            comp.on_definition(|cd| {
+                assert_eq!(cd.data.len() as u64, total_msgs);
+            });
+        }
+        pipeline.shutdown();
+    }
 }
@@ -54,7 +54,7 @@ where
                 ArconEvent::Epoch(e) => {
                     self.epochs.push(e);
                 }
-                ArconEvent::Death(_) => {}
+                ArconEvent::Idle(_) | ArconEvent::Death(_) => {}
             }
         }
     }
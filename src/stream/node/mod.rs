@@ -10,12 +10,17 @@ pub mod source;
 use crate::data::flight_serde::unsafe_remote::UnsafeSerde;
 use crate::index::{ArconState, StateConstructor};
 use crate::{
+    conf::CheckpointMode,
     data::{flight_serde::reliable_remote::ReliableSerde, RawArconMessage, *},
-    index::{AppenderIndex, EagerAppender, IndexOps, Timer as ArconTimer},
+    index::{AppenderIndex, EagerAppender, IndexOps, Timer as ArconTimer, TimerEvent},
     manager::node::{NodeManagerEvent::Checkpoint, *},
     stream::{
         channel::strategy::ChannelStrategy,
-        operator::{Operator, OperatorContext},
+        operator::{
+            side_output::{SideControl, SideOutputs},
+            Operator, OperatorContext,
+        },
+        time::ArconTime,
     },
 }; // conflicts with Kompact Timer trait
 use arcon_error::{arcon_err, arcon_err_kind, ArconResult};
@@ -23,6 +28,7 @@ use arcon_macros::ArconState;
 use arcon_state::Backend;
 use fxhash::*;
 use kompact::prelude::*;
+use prost::Message as _;
 use std::{cell::UnsafeCell, sync::Arc};
 
 #[cfg(feature = "metrics")]
@@ -31,6 +37,14 @@ use crate::metrics::{counter::Counter, gauge::Gauge, meter::Meter};
 /// Type alias for a Node description
 pub type NodeDescriptor = String;
 
+/// Returns the id of the timer of the Node with the given descriptor for the time domain `time`
+pub(crate) fn timer_id(descriptor: &str, time: ArconTime) -> String {
+    match time {
+        ArconTime::Event => format!("_{}_timer", descriptor),
+        ArconTime::Process => format!("_{}_processing_timer", descriptor),
+    }
+}
+
 #[cfg(feature = "metrics")]
 /// Metrics reported by an Arcon Node
 #[derive(Debug, Clone)]
@@ -49,6 +63,8 @@ pub struct NodeMetrics {
     pub outbound_channels: Gauge,
     /// Gauge Metric representing number of inbound channels
     pub inbound_channels: Gauge,
+    /// Counter for elements that arrived too late to be processed by the Operator
+    pub late_elements: Counter,
 }
 
 #[cfg(feature = "metrics")]
@@ -70,6 +86,7 @@ impl NodeMetrics {
             epoch: Epoch::new(0),
             outbound_channels: Gauge::new(),
             inbound_channels: Gauge::new(),
+            late_elements: Counter::new(),
         }
     }
 }
@@ -81,6 +98,9 @@ pub struct NodeState<OP: Operator + 'static, B: Backend> {
     /// Map of senders and their corresponding Watermark
     #[ephemeral]
     watermarks: FxHashMap<NodeID, Watermark>,
+    /// Senders that are idle and do not hold back the watermark of the Node
+    #[ephemeral]
+    idle_channels: FxHashSet<NodeID>,
     /// Map of blocked senders
     #[ephemeral]
     blocked_channels: FxHashSet<NodeID>,
@@ -120,6 +140,7 @@ impl<OP: Operator + 'static, B: Backend> NodeState<OP, B> {
         Self {
             message_buffer,
             watermarks,
+            idle_channels: FxHashSet::default(),
             blocked_channels: FxHashSet::default(),
             current_watermark: Watermark::new(0),
             current_epoch: Epoch::new(0),
@@ -127,6 +148,87 @@ impl<OP: Operator + 'static, B: Backend> NodeState<OP, B> {
             id,
         }
     }
+
+    /// Sets the first epoch the Node expects, e.g., after a restore from a savepoint
+    pub(crate) fn with_epoch(mut self, epoch: Epoch) -> Self {
+        self.current_epoch = epoch;
+        self
+    }
+}
+
+/// Number of events after which a run of recorded in-flight events is spilled to the backend
+const IN_FLIGHT_RUN: usize = 1024;
+
+/// Messages that a Node records for an unaligned checkpoint
+///
+/// Once a Node has checkpointed on the first marker of an epoch, the elements and
+/// watermarks it receives on another channel before the marker of that channel
+/// belong to the checkpoint.
+struct InFlight<A: ArconType> {
+    /// Epoch of the checkpoint
+    epoch: Epoch,
+    /// Marker that the pending senders are expected to deliver
+    marker: Epoch,
+    /// Senders whose marker has not arrived yet
+    pending: FxHashSet<NodeID>,
+    /// Latest events of a single sender, which have not been spilled yet
+    run: Option<RawArconMessage<A>>,
+}
+
+impl<A: ArconType> InFlight<A> {
+    /// Records `event` of `sender`, spilling full runs into `messages`
+    #[inline]
+    fn record<B: Backend>(
+        &mut self,
+        sender: NodeID,
+        event: &ArconEventWrapper<A>,
+        messages: &mut EagerAppender<RawArconMessage<A>, B>,
+    ) -> ArconResult<()> {
+        let data = matches!(
+            event.unwrap_ref(),
+            ArconEvent::Element(_) | ArconEvent::Watermark(_) | ArconEvent::Idle(_)
+        );
+        if !data || !self.pending.contains(&sender) {
+            return Ok(());
+        }
+
+        match &mut self.run {
+            Some(run) if run.sender == sender && run.events.len() < IN_FLIGHT_RUN => {
+                run.events.push(event.clone())
+            }
+            run => {
+                let full = run.replace(RawArconMessage {
+                    events: vec![event.clone()],
+                    sender,
+                });
+                if let Some(full) = full {
+                    messages.append(full)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serializes in-flight messages as length-delimited protobuf messages
+fn encode_in_flight<A: ArconType>(messages: &[RawArconMessage<A>]) -> ArconResult<Vec<u8>> {
+    let mut buf = Vec::new();
+    for message in messages {
+        message
+            .encode_length_delimited(&mut buf)
+            .map_err(|e| arcon_err_kind!("Failed to encode in-flight message {:?}", e))?;
+    }
+    Ok(buf)
+}
+
+fn decode_in_flight<A: ArconType>(mut bytes: &[u8]) -> ArconResult<Vec<RawArconMessage<A>>> {
+    let mut messages = Vec::new();
+    while !bytes.is_empty() {
+        let message = RawArconMessage::decode_length_delimited(&mut bytes)
+            .map_err(|e| arcon_err_kind!("Failed to decode in-flight message {:?}", e))?;
+        messages.push(message);
+    }
+    Ok(messages)
 }
 
 // Just a shorthand to avoid repeating the OperatorContext construction everywhere
@@ -134,8 +236,13 @@ macro_rules! make_context {
     ($sel:ident) => {
         OperatorContext::new(
             $sel,
+            &$sel.descriptor,
             &mut (*$sel.timer.get()),
+            &mut (*$sel.processing_timer.get()),
             &mut (*$sel.channel_strategy.get()),
+            &mut (*$sel.side_outputs.get()),
+            #[cfg(feature = "metrics")]
+            &mut (*$sel.metrics.get()),
         )
     };
 }
@@ -155,15 +262,31 @@ where
     descriptor: NodeDescriptor,
     /// Channel Strategy used by the Node
     channel_strategy: UnsafeCell<ChannelStrategy<OP::OUT>>,
+    /// Side outputs of the Node
+    side_outputs: UnsafeCell<SideOutputs>,
     /// User-defined Operator
     operator: UnsafeCell<OP>,
     #[cfg(feature = "metrics")]
     /// Metrics collected by the Node
-    metrics: NodeMetrics,
+    metrics: UnsafeCell<NodeMetrics>,
     /// Internal Node State
     node_state: NodeState<OP, B>,
     /// Event time scheduler
     timer: UnsafeCell<ArconTimer<u64, OP::TimerState, B>>,
+    /// Processing time scheduler, advanced to the system time when its next timer is due
+    processing_timer: UnsafeCell<ArconTimer<u64, OP::TimerState, B>>,
+    /// Kompact timeout at the deadline of the next processing-time timer
+    processing_timeout: Option<(u64, ScheduledTimer)>,
+    /// Whether the Node aligns its channels on epoch markers before it checkpoints
+    checkpoint_mode: CheckpointMode,
+    /// The ongoing unaligned checkpoint
+    in_flight: Option<InFlight<OP::IN>>,
+    /// Messages recorded for the ongoing unaligned checkpoint
+    in_flight_messages: EagerAppender<RawArconMessage<OP::IN>, B>,
+    /// Inbound events since the last throughput report, only counted with a managed parallelism
+    inbound_events: Option<u64>,
+    /// Reassigned timers that had already expired, fired once the Node is started
+    expired_timers: Vec<(ArconTime, u64, OP::TimerState)>,
 }
 
 impl<OP, B> Node<OP, B>
@@ -178,23 +301,213 @@ where
         operator: OP,
         node_state: NodeState<OP, B>,
         backend: Arc<B>,
-    ) -> Self {
-        let timer_id = format!("_{}_timer", descriptor);
-        let timer = ArconTimer::new(timer_id, backend);
+    ) -> ArconResult<Self> {
+        let timer = ArconTimer::new(timer_id(&descriptor, ArconTime::Event), backend.clone());
+        let mut processing_timer =
+            ArconTimer::new(timer_id(&descriptor, ArconTime::Process), backend.clone());
+        let in_flight_messages = EagerAppender::new(
+            format!("_{}_{}_in_flight", descriptor, node_state.id.id),
+            backend,
+        );
+        // A new processing timer starts at the current system time,
+        // while a restored one fires the timers that expired in the meantime
+        if processing_timer.current_time().unwrap_or(0) == 0 {
+            processing_timer.set_time(crate::util::get_system_time())?;
+        }
 
-        Node {
+        Ok(Node {
             ctx: ComponentContext::uninitialised(),
             node_manager_port: RequiredPort::uninitialised(),
             descriptor,
             channel_strategy: UnsafeCell::new(channel_strategy),
+            side_outputs: UnsafeCell::new(SideOutputs::default()),
             operator: UnsafeCell::new(operator),
             #[cfg(feature = "metrics")]
-            metrics: NodeMetrics::new(),
+            metrics: UnsafeCell::new(NodeMetrics::new()),
             node_state,
             timer: UnsafeCell::new(timer),
+            processing_timer: UnsafeCell::new(processing_timer),
+            processing_timeout: None,
+            checkpoint_mode: CheckpointMode::Aligned,
+            in_flight: None,
+            in_flight_messages,
+            inbound_events: None,
+            expired_timers: Vec::new(),
+        })
+    }
+
+    /// Sets the side outputs of the Node
+    pub(crate) fn with_side_outputs(mut self, side_outputs: SideOutputs) -> Self {
+        self.side_outputs = UnsafeCell::new(side_outputs);
+        self
+    }
+
+    /// Sets how the Node takes part in checkpoints
+    pub(crate) fn with_checkpoint_mode(mut self, mode: CheckpointMode) -> Self {
+        self.checkpoint_mode = mode;
+        self
+    }
+
+    /// Lets the Node report its inbound throughput to the NodeManager for rebalancing
+    pub(crate) fn with_throughput_reporting(mut self) -> Self {
+        self.inbound_events = Some(0);
+        self
+    }
+
+    /// Processes the in-flight messages of the unaligned checkpoint the Node was restored from
+    ///
+    /// Called by the NodeManager before the Node receives any other events.
+    pub(crate) fn replay_in_flight(&mut self, messages: &[u8]) -> ArconResult<()> {
+        for message in decode_in_flight::<OP::IN>(messages)? {
+            self.handle_raw_msg(message)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the timer of the time domain `time`
+    #[inline]
+    fn timer_of(&mut self, time: ArconTime) -> &mut ArconTimer<u64, OP::TimerState, B> {
+        match time {
+            ArconTime::Event => self.timer.get_mut(),
+            ArconTime::Process => self.processing_timer.get_mut(),
         }
     }
 
+    /// Removes all timers of the time domain `time` and returns them
+    ///
+    /// Used by the NodeManager when the key ranges of the Operator change.
+    pub(crate) fn drain_timers(
+        &mut self,
+        time: ArconTime,
+    ) -> ArconResult<Vec<(u64, TimerEvent<OP::TimerState>)>> {
+        Ok(self.timer_of(time).drain()?)
+    }
+
+    /// Replaces the Operator with a fresh instance and schedules the given timers
+    ///
+    /// Called by the NodeManager at an epoch boundary after the key ranges of the
+    /// Operator have changed. As the new instance reads its state from the backend,
+    /// the Node keeps no cached state of keys that it is no longer responsible for.
+    pub(crate) fn rebalance(
+        &mut self,
+        operator: OP,
+        timers: Vec<(u64, TimerEvent<OP::TimerState>)>,
+        processing_timers: Vec<(u64, TimerEvent<OP::TimerState>)>,
+    ) -> ArconResult<()> {
+        self.operator = UnsafeCell::new(operator);
+        self.add_timers(ArconTime::Event, timers)?;
+        self.add_timers(ArconTime::Process, processing_timers)?;
+        self.fire_expired_timers()?;
+        self.schedule_processing_timeout()
+    }
+
+    /// Schedules timers of the time domain `time` that were drained from other Nodes
+    ///
+    /// Timers that have already expired on this Node are kept aside
+    /// until [fire_expired_timers](Node::fire_expired_timers) is called.
+    pub(crate) fn add_timers(
+        &mut self,
+        time: ArconTime,
+        timers: Vec<(u64, TimerEvent<OP::TimerState>)>,
+    ) -> ArconResult<()> {
+        let mut expired = Vec::new();
+        let timer = self.timer_of(time);
+        for (id, event) in timers {
+            if let Err((key, timeout)) = timer.restore(id, event) {
+                expired.push((time, key, timeout));
+            }
+        }
+        self.expired_timers.extend(expired);
+        Ok(())
+    }
+
+    /// Lets the Operator handle the reassigned timers that had already expired
+    fn fire_expired_timers(&mut self) -> ArconResult<()> {
+        for (time, key, timeout) in std::mem::take(&mut self.expired_timers) {
+            self.timer_of(time).set_key(key);
+            unsafe {
+                let operator = &mut (*self.operator.get());
+                match time {
+                    ArconTime::Event => operator.handle_timeout(timeout, make_context!(self))?,
+                    ArconTime::Process => {
+                        operator.handle_processing_timeout(timeout, make_context!(self))?
+                    }
+                }
+            };
+        }
+        Ok(())
+    }
+
+    /// Restores the watermark and epoch that the Node continues from
+    ///
+    /// Every channel starts at `watermark`, while the Node expects the marker
+    /// of the epoch after the checkpointed `epoch`.
+    pub(crate) fn restore_node_state(&mut self, watermark: Watermark, epoch: Epoch) {
+        for channel_watermark in self.node_state.watermarks.values_mut() {
+            *channel_watermark = watermark;
+        }
+        self.node_state.current_watermark = watermark;
+        self.node_state.current_epoch = Epoch::new(epoch.epoch + 1);
+    }
+
+    /// Advances the processing timer to the system time and handles the expired timers
+    fn advance_processing_time(&mut self) -> ArconResult<()> {
+        let now = crate::util::get_system_time();
+        let timeouts = self.processing_timer.get_mut().advance_to_keyed(now)?;
+        if timeouts.is_empty() {
+            return Ok(());
+        }
+
+        for (key, timeout) in timeouts {
+            self.processing_timer.get_mut().set_key(key);
+            unsafe {
+                (*self.operator.get()).handle_processing_timeout(timeout, make_context!(self))?;
+            };
+        }
+
+        // Output is not driven by incoming events, so it is sent right away
+        unsafe {
+            (*self.channel_strategy.get()).flush(self);
+            (*self.side_outputs.get()).flush(self);
+        };
+        Ok(())
+    }
+
+    /// Schedules a Kompact timeout at the deadline of the next processing-time timer
+    ///
+    /// Called whenever the Operator may have scheduled processing-time timers. A timeout
+    /// that is due no later than the next timer is kept, as it schedules the next one once it fires.
+    fn schedule_processing_timeout(&mut self) -> ArconResult<()> {
+        let deadline = match self.processing_timer.get_mut().next_deadline()? {
+            Some(deadline) => deadline,
+            None => return Ok(()),
+        };
+        if let Some((scheduled, timeout)) = self.processing_timeout.take() {
+            if scheduled <= deadline {
+                self.processing_timeout = Some((scheduled, timeout));
+                return Ok(());
+            }
+            self.cancel_timer(timeout);
+        }
+
+        let delay = deadline.saturating_sub(crate::util::get_system_time());
+        let timeout = self.schedule_once(std::time::Duration::from_millis(delay), |c_self, _id| {
+            c_self.processing_timeout = None;
+            if let Err(error) = c_self
+                .advance_processing_time()
+                .and_then(|_| c_self.schedule_processing_timeout())
+            {
+                error!(
+                    c_self.ctx.log(),
+                    "Failed to advance processing time with error {:?}", error
+                );
+            }
+            Handled::Ok
+        });
+        self.processing_timeout = Some((deadline, timeout));
+        Ok(())
+    }
+
     /// Handle a Raw ArconMessage that has either been sent remotely or temporarily stored in the state backend
     #[inline]
     fn handle_raw_msg(&mut self, message: RawArconMessage<OP::IN>) -> ArconResult<()> {
@@ -208,7 +521,6 @@ where
             return Ok(());
         }
 
-        #[cfg(feature = "metrics")]
         self.record_incoming_events(message.events.len() as u64);
 
         self.handle_events(message.sender, message.events)
@@ -227,7 +539,6 @@ where
             return Ok(());
         }
 
-        #[cfg(feature = "metrics")]
         self.record_incoming_events(message.events.len() as u64);
 
         self.handle_events(message.sender, message.events)
@@ -238,11 +549,14 @@ where
         self.node_state.blocked_channels().contains(sender)
     }
 
-    #[cfg(feature = "metrics")]
     /// Mark amount of inbound events
     #[inline(always)]
     fn record_incoming_events(&mut self, total: u64) {
-        self.metrics.inbound_throughput.mark_n(total);
+        #[cfg(feature = "metrics")]
+        self.metrics.get_mut().inbound_throughput.mark_n(total);
+        if let Some(events) = &mut self.inbound_events {
+            *events += total;
+        }
     }
 
     /// Iterate over a batch of ArconEvent's
@@ -251,9 +565,28 @@ where
     where
         I: IntoIterator<Item = ArconEventWrapper<OP::IN>>,
     {
-        'event_loop: for event in events.into_iter() {
+        let unaligned = self.checkpoint_mode == CheckpointMode::Unaligned;
+        let mut events = events.into_iter();
+
+        'event_loop: while let Some(event) = events.next() {
+            if unaligned {
+                // The sender may have been blocked by an earlier event of the batch
+                if self.sender_blocked(&sender) {
+                    let rest = std::iter::once(event).chain(events.by_ref()).collect();
+                    return self.buffer_events(sender, rest);
+                }
+                if let Some(in_flight) = &mut self.in_flight {
+                    in_flight.record(sender, &event, &mut self.in_flight_messages)?;
+                }
+            }
+
             match event.unwrap() {
                 ArconEvent::Element(e) => {
+                    // An element marks an idle sender as active again
+                    if !self.node_state.idle_channels.is_empty() {
+                        self.node_state.idle_channels().remove(&sender);
+                    }
+
                     let watermark = match self.node_state.watermarks().get(&sender) {
                         Some(wm) => wm,
                         None => return arcon_err!("Uninitialised watermark"),
@@ -268,10 +601,13 @@ where
                         // Set key for the current element
                         // TODO: Should use a pre-defined key for Non-Keyed Streams.
                         operator.state().set_key(e.data.get_key());
+                        // Timers scheduled for the element belong to its key
+                        (*self.timer.get()).set_key(e.data.get_key());
                         operator.handle_element(e, make_context!(self))?;
                     };
                 }
                 ArconEvent::Watermark(w) => {
+                    let was_idle = self.node_state.idle_channels().remove(&sender);
                     let watermark = match self.node_state.watermarks().get(&sender) {
                         Some(wm) => wm,
                         None => return arcon_err!("Uninitialised watermark"),
@@ -282,7 +618,8 @@ where
 
                     // Insert the watermark and try early return
                     if let Some(old) = self.node_state.watermarks().insert(sender, w) {
-                        if old > self.node_state.current_watermark {
+                        // The minimum did not include an idle sender
+                        if !was_idle && old > self.node_state.current_watermark {
                             continue 'event_loop;
                         }
                     }
@@ -292,32 +629,35 @@ where
                         continue 'event_loop;
                     }
 
-                    let new_watermark = *self.node_state.watermarks().values().min().unwrap();
-
-                    if new_watermark.timestamp > self.node_state.current_watermark.timestamp {
-                        self.node_state.current_watermark = new_watermark;
-
-                        unsafe {
-                            let timer = &mut (*self.timer.get());
-                            let timeouts = timer.advance_to(new_watermark.timestamp)?;
-                            for timeout in timeouts {
-                                (*self.operator.get())
-                                    .handle_timeout(timeout, make_context!(self))?;
-                            }
-                        };
-
-                        // Set current watermark
-                        #[cfg(feature = "metrics")]
-                        {
-                            self.metrics.watermark = new_watermark;
-                            self.metrics.watermark_counter.inc();
-                        }
+                    self.advance_watermark()?;
+                }
+                ArconEvent::Idle(w) => {
+                    if !self.node_state.idle_channels().insert(sender) {
+                        continue 'event_loop;
+                    }
+                    if let Some(watermark) = self.node_state.watermarks().get_mut(&sender) {
+                        *watermark = w.max(*watermark);
+                    }
 
-                        // Forward the watermark
+                    if self.node_state.idle_channels.len() == self.node_state.in_channels.len() {
+                        // Downstream nodes are not held back while all senders are idle
+                        let current = self.node_state.current_watermark;
                         unsafe {
-                            (*self.channel_strategy.get())
-                                .add(ArconEvent::Watermark(new_watermark), self);
+                            (*self.channel_strategy.get()).add(ArconEvent::Idle(current), self);
+                            (*self.side_outputs.get()).forward(SideControl::Idle(current), self);
                         };
+                    } else {
+                        self.advance_watermark()?;
+                    }
+                }
+                ArconEvent::Epoch(e) if unaligned => {
+                    debug!(self.ctx.log(), "Got Epoch {:?}", e);
+                    if self.handle_unaligned_epoch(sender, e)? {
+                        // The marker is processed again once the sender is unblocked
+                        let rest = std::iter::once(ArconEvent::Epoch(e).into())
+                            .chain(events.by_ref())
+                            .collect();
+                        return self.buffer_events(sender, rest);
                     }
                 }
                 ArconEvent::Epoch(e) => {
@@ -332,38 +672,13 @@ where
                     // If all senders blocked we can transition to new Epoch
                     if self.node_state.blocked_channels().len() == self.node_state.in_channels.len()
                     {
-                        // persist internal node state for this node
-                        self.node_state.persist()?;
-
-                        unsafe {
-                            // persist timer
-                            let timer = &mut (*self.timer.get());
-                            timer.persist()?;
-
-                            // persist possible operator state..
-                            (*self.operator.get()).persist()?;
-                        };
-
-                        // Create checkpoint request and send it off to the NodeManager
-                        let request = CheckpointRequest::new(
-                            self.node_state.id,
-                            self.node_state.current_epoch,
-                        );
-                        self.node_manager_port.trigger(Checkpoint(request));
-
-                        // Forward the Epoch
-                        unsafe {
-                            (*self.channel_strategy.get())
-                                .add(ArconEvent::Epoch(self.node_state.current_epoch), self);
-                        };
-
-                        // Update current epoch
-                        self.node_state.current_epoch.epoch += 1;
+                        self.checkpoint()?;
                     }
                 }
                 ArconEvent::Death(s) => {
                     // We are instructed to shutdown....
                     unsafe {
+                        (*self.side_outputs.get()).forward(SideControl::Death(s.clone()), self);
                         (*self.channel_strategy.get()).add(ArconEvent::Death(s), self);
                     };
                     self.ctx.suicide(); // TODO: is suicide enough?
@@ -374,17 +689,219 @@ where
         Ok(())
     }
 
+    /// Advances the watermark of the Node to the minimum watermark of its active senders
+    ///
+    /// Fires the expired event-time timers and forwards the new watermark.
+    fn advance_watermark(&mut self) -> ArconResult<()> {
+        let idle_channels = &self.node_state.idle_channels;
+        let new_watermark = match self
+            .node_state
+            .watermarks
+            .iter()
+            .filter(|(sender, _)| !idle_channels.contains(sender))
+            .map(|(_, watermark)| *watermark)
+            .min()
+        {
+            Some(watermark) => watermark,
+            None => return Ok(()),
+        };
+
+        if new_watermark.timestamp > self.node_state.current_watermark.timestamp {
+            self.node_state.current_watermark = new_watermark;
+
+            unsafe {
+                let timer = &mut (*self.timer.get());
+                let timeouts = timer.advance_to_keyed(new_watermark.timestamp)?;
+                for (key, timeout) in timeouts {
+                    (*self.timer.get()).set_key(key);
+                    (*self.operator.get()).handle_timeout(timeout, make_context!(self))?;
+                }
+            };
+
+            // Set current watermark
+            #[cfg(feature = "metrics")]
+            {
+                let metrics = self.metrics.get_mut();
+                metrics.watermark = new_watermark;
+                metrics.watermark_counter.inc();
+            }
+
+            // Forward the watermark
+            unsafe {
+                (*self.channel_strategy.get()).add(ArconEvent::Watermark(new_watermark), self);
+                (*self.side_outputs.get()).forward(SideControl::Watermark(new_watermark), self);
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Persists the state of the Node, requests a checkpoint and forwards the current Epoch
+    fn checkpoint(&mut self) -> ArconResult<()> {
+        unsafe {
+            // Let the Operator finish the epoch, e.g., by pre-committing its output
+            let epoch = self.node_state.current_epoch.epoch;
+            (*self.operator.get()).handle_epoch(epoch, make_context!(self))?;
+        };
+
+        // persist internal node state for this node
+        self.node_state.persist()?;
+
+        unsafe {
+            // persist timers
+            (*self.timer.get()).persist()?;
+            (*self.processing_timer.get()).persist()?;
+
+            // persist possible operator state..
+            (*self.operator.get()).persist()?;
+        };
+
+        // Create checkpoint request and send it off to the NodeManager
+        let request = CheckpointRequest::new(
+            self.node_state.id,
+            self.node_state.current_epoch,
+            self.node_state.current_watermark,
+        );
+        self.node_manager_port.trigger(Checkpoint(request));
+
+        // Forward the Epoch
+        unsafe {
+            (*self.channel_strategy.get())
+                .add(ArconEvent::Epoch(self.node_state.current_epoch), self);
+            (*self.side_outputs.get())
+                .forward(SideControl::Epoch(self.node_state.current_epoch), self);
+        };
+
+        // Update current epoch
+        self.node_state.current_epoch.epoch += 1;
+        Ok(())
+    }
+
+    /// Lets the Operator act on the commit of `epoch`
+    pub(crate) fn commit_epoch(&mut self, epoch: Epoch) -> ArconResult<()> {
+        unsafe {
+            (*self.operator.get()).handle_epoch_commit(epoch.epoch, make_context!(self))?;
+        };
+        Ok(())
+    }
+
+    /// Replays the commit of the `epoch` that the Node was restored from
+    ///
+    /// Called by the NodeManager before the Node is started.
+    pub(crate) fn replay_commit(&mut self, epoch: Epoch) -> ArconResult<()> {
+        self.commit_epoch(epoch)?;
+        unsafe {
+            (*self.operator.get()).handle_restore(epoch.epoch, make_context!(self))?;
+        };
+        Ok(())
+    }
+
+    /// Handles an epoch marker in unaligned mode
+    ///
+    /// The Node checkpoints on the first marker of an epoch and forwards it right away,
+    /// ahead of the messages that are still to be processed. All channels are blocked
+    /// until the backend has taken its snapshot, after which the messages of the channels
+    /// whose marker is outstanding are recorded as in-flight messages of the checkpoint.
+    ///
+    /// Returns true if the sender is blocked until the in-flight messages of the previous
+    /// epoch have been recorded and the marker has to be processed again.
+    fn handle_unaligned_epoch(&mut self, sender: NodeID, epoch: Epoch) -> ArconResult<bool> {
+        if let Some(in_flight) = &mut self.in_flight {
+            if in_flight.marker == epoch {
+                in_flight.pending.remove(&sender);
+                if in_flight.pending.is_empty() {
+                    self.complete_in_flight()?;
+                    // Senders that have moved on to the next epoch may continue
+                    self.unblock_channels()?;
+                }
+                return Ok(false);
+            }
+        }
+
+        if epoch < self.node_state.current_epoch {
+            return Ok(false);
+        }
+
+        if self.in_flight.is_some() {
+            self.node_state.blocked_channels().insert(sender);
+            return Ok(true);
+        }
+
+        let pending: FxHashSet<NodeID> = self
+            .node_state
+            .in_channels
+            .iter()
+            .filter(|id| **id != sender)
+            .copied()
+            .collect();
+        // Nothing is in flight for a Node with a single channel
+        let complete = pending.is_empty();
+        self.in_flight = Some(InFlight {
+            epoch: self.node_state.current_epoch,
+            marker: epoch,
+            pending,
+            run: None,
+        });
+        self.checkpoint()?;
+
+        // Nothing is processed until the snapshot has been taken
+        let in_channels = self.node_state.in_channels.clone();
+        self.node_state.blocked_channels().extend(in_channels);
+
+        if complete {
+            self.complete_in_flight()?;
+        }
+
+        Ok(false)
+    }
+
+    /// Sends the recorded in-flight messages to the NodeManager
+    fn complete_in_flight(&mut self) -> ArconResult<()> {
+        if let Some(in_flight) = self.in_flight.take() {
+            if let Some(run) = in_flight.run {
+                self.in_flight_messages.append(run)?;
+            }
+            let messages = encode_in_flight(&self.in_flight_messages.consume()?)?;
+            self.node_manager_port.trigger(NodeManagerEvent::InFlight(
+                self.node_state.id,
+                in_flight.epoch,
+                messages,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Appends the remaining events of a batch to the message buffer
+    fn buffer_events(
+        &mut self,
+        sender: NodeID,
+        events: Vec<ArconEventWrapper<OP::IN>>,
+    ) -> ArconResult<()> {
+        if !events.is_empty() {
+            self.node_state
+                .message_buffer()
+                .append(RawArconMessage { events, sender })?;
+        }
+        Ok(())
+    }
+
     #[inline]
     fn complete_epoch(&mut self) -> ArconResult<()> {
-        // flush the blocked_channels list
-        self.node_state.blocked_channels().clear();
-
         #[cfg(feature = "metrics")]
         {
-            self.metrics.epoch = self.node_state.current_epoch;
-            self.metrics.epoch_counter.inc();
+            let metrics = self.metrics.get_mut();
+            metrics.epoch = self.node_state.current_epoch;
+            metrics.epoch_counter.inc();
         }
 
+        self.unblock_channels()
+    }
+
+    /// Unblocks all channels and processes the messages that were buffered in the meantime
+    fn unblock_channels(&mut self) -> ArconResult<()> {
+        // flush the blocked_channels list
+        self.node_state.blocked_channels().clear();
+
         // Iterate over the message-buffer until empty
         for message in self.node_state.message_buffer().consume()? {
             self.handle_events(message.sender, message.events)?;
@@ -405,6 +922,37 @@ where
             "Started Arcon Node {} with Node ID {:?}", self.descriptor, self.node_state.id
         );
 
+        if let Err(error) = self.fire_expired_timers() {
+            error!(
+                self.ctx.log(),
+                "Failed to fire expired timers with error {:?}", error
+            );
+        }
+
+        // Fires the processing-time timers that were restored or reassigned to the Node
+        if let Err(error) = self.schedule_processing_timeout() {
+            error!(
+                self.ctx.log(),
+                "Failed to schedule processing time with error {:?}", error
+            );
+        }
+
+        // Start periodic timer reporting the inbound throughput used for rebalancing
+        if self.inbound_events.is_some() {
+            if let Some(interval) = self.ctx().config()["node_metrics_interval"].as_i64() {
+                let time_dur = std::time::Duration::from_millis(interval as u64);
+                self.schedule_periodic(time_dur, time_dur, move |c_self, _id| {
+                    let events = c_self.inbound_events.replace(0).unwrap_or(0);
+                    let throughput = events as f64 / time_dur.as_secs_f64();
+                    let id = c_self.node_state.id;
+                    c_self
+                        .node_manager_port
+                        .trigger(NodeManagerEvent::Throughput(id, throughput));
+                    Handled::Ok
+                });
+            }
+        }
+
         #[cfg(feature = "metrics")]
         {
             // Start periodic timer reporting Node metrics
@@ -413,7 +961,7 @@ where
                 self.schedule_periodic(time_dur, time_dur, |c_self, _id| {
                     c_self.node_manager_port.trigger(NodeManagerEvent::Metrics(
                         c_self.node_state.id,
-                        c_self.metrics.clone(),
+                        c_self.metrics.get_mut().clone(),
                     ));
                     Handled::Ok
                 });
@@ -439,6 +987,21 @@ where
                     );
                 }
             }
+            NodeEvent::EpochCommit(epoch) => {
+                if let Err(error) = self.commit_epoch(epoch) {
+                    error!(
+                        self.ctx.log(),
+                        "Failed to commit epoch {:?} with error {:?}", epoch, error
+                    );
+                }
+            }
+        }
+        // Buffered messages and epoch commits may schedule processing-time timers
+        if let Err(error) = self.schedule_processing_timeout() {
+            error!(
+                self.ctx.log(),
+                "Failed to schedule processing time with error {:?}", error
+            );
         }
         Handled::Ok
     }
@@ -463,7 +1026,10 @@ where
     type Message = ArconMessage<OP::IN>;
 
     fn receive_local(&mut self, msg: Self::Message) -> Handled {
-        if let Err(err) = self.handle_message(msg) {
+        let handled = self
+            .handle_message(msg)
+            .and_then(|_| self.schedule_processing_timeout());
+        if let Err(err) = handled {
             error!(self.ctx.log(), "Failed to handle message: {}", err);
         }
         Handled::Ok
@@ -488,7 +1054,10 @@ where
 
         match arcon_msg {
             Ok(m) => {
-                if let Err(err) = self.handle_raw_msg(m) {
+                let handled = self
+                    .handle_raw_msg(m)
+                    .and_then(|_| self.schedule_processing_timeout());
+                if let Err(err) = handled {
                     error!(self.ctx.log(), "Failed to handle node message: {}", err);
                 }
             }
@@ -513,6 +1082,12 @@ mod tests {
     use std::{sync::Arc, thread, time};
 
     fn node_test_setup() -> (ActorRef<ArconMessage<i32>>, Arc<Component<DebugNode<i32>>>) {
+        node_test_setup_with(CheckpointMode::Aligned)
+    }
+
+    fn node_test_setup_with(
+        mode: CheckpointMode,
+    ) -> (ActorRef<ArconMessage<i32>>, Arc<Component<DebugNode<i32>>>) {
         fn filter_fn(x: &i32) -> bool {
             *x >= 0
         }
@@ -521,6 +1096,7 @@ mod tests {
 
         fn setup<OP: Operator<IN = i32, OUT = i32> + 'static>(
             op: OP,
+            mode: CheckpointMode,
         ) -> (ActorRef<ArconMessage<i32>>, Arc<Component<DebugNode<i32>>>) {
             // Returns a filter Node with input channels: sender1..sender3
             // And a debug sink receiving its results
@@ -555,7 +1131,8 @@ mod tests {
                 epoch_manager_ref,
                 in_channels.clone(),
                 backend.clone(),
-            );
+            )
+            .with_checkpoint_mode(mode);
             let node_manager_comp = pipeline.ctrl_system().create(|| nm);
 
             pipeline
@@ -570,7 +1147,9 @@ mod tests {
                 op,
                 NodeState::new(NodeID::new(0), in_channels, backend.clone()),
                 backend,
-            );
+            )
+            .unwrap()
+            .with_checkpoint_mode(mode);
 
             let filter_comp = pipeline.data_system().create(|| node);
             let required_ref = filter_comp.on_definition(|cd| cd.node_manager_port.share());
@@ -594,7 +1173,7 @@ mod tests {
             (filter_ref, sink)
         }
 
-        setup(filter)
+        setup(filter, mode)
     }
 
     fn watermark(time: u64, sender: u32) -> ArconMessage<i32> {
@@ -605,6 +1184,10 @@ mod tests {
         ArconMessage::element(data, Some(time), sender.into())
     }
 
+    fn idle(time: u64, sender: u32) -> ArconMessage<i32> {
+        ArconMessage::idle(time, sender.into())
+    }
+
     fn epoch(epoch: u64, sender: u32) -> ArconMessage<i32> {
         ArconMessage::epoch(epoch, sender.into())
     }
@@ -663,6 +1246,26 @@ mod tests {
         });
     }
 
+    #[test]
+    fn node_idle_channels() {
+        let (node_ref, sink) = node_test_setup();
+        node_ref.tell(watermark(4, 1));
+        node_ref.tell(watermark(2, 2));
+        // Sender 3 does not hold back the watermark once it is idle
+        node_ref.tell(idle(0, 3));
+        node_ref.tell(idle(2, 2));
+        // An element marks sender 3 as active again
+        node_ref.tell(element(1, 5, 3));
+        node_ref.tell(watermark(6, 1));
+
+        wait(1);
+        sink.on_definition(|cd| {
+            let watermarks: Vec<u64> = cd.watermarks.iter().map(|w| w.timestamp).collect();
+            assert_eq!(watermarks, vec![2, 4]);
+            assert_eq!(cd.data.len(), 1);
+        });
+    }
+
     #[test]
     fn node_epoch_block() {
         let (node_ref, sink) = node_test_setup();
@@ -747,4 +1350,44 @@ mod tests {
             assert_eq!(data_len, 6);
         });
     }
+
+    #[test]
+    fn node_unaligned_epoch() {
+        let (node_ref, sink) = node_test_setup_with(CheckpointMode::Unaligned);
+        node_ref.tell(element(11, 1, 1));
+        node_ref.tell(epoch(1, 1)); // checkpoint and forward the marker right away
+        node_ref.tell(element(12, 1, 1)); // after the marker of sender1
+        node_ref.tell(element(21, 1, 2)); // in flight
+        node_ref.tell(element(31, 1, 3)); // in flight
+        node_ref.tell(epoch(1, 2));
+        node_ref.tell(epoch(2, 1)); // blocked until the marker of sender3 arrives
+        node_ref.tell(element(13, 1, 1)); // blocked
+        node_ref.tell(epoch(1, 3));
+
+        wait(1);
+        node_ref.tell(death(3)); // send death marker on unblocked channel to flush
+        wait(1);
+
+        sink.on_definition(|cd| {
+            // Both markers overtook the elements of the other channels
+            assert_eq!(cd.epochs.len(), 2);
+            let data: Vec<i32> = cd.data.iter().map(|e| e.data).collect();
+            assert_eq!(data, vec![11, 12, 21, 31, 13]);
+        });
+    }
+
+    #[test]
+    fn in_flight_serde_test() {
+        let messages: Vec<RawArconMessage<i32>> = vec![
+            element(1, 1, 1).into(),
+            watermark(2, 2).into(),
+            element(3, 4, 1).into(),
+        ];
+        let bytes = encode_in_flight(&messages).unwrap();
+        let decoded = decode_in_flight::<i32>(&bytes).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[1].sender, NodeID::new(2));
+        assert_eq!(encode_in_flight(&decoded).unwrap(), bytes);
+        assert!(decode_in_flight::<i32>(&bytes[..bytes.len() - 1]).is_err());
+    }
 }
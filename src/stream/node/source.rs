@@ -7,7 +7,7 @@ use crate::{
     stream::{
         channel::strategy::ChannelStrategy,
         source::{NodeContext, Source, SourceContext},
-        time::ArconTime,
+        time::{ArconTime, WatermarkStrategy},
     },
 };
 use kompact::prelude::*;
@@ -37,8 +37,16 @@ where
 {
     /// Component context
     ctx: ComponentContext<Self>,
+    /// Index of this source component within its SourceManager
+    index: usize,
     manager_port: RequiredPort<SourceManagerPort>,
     node_context: RefCell<NodeContext<S>>,
+    /// Time in milliseconds without any output after which the source is idle
+    idle_timeout: Option<u64>,
+    /// System time at which the source last had output
+    last_active: u64,
+    /// Whether the source has been marked as idle
+    idle: bool,
     loopback_send: RequiredPort<LoopbackPort>,
     loopback_receive: ProvidedPort<LoopbackPort>,
     source: RefCell<S>,
@@ -48,15 +56,21 @@ impl<S> SourceNode<S>
 where
     S: Source,
 {
-    pub fn new(source: S, channel_strategy: ChannelStrategy<S::Data>) -> Self {
+    pub fn new(
+        index: usize,
+        source: S,
+        channel_strategy: ChannelStrategy<S::Data>,
+        watermark_strategy: WatermarkStrategy,
+        idle_timeout: Option<u64>,
+    ) -> Self {
         Self {
             ctx: ComponentContext::uninitialised(),
+            index,
             manager_port: RequiredPort::uninitialised(),
-            node_context: RefCell::new(NodeContext {
-                channel_strategy,
-                watermark: 0,
-                ended: false,
-            }),
+            node_context: RefCell::new(NodeContext::new(channel_strategy, watermark_strategy)),
+            idle_timeout,
+            last_active: crate::util::get_system_time(),
+            idle: false,
             loopback_send: RequiredPort::uninitialised(),
             loopback_receive: ProvidedPort::uninitialised(),
             source: RefCell::new(source),
@@ -66,14 +80,29 @@ where
     pub fn handle_source_event(&mut self, event: SourceEvent) {
         match event {
             SourceEvent::Epoch(epoch) => {
+                // The position is taken before the marker is forwarded so that
+                // it matches the elements that were sent within the epoch.
+                let position = self.source.borrow().position();
+                self.manager_port
+                    .trigger(SourceManagerEvent::Epoch(self.index, epoch, position));
                 self.node_context
                     .borrow_mut()
                     .channel_strategy
                     .add(ArconEvent::Epoch(epoch), self);
             }
             SourceEvent::Watermark(time) => {
+                // A source that has ended has already sent its final watermark
+                if self.node_context.borrow().ended {
+                    return;
+                }
+
                 let wm = match time {
-                    ArconTime::Event => Watermark::new(self.node_context.borrow().watermark),
+                    ArconTime::Event => {
+                        if self.check_idle() {
+                            return;
+                        }
+                        Watermark::new(self.node_context.borrow().event_watermark())
+                    }
                     ArconTime::Process => {
                         let system_time = crate::util::get_system_time();
                         Watermark::new(system_time)
@@ -96,6 +125,46 @@ where
     }
 }
 
+impl<S> SourceNode<S>
+where
+    S: Source,
+{
+    /// Returns true if the source is idle and should not send a watermark
+    ///
+    /// Marks the source as idle once it has had no output for its idle timeout.
+    /// Sources whose watermarks follow the system time are never idle.
+    fn check_idle(&mut self) -> bool {
+        let timeout = match self.idle_timeout {
+            Some(timeout) => timeout,
+            None => return false,
+        };
+        if self.node_context.borrow().watermark_strategy == WatermarkStrategy::IngestionTime {
+            return false;
+        }
+
+        let now = crate::util::get_system_time();
+        if std::mem::take(&mut self.node_context.borrow_mut().active) {
+            self.last_active = now;
+            self.idle = false;
+            return false;
+        }
+        if self.idle {
+            return true;
+        }
+        if now.saturating_sub(self.last_active) < timeout {
+            return false;
+        }
+
+        self.idle = true;
+        let wm = Watermark::new(self.node_context.borrow().watermark);
+        self.node_context
+            .borrow_mut()
+            .channel_strategy
+            .add(ArconEvent::Idle(wm), self);
+        true
+    }
+}
+
 impl<S> ComponentLifecycle for SourceNode<S>
 where
     S: Source,
@@ -117,7 +186,16 @@ where
             &mut self.node_context.borrow_mut(),
         ));
         if self.node_context.borrow().ended {
-            self.manager_port.trigger(SourceManagerEvent::End);
+            // Advance event time to the end so that downstream nodes
+            // are not held back by a source without further input.
+            let wm = Watermark::new(u64::MAX);
+            self.node_context.borrow_mut().watermark = wm.timestamp;
+            self.node_context
+                .borrow_mut()
+                .channel_strategy
+                .add(ArconEvent::Watermark(wm), self);
+            self.manager_port
+                .trigger(SourceManagerEvent::End(self.index));
         } else {
             self.loopback_send.trigger(ProcessSource);
         }
@@ -0,0 +1,354 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::{
+    data::{ser_id, ArconElement, ArconType, VersionId},
+    index::{EagerHashTable, IndexOps},
+    stream::operator::{window::WindowContext, Operator, OperatorContext},
+};
+#[cfg(feature = "unsafe_flight")]
+use abomonation_derive::*;
+use arcon_error::*;
+use arcon_state::{backend::handles::ActiveHandle, Backend, Handle, VecState};
+use kompact::prelude::{ComponentDefinition, SerId};
+use prost::{Message, Oneof};
+#[cfg(feature = "arcon_serde")]
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Defines the windows that elements of two streams are joined within
+#[derive(Clone, Copy, Debug)]
+pub struct JoinWindow {
+    length: u64,
+    late_arrival_time: u64,
+}
+
+impl JoinWindow {
+    /// Join elements that fall into the same tumbling event-time window
+    pub fn tumbling(length: u64, late_arrival_time: u64) -> Self {
+        assert!(length > 0, "Window length must be larger than 0");
+        JoinWindow {
+            length,
+            late_arrival_time,
+        }
+    }
+}
+
+/// An element from either side of a join
+#[cfg_attr(feature = "arcon_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "unsafe_flight", derive(Abomonation))]
+#[derive(Oneof, Clone)]
+#[cfg_attr(feature = "arcon_serde", serde(bound = "L: ArconType, R: ArconType"))]
+pub enum JoinSide<L: ArconType, R: ArconType> {
+    #[prost(message, tag = "2")]
+    Left(L),
+    #[prost(message, tag = "3")]
+    Right(R),
+}
+
+/// Input type of a [WindowJoin]
+///
+/// Carries an element of either stream together with its join key.
+#[cfg_attr(feature = "arcon_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "unsafe_flight", derive(Abomonation))]
+#[derive(Message, Clone)]
+#[cfg_attr(feature = "arcon_serde", serde(bound = "L: ArconType, R: ArconType"))]
+pub struct JoinInput<L: ArconType, R: ArconType> {
+    #[prost(uint64, tag = "1")]
+    pub key: u64,
+    #[prost(oneof = "JoinSide::<L, R>", tags = "2, 3")]
+    pub side: Option<JoinSide<L, R>>,
+}
+
+impl<L: ArconType, R: ArconType> JoinInput<L, R> {
+    /// Creates a JoinInput for an element of the left stream
+    pub fn left(key: u64, data: L) -> Self {
+        JoinInput {
+            key,
+            side: Some(JoinSide::Left(data)),
+        }
+    }
+    /// Creates a JoinInput for an element of the right stream
+    pub fn right(key: u64, data: R) -> Self {
+        JoinInput {
+            key,
+            side: Some(JoinSide::Right(data)),
+        }
+    }
+}
+
+impl<L: ArconType, R: ArconType> ArconType for JoinInput<L, R> {
+    #[cfg(feature = "unsafe_flight")]
+    const UNSAFE_SER_ID: SerId = ser_id::UNSAFE_JOIN_INPUT_ID;
+    const RELIABLE_SER_ID: SerId = ser_id::RELIABLE_JOIN_INPUT_ID;
+    const VERSION_ID: VersionId = 1;
+
+    fn get_key(&self) -> u64 {
+        self.key
+    }
+}
+
+/// A pair of elements that matched on key and window
+#[cfg_attr(feature = "arcon_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "unsafe_flight", derive(Abomonation))]
+#[derive(Message, Clone)]
+#[cfg_attr(feature = "arcon_serde", serde(bound = "L: ArconType, R: ArconType"))]
+pub struct Joined<L: ArconType, R: ArconType> {
+    #[prost(uint64, tag = "1")]
+    pub key: u64,
+    #[prost(message, required, tag = "2")]
+    pub left: L,
+    #[prost(message, required, tag = "3")]
+    pub right: R,
+}
+
+impl<L: ArconType, R: ArconType> ArconType for Joined<L, R> {
+    #[cfg(feature = "unsafe_flight")]
+    const UNSAFE_SER_ID: SerId = ser_id::UNSAFE_JOINED_ID;
+    const RELIABLE_SER_ID: SerId = ser_id::RELIABLE_JOINED_ID;
+    const VERSION_ID: VersionId = 1;
+
+    fn get_key(&self) -> u64 {
+        self.key
+    }
+}
+
+#[cfg_attr(feature = "arcon_serde", derive(Serialize, Deserialize))]
+#[derive(Message, PartialEq, Clone)]
+pub struct JoinTimeout {
+    #[prost(uint64, tag = "1")]
+    key: u64,
+    #[prost(uint64, tag = "2")]
+    index: u64,
+    #[prost(uint64, tag = "3")]
+    timestamp: u64,
+}
+
+/// A keyed event-time join of two streams
+///
+/// Elements of both sides are buffered per key and window. Once the watermark
+/// passes the end of a window (plus the allowed lateness), every pair of
+/// left and right elements in that window is emitted and the buffers are cleared.
+pub struct WindowJoin<L, R, B>
+where
+    L: ArconType,
+    R: ArconType,
+    B: Backend,
+{
+    window: JoinWindow,
+    left_buffer: ActiveHandle<B, VecState<L>, u64, u64>,
+    right_buffer: ActiveHandle<B, VecState<R>, u64, u64>,
+    active_windows: EagerHashTable<WindowContext, (), B>,
+    op_state: (),
+}
+
+impl<L, R, B> WindowJoin<L, R, B>
+where
+    L: ArconType,
+    R: ArconType,
+    B: Backend,
+{
+    pub fn new(backend: Arc<B>, window: JoinWindow) -> Self {
+        let mut left_buffer = Handle::vec("_join_left_buffer")
+            .with_item_key(0)
+            .with_namespace(0);
+        backend.register_vec_handle(&mut left_buffer);
+
+        let mut right_buffer = Handle::vec("_join_right_buffer")
+            .with_item_key(0)
+            .with_namespace(0);
+        backend.register_vec_handle(&mut right_buffer);
+
+        WindowJoin {
+            window,
+            left_buffer: left_buffer.activate(backend.clone()),
+            right_buffer: right_buffer.activate(backend.clone()),
+            active_windows: EagerHashTable::new("_join_active_windows", backend),
+            op_state: (),
+        }
+    }
+
+    /// Emit all matching pairs of a window and clean up its state
+    fn fire(
+        &mut self,
+        timeout: JoinTimeout,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let JoinTimeout {
+            key,
+            index,
+            timestamp,
+        } = timeout;
+
+        self.left_buffer.set_item_key(key);
+        self.left_buffer.set_namespace(index);
+        self.right_buffer.set_item_key(key);
+        self.right_buffer.set_namespace(index);
+
+        let lefts = self.left_buffer.get()?;
+        let rights = self.right_buffer.get()?;
+
+        for left in &lefts {
+            for right in &rights {
+                let joined = Joined {
+                    key,
+                    left: left.clone(),
+                    right: right.clone(),
+                };
+                ctx.output(ArconElement::with_timestamp(joined, timestamp));
+            }
+        }
+
+        self.left_buffer.clear()?;
+        self.right_buffer.clear()?;
+        self.active_windows
+            .remove(&WindowContext::new(key, index))?;
+
+        Ok(())
+    }
+}
+
+impl<L, R, B> Operator for WindowJoin<L, R, B>
+where
+    L: ArconType,
+    R: ArconType,
+    B: Backend,
+{
+    type IN = JoinInput<L, R>;
+    type OUT = Joined<L, R>;
+    type TimerState = JoinTimeout;
+    type OperatorState = ();
+
+    fn handle_element(
+        &mut self,
+        element: ArconElement<Self::IN>,
+        mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let ts = element.timestamp.unwrap_or(1);
+        let time = ctx.current_time()?;
+
+        if ts < time.saturating_sub(self.window.late_arrival_time) {
+            // Late arrival: early return
+            return Ok(());
+        }
+
+        let JoinInput { key, side } = element.data;
+        let index = ts / self.window.length;
+
+        match side {
+            Some(JoinSide::Left(data)) => {
+                self.left_buffer.set_item_key(key);
+                self.left_buffer.set_namespace(index);
+                self.left_buffer.append(data)?;
+            }
+            Some(JoinSide::Right(data)) => {
+                self.right_buffer.set_item_key(key);
+                self.right_buffer.set_namespace(index);
+                self.right_buffer.append(data)?;
+            }
+            None => return Ok(()),
+        }
+
+        let window_ctx = WindowContext::new(key, index);
+        if !self.active_windows.contains(&window_ctx)? {
+            self.active_windows.put(window_ctx, ())?;
+
+            let window_end = (index + 1) * self.window.length;
+            let timeout = JoinTimeout {
+                key,
+                index,
+                timestamp: window_end,
+            };
+
+            // The window may already have expired if the element arrived late
+            if let Err(timeout) = ctx.schedule_at(
+                window_ctx,
+                window_end + self.window.late_arrival_time,
+                timeout,
+            ) {
+                self.fire(timeout, &mut ctx)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_timeout(
+        &mut self,
+        timeout: Self::TimerState,
+        mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        self.fire(timeout, &mut ctx)
+    }
+
+    fn persist(&mut self) -> OperatorResult<()> {
+        self.active_windows.persist()
+    }
+
+    fn state(&mut self) -> &mut Self::OperatorState {
+        &mut self.op_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::ArconMessage,
+        pipeline::*,
+        stream::{
+            node::debug::DebugNode,
+            operator::{side_output::SideOutputs, window::tests::window_test_setup},
+        },
+    };
+    use kompact::prelude::{ActorRefStrong, Component};
+    use std::{thread, time};
+
+    fn join_test_setup(
+        pipeline: &mut Pipeline,
+        length: u64,
+    ) -> (
+        ActorRefStrong<ArconMessage<JoinInput<u64, u32>>>,
+        Arc<Component<DebugNode<Joined<u64, u32>>>>,
+    ) {
+        window_test_setup(
+            pipeline,
+            |backend| WindowJoin::new(backend, JoinWindow::tumbling(length, 0)),
+            SideOutputs::default(),
+        )
+    }
+
+    fn left(key: u64, data: u64, ts: u64) -> ArconMessage<JoinInput<u64, u32>> {
+        ArconMessage::element(JoinInput::left(key, data), Some(ts), 0.into())
+    }
+
+    fn right(key: u64, data: u32, ts: u64) -> ArconMessage<JoinInput<u64, u32>> {
+        ArconMessage::element(JoinInput::right(key, data), Some(ts), 0.into())
+    }
+
+    #[test]
+    fn window_join_test() {
+        let mut pipeline = Pipeline::default();
+        let (join_ref, sink) = join_test_setup(&mut pipeline, 10);
+        thread::sleep(time::Duration::from_secs(1));
+
+        join_ref.tell(left(1, 100, 1));
+        join_ref.tell(left(1, 101, 2));
+        join_ref.tell(right(1, 10, 3));
+        // different key, no match on the left side
+        join_ref.tell(right(2, 20, 4));
+        // same key, but in the next window
+        join_ref.tell(right(1, 11, 12));
+        join_ref.tell(ArconMessage::watermark(20, 0.into()));
+
+        thread::sleep(time::Duration::from_secs(1));
+        sink.on_definition(|cd| {
+            assert_eq!(cd.data.len(), 2);
+            for element in &cd.data {
+                assert_eq!(element.data.key, 1);
+                assert_eq!(element.data.right, 10);
+                assert_eq!(element.timestamp, Some(10));
+            }
+        });
+    }
+}
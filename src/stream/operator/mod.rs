@@ -3,15 +3,24 @@
 
 /// Available function operators
 pub mod function;
+/// Available join operators
+pub mod join;
+/// Side outputs of operators
+pub mod side_output;
 /// Available sink operators
 pub mod sink;
 /// Available window operators
 pub mod window;
 
+#[cfg(feature = "metrics")]
+use crate::stream::node::NodeMetrics;
 use crate::{
     data::{ArconElement, ArconEvent, ArconType},
     index::{ArconState, Timer},
-    stream::channel::strategy::ChannelStrategy,
+    stream::{
+        channel::strategy::ChannelStrategy,
+        operator::side_output::{OutputTag, SideOutputs},
+    },
 };
 use arcon_error::*;
 use arcon_state::Backend;
@@ -46,9 +55,55 @@ pub trait Operator: Send + Sized {
         ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
     ) -> OperatorResult<()>;
 
+    /// Determines how the `Operator` handles processing-time timeouts it registered earlier
+    ///
+    /// Processing-time timers are scheduled through [OperatorContext::schedule_processing_time].
+    fn handle_processing_timeout(
+        &mut self,
+        _timeout: Self::TimerState,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        Ok(())
+    }
+
     /// Determines how the `Operator` persists its state
     fn persist(&mut self) -> OperatorResult<()>;
 
+    /// Called when the Node checkpoints `epoch`, right before the state of the `Operator` is persisted
+    ///
+    /// Elements that are handled after this call belong to the next epoch.
+    fn handle_epoch(
+        &mut self,
+        _epoch: u64,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        Ok(())
+    }
+
+    /// Called once `epoch` has been committed by all operators of the pipeline
+    ///
+    /// A Node that is restored from a checkpoint is called with the restored epoch
+    /// before it handles any elements, as the commit may not have been observed.
+    fn handle_epoch_commit(
+        &mut self,
+        _epoch: u64,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        Ok(())
+    }
+
+    /// Called once a Node has been restored from the checkpoint of `epoch`
+    ///
+    /// Follows the replayed [Operator::handle_epoch_commit] of `epoch`. The Nodes of an
+    /// operator are restored one after the other, before any of them handles elements.
+    fn handle_restore(
+        &mut self,
+        _epoch: u64,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        Ok(())
+    }
+
     /// A get function to the operator's state.
     ///
     /// Use the ``ignore_state!()`` macro to indicate its an empty state.
@@ -98,10 +153,19 @@ where
 {
     /// Channel Strategy that is used to pass on events
     channel_strategy: &'c mut ChannelStrategy<OP::OUT>,
+    /// Side outputs that elements may be routed to
+    side_outputs: &'c mut SideOutputs,
     /// A Timer that can be used to schedule event timers
     timer: &'b mut Timer<u64, OP::TimerState, B>,
+    /// A Timer that can be used to schedule processing-time timers
+    processing_timer: &'b mut Timer<u64, OP::TimerState, B>,
     /// A reference to the backing ComponentDefinition
     source: &'a CD,
+    /// Descriptor of the Node that runs the Operator
+    descriptor: &'a str,
+    #[cfg(feature = "metrics")]
+    /// Metrics collected by the Node that runs the Operator
+    metrics: &'c mut NodeMetrics,
 }
 
 impl<'a, 'c, 'b, OP, B, CD> OperatorContext<'a, 'c, 'b, OP, B, CD>
@@ -113,13 +177,22 @@ where
     #[inline]
     pub(crate) fn new(
         source: &'a CD,
+        descriptor: &'a str,
         timer: &'b mut Timer<u64, OP::TimerState, B>,
+        processing_timer: &'b mut Timer<u64, OP::TimerState, B>,
         channel_strategy: &'c mut ChannelStrategy<OP::OUT>,
+        side_outputs: &'c mut SideOutputs,
+        #[cfg(feature = "metrics")] metrics: &'c mut NodeMetrics,
     ) -> Self {
         OperatorContext {
             channel_strategy,
+            side_outputs,
             timer,
+            processing_timer,
             source,
+            descriptor,
+            #[cfg(feature = "metrics")]
+            metrics,
         }
     }
 
@@ -130,6 +203,23 @@ where
             .add(ArconEvent::Element(element), self.source)
     }
 
+    /// Add an element to the side output identified by `tag`
+    ///
+    /// The element is dropped if the side output is not consumed by any operator.
+    #[inline]
+    pub fn output_to<T: ArconType>(&mut self, tag: &OutputTag<T>, element: ArconElement<T>) {
+        self.side_outputs.output(tag, element, self.source)
+    }
+
+    /// Marks that an element arrived too late to be processed
+    ///
+    /// Late elements are counted in the metrics of the Node if the `metrics` feature is enabled.
+    #[inline]
+    pub fn mark_late_element(&mut self) {
+        #[cfg(feature = "metrics")]
+        self.metrics.late_elements.inc();
+    }
+
     /// Enable users to log within an Operator
     ///
     /// `error!(ctx.log(), "Something bad happened!");
@@ -138,6 +228,14 @@ where
         self.source.log()
     }
 
+    /// Returns the descriptor of the Node that runs the Operator
+    ///
+    /// The descriptor is unique within the pipeline and may be used to name node-local state.
+    #[inline]
+    pub fn descriptor(&self) -> &str {
+        self.descriptor
+    }
+
     /// Get current event time
     #[inline]
     pub fn current_time(&mut self) -> OperatorResult<u64> {
@@ -148,6 +246,7 @@ where
     ///
     /// Returns Ok if the entry was scheduled successfully
     /// or `Err(entry)` if it has already expired.
+    /// A timer that is already scheduled under `key` is rescheduled.
     #[inline]
     pub fn schedule_at<I: Into<u64>>(
         &mut self,
@@ -157,4 +256,53 @@ where
     ) -> Result<(), OP::TimerState> {
         self.timer.schedule_at(key.into(), time, entry)
     }
+
+    /// Returns true if a timer is scheduled under `key`
+    #[inline]
+    pub fn has_timer<I: Into<u64>>(&self, key: I) -> OperatorResult<bool> {
+        self.timer.has_timer(&key.into())
+    }
+
+    /// Cancels the timer scheduled under `key` and returns its entry, if any
+    #[inline]
+    pub fn cancel_timer<I: Into<u64>>(&mut self, key: I) -> OperatorResult<Option<OP::TimerState>> {
+        self.timer.cancel_timer(&key.into())
+    }
+
+    /// Get current processing time, i.e., the system time in milliseconds
+    #[inline]
+    pub fn current_processing_time(&self) -> u64 {
+        crate::util::get_system_time()
+    }
+
+    /// Schedule at a specific processing time in the future
+    ///
+    /// Once the system time reaches `time`, the entry is passed to
+    /// [Operator::handle_processing_timeout]. Returns Ok if the entry was
+    /// scheduled successfully or `Err(entry)` if it has already expired.
+    /// A processing-time timer that is already scheduled under `key` is rescheduled.
+    #[inline]
+    pub fn schedule_processing_time<I: Into<u64>>(
+        &mut self,
+        key: I,
+        time: u64,
+        entry: OP::TimerState,
+    ) -> Result<(), OP::TimerState> {
+        self.processing_timer.schedule_at(key.into(), time, entry)
+    }
+
+    /// Returns true if a processing-time timer is scheduled under `key`
+    #[inline]
+    pub fn has_processing_timer<I: Into<u64>>(&self, key: I) -> OperatorResult<bool> {
+        self.processing_timer.has_timer(&key.into())
+    }
+
+    /// Cancels the processing-time timer scheduled under `key` and returns its entry, if any
+    #[inline]
+    pub fn cancel_processing_timer<I: Into<u64>>(
+        &mut self,
+        key: I,
+    ) -> OperatorResult<Option<OP::TimerState>> {
+        self.processing_timer.cancel_timer(&key.into())
+    }
 }
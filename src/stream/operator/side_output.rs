@@ -0,0 +1,179 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::{
+    data::{ArconElement, ArconEvent, ArconMessage, ArconType, Epoch, NodeID, Watermark},
+    stream::channel::strategy::ChannelStrategy,
+};
+use fxhash::FxHashMap;
+use kompact::prelude::{ActorRefStrong, ComponentDefinition};
+use std::{any::Any, marker::PhantomData};
+
+/// A typed identifier of a side output
+///
+/// An [Operator](super::Operator) may emit elements of type `T` to a side output
+/// through [OperatorContext::output_to](super::OperatorContext::output_to).
+/// The side output is consumed using [Stream::side_output](crate::pipeline::Stream::side_output).
+#[derive(Clone, Debug)]
+pub struct OutputTag<T: ArconType> {
+    id: String,
+    _marker: PhantomData<T>,
+}
+
+impl<T: ArconType> OutputTag<T> {
+    /// Creates a new OutputTag
+    ///
+    /// The id must be unique among the side outputs of an operator.
+    pub fn new(id: impl Into<String>) -> Self {
+        OutputTag {
+            id: id.into(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the id of the tag
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+/// Events that are forwarded to the downstream nodes of a side output
+pub(crate) enum SideControl {
+    Watermark(Watermark),
+    Idle(Watermark),
+    Epoch(Epoch),
+    Death(String),
+}
+
+impl SideControl {
+    fn event<T: ArconType>(&self) -> ArconEvent<T> {
+        match self {
+            SideControl::Watermark(w) => ArconEvent::Watermark(*w),
+            SideControl::Idle(w) => ArconEvent::Idle(*w),
+            SideControl::Epoch(e) => ArconEvent::Epoch(*e),
+            SideControl::Death(s) => ArconEvent::Death(s.clone()),
+        }
+    }
+}
+
+/// Flushes a type-erased strategy, or adds a control event to it, on behalf of a type-erased source
+type SideOps = fn(&mut dyn Any, Option<&SideControl>, &dyn Any);
+
+fn side_ops<T, CD>(strategy: &mut dyn Any, control: Option<&SideControl>, source: &dyn Any)
+where
+    T: ArconType,
+    CD: ComponentDefinition + Sized + 'static,
+{
+    let strategy = strategy
+        .downcast_mut::<ChannelStrategy<T>>()
+        .expect("Side output strategy of the wrong type");
+    let source = source
+        .downcast_ref::<CD>()
+        .expect("Side output used by a different component");
+    match control {
+        Some(control) => strategy.add(control.event(), source),
+        None => strategy.flush(source),
+    }
+}
+
+/// A type-erased side output of a Node
+///
+/// Elements are buffered by the channel strategy of the side output and are flushed
+/// together with the main output of the Node. Control events are added to the same
+/// strategy so that they keep their order with the buffered elements.
+pub(crate) struct SideOutput {
+    /// A `ChannelStrategy<T>` where `T` is the type of the [OutputTag]
+    strategy: Box<dyn Any + Send>,
+    /// Operations on the strategy, bound to the type of the Node when the first element is added
+    ops: Option<SideOps>,
+    /// Whether elements were added since the strategy was last flushed
+    dirty: bool,
+    /// Sends control events to the downstream nodes while no element has been added
+    control: Box<dyn Fn(&SideControl) + Send>,
+}
+
+impl SideOutput {
+    pub(crate) fn new<T: ArconType>(
+        strategy: ChannelStrategy<T>,
+        targets: Vec<(ActorRefStrong<ArconMessage<T>>, NodeID)>,
+    ) -> Self {
+        // Nothing is buffered by the strategy yet, so the events can't overtake any element
+        let control = move |event: &SideControl| {
+            for (actor_ref, sender) in &targets {
+                actor_ref.tell(ArconMessage {
+                    events: vec![event.event::<T>().into()].into(),
+                    sender: *sender,
+                });
+            }
+        };
+
+        SideOutput {
+            strategy: Box::new(strategy),
+            ops: None,
+            dirty: false,
+            control: Box::new(control),
+        }
+    }
+}
+
+/// Side outputs of a Node identified by their tag id
+#[derive(Default)]
+pub(crate) struct SideOutputs {
+    outputs: FxHashMap<String, SideOutput>,
+}
+
+impl SideOutputs {
+    pub(crate) fn insert(&mut self, id: String, output: SideOutput) {
+        self.outputs.insert(id, output);
+    }
+
+    /// Add an element to the side output of `tag`
+    ///
+    /// Elements of side outputs without any consumers are dropped.
+    #[inline]
+    pub(crate) fn output<T, CD>(
+        &mut self,
+        tag: &OutputTag<T>,
+        element: ArconElement<T>,
+        source: &CD,
+    ) where
+        T: ArconType,
+        CD: ComponentDefinition + Sized + 'static,
+    {
+        if let Some(output) = self.outputs.get_mut(tag.id()) {
+            let strategy = output
+                .strategy
+                .downcast_mut::<ChannelStrategy<T>>()
+                .unwrap_or_else(|| panic!("Side output {} was used with the wrong type", tag.id()));
+            strategy.add(ArconEvent::Element(element), source);
+            output.ops.get_or_insert(side_ops::<T, CD>);
+            output.dirty = true;
+        }
+    }
+
+    /// Send the buffered elements of all side outputs
+    #[inline]
+    pub(crate) fn flush(&mut self, source: &dyn Any) {
+        for output in self.outputs.values_mut() {
+            if let (Some(ops), true) = (output.ops, output.dirty) {
+                ops(output.strategy.as_mut(), None, source);
+                output.dirty = false;
+            }
+        }
+    }
+
+    /// Forward a control event to all side outputs
+    #[inline]
+    pub(crate) fn forward(&mut self, event: SideControl, source: &dyn Any) {
+        for output in self.outputs.values_mut() {
+            match output.ops {
+                Some(ops) => {
+                    // Control events flush the strategy
+                    ops(output.strategy.as_mut(), Some(&event), source);
+                    output.dirty = false;
+                }
+                None => (output.control)(&event),
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+//! Record encoders for file sinks
+
+use crate::data::ArconType;
+use arcon_error::OperatorResult;
+#[cfg(feature = "arcon_serde")]
+use arcon_state::error::ArconStateError;
+use prost::Message;
+
+/// Encodes the records that a file sink writes
+pub trait Encoder<IN>: Send + 'static
+where
+    IN: ArconType,
+{
+    /// Appends the encoding of `record` to `buf`
+    fn encode(&mut self, record: &IN, buf: &mut Vec<u8>) -> OperatorResult<()>;
+
+    /// Appends the bytes that every file starts with to `buf`
+    fn header(&mut self, _buf: &mut Vec<u8>) -> OperatorResult<()> {
+        Ok(())
+    }
+
+    /// File extension of the encoded files, without the leading dot
+    fn extension(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Encodes records as length-delimited protobuf messages
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProtobufEncoder;
+
+impl<IN> Encoder<IN> for ProtobufEncoder
+where
+    IN: ArconType,
+{
+    fn encode(&mut self, record: &IN, buf: &mut Vec<u8>) -> OperatorResult<()> {
+        // Encoding into a Vec only fails if the buffer can't grow
+        record
+            .encode_length_delimited(buf)
+            .expect("Failed to encode record");
+        Ok(())
+    }
+
+    fn extension(&self) -> Option<&str> {
+        Some("pb")
+    }
+}
+
+/// Encodes records as JSON objects, one per line
+#[cfg(feature = "arcon_serde")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsonLinesEncoder;
+
+#[cfg(feature = "arcon_serde")]
+impl<IN> Encoder<IN> for JsonLinesEncoder
+where
+    IN: ArconType,
+{
+    fn encode(&mut self, record: &IN, buf: &mut Vec<u8>) -> OperatorResult<()> {
+        serde_json::to_writer(&mut *buf, record)
+            .map_err(|e| ArconStateError::Unknown { msg: e.to_string() })?;
+        buf.push(b'\n');
+        Ok(())
+    }
+
+    fn extension(&self) -> Option<&str> {
+        Some("jsonl")
+    }
+}
+
+/// Encodes records as CSV rows
+///
+/// The fields of a record are extracted by a user-defined function and are
+/// quoted as described in RFC 4180 where needed.
+pub struct CsvEncoder<IN>
+where
+    IN: ArconType,
+{
+    fields: Box<dyn Fn(&IN) -> Vec<String> + Send>,
+    delimiter: char,
+    header: Option<Vec<String>>,
+}
+
+impl<IN> CsvEncoder<IN>
+where
+    IN: ArconType,
+{
+    /// Creates an encoder that writes the fields returned by `fields` as a row
+    pub fn new(fields: impl Fn(&IN) -> Vec<String> + Send + 'static) -> Self {
+        CsvEncoder {
+            fields: Box::new(fields),
+            delimiter: ',',
+            header: None,
+        }
+    }
+
+    /// Separates fields by `delimiter` instead of a comma
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Starts every file with a row of column names
+    pub fn with_header(mut self, columns: Vec<impl Into<String>>) -> Self {
+        self.header = Some(columns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn write_row(&self, fields: &[String], buf: &mut Vec<u8>) {
+        let mut utf8 = [0; 4];
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                buf.extend_from_slice(self.delimiter.encode_utf8(&mut utf8).as_bytes());
+            }
+            let needs_quotes = field
+                .chars()
+                .any(|c| c == self.delimiter || c == '"' || c == '\n' || c == '\r');
+            if needs_quotes {
+                buf.push(b'"');
+                buf.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+                buf.push(b'"');
+            } else {
+                buf.extend_from_slice(field.as_bytes());
+            }
+        }
+        buf.push(b'\n');
+    }
+}
+
+impl<IN> Encoder<IN> for CsvEncoder<IN>
+where
+    IN: ArconType,
+{
+    fn encode(&mut self, record: &IN, buf: &mut Vec<u8>) -> OperatorResult<()> {
+        let fields = (self.fields)(record);
+        self.write_row(&fields, buf);
+        Ok(())
+    }
+
+    fn header(&mut self, buf: &mut Vec<u8>) -> OperatorResult<()> {
+        if let Some(columns) = &self.header {
+            self.write_row(columns, buf);
+        }
+        Ok(())
+    }
+
+    fn extension(&self) -> Option<&str> {
+        Some("csv")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoder_test() {
+        let mut csv = CsvEncoder::new(|x: &u64| vec![x.to_string(), format!("a,\"{}\"", x)])
+            .with_header(vec!["id", "name"]);
+        let mut buf = Vec::new();
+        csv.header(&mut buf).unwrap();
+        csv.encode(&7, &mut buf).unwrap();
+        assert_eq!(buf, b"id,name\n7,\"a,\"\"7\"\"\"\n".to_vec());
+
+        let mut protobuf = ProtobufEncoder;
+        let mut buf = Vec::new();
+        Encoder::<u64>::encode(&mut protobuf, &1, &mut buf).unwrap();
+        Encoder::<u64>::encode(&mut protobuf, &300, &mut buf).unwrap();
+        let mut bytes = &buf[..];
+        assert_eq!(u64::decode_length_delimited(&mut bytes).unwrap(), 1);
+        assert_eq!(u64::decode_length_delimited(&mut bytes).unwrap(), 300);
+        assert!(bytes.is_empty());
+    }
+}
@@ -108,6 +108,7 @@ mod tests {
                 NodeState::new(NodeID::new(0), vec![node_id], backend.clone()),
                 backend,
             )
+            .unwrap()
         });
         system.start(&sink_comp);
         let input_one = ArconMessage::element(6i32, None, node_id);
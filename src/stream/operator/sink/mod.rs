@@ -1,7 +1,10 @@
 // Copyright (c) 2020, KTH Royal Institute of Technology.
 // SPDX-License-Identifier: AGPL-3.0-only
 
+pub mod encoder;
 pub mod local_file;
+pub mod rolling_file;
+pub mod transactional;
 
 #[cfg(feature = "socket")]
 #[allow(dead_code)]
@@ -0,0 +1,304 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use super::{encoder::Encoder, transactional::TransactionalSink};
+use crate::data::{ArconElement, ArconType};
+use arcon_error::OperatorResult;
+use prost::Message;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// Decides when a [RollingFileSink] closes its part file and starts a new one
+///
+/// Part files are also rolled whenever an epoch is pre-committed.
+#[derive(Debug, Clone)]
+pub struct RollingPolicy {
+    /// Size in bytes after which a part file is rolled
+    pub max_part_size: u64,
+    /// Time after which a part file is rolled, checked whenever a record is written
+    pub rollover_interval: Option<Duration>,
+}
+
+impl Default for RollingPolicy {
+    fn default() -> Self {
+        RollingPolicy {
+            max_part_size: 128 * 1024 * 1024,
+            rollover_interval: None,
+        }
+    }
+}
+
+/// Part files of a pre-committed transaction
+#[derive(Message, Clone)]
+pub struct PartFiles {
+    /// Final file names relative to the directory of the sink
+    #[prost(string, repeated, tag = "1")]
+    pub files: Vec<String>,
+}
+
+struct InProgressPart {
+    name: String,
+    writer: BufWriter<File>,
+    bytes: u64,
+    opened_at: Instant,
+}
+
+/// A [TransactionalSink] that writes records into rolling part files of a directory
+///
+/// Part files are written as hidden `.{name}.inprogress` files and are renamed to
+/// `part-{id}-{n}[.{extension}]` once the epoch that produced them is committed.
+/// Readers of the directory that skip hidden files thus only see committed output.
+/// Run it through a [TwoPhaseCommitSink](super::transactional::TwoPhaseCommitSink)
+/// for exactly-once output.
+///
+/// Part files of transactions that fail before they are pre-committed are removed once the sink is restored.
+pub struct RollingFileSink<IN, E>
+where
+    IN: ArconType,
+    E: Encoder<IN>,
+{
+    dir: PathBuf,
+    encoder: E,
+    policy: RollingPolicy,
+    /// Unique id of this sink instance that keeps parallel instances from colliding
+    id: String,
+    /// Number of part files that have been opened
+    part_counter: u64,
+    /// The part file that records are currently written to
+    current: Option<InProgressPart>,
+    /// Closed part files of the ongoing transaction
+    closed: Vec<String>,
+    /// Set once a transaction has been begun, see [TransactionalSink::abort]
+    began: bool,
+    buf: Vec<u8>,
+    _marker: PhantomData<IN>,
+}
+
+impl<IN, E> RollingFileSink<IN, E>
+where
+    IN: ArconType,
+    E: Encoder<IN>,
+{
+    /// Creates a sink that writes part files into `dir`, which is created if needed
+    pub fn new(dir: impl Into<PathBuf>, encoder: E) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(RollingFileSink {
+            dir,
+            encoder,
+            policy: RollingPolicy::default(),
+            id: uuid::Uuid::new_v4().to_simple().to_string(),
+            part_counter: 0,
+            current: None,
+            closed: Vec::new(),
+            began: false,
+            buf: Vec::new(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Rolls part files according to `policy` instead of the default [RollingPolicy]
+    pub fn with_policy(mut self, policy: RollingPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    fn open_part(&mut self) -> OperatorResult<InProgressPart> {
+        let name = match self.encoder.extension() {
+            Some(ext) => format!("part-{}-{}.{}", self.id, self.part_counter, ext),
+            None => format!("part-{}-{}", self.id, self.part_counter),
+        };
+        self.part_counter += 1;
+
+        let file = File::create(in_progress_path(&self.dir, &name))?;
+        let mut part = InProgressPart {
+            name,
+            writer: BufWriter::new(file),
+            bytes: 0,
+            opened_at: Instant::now(),
+        };
+
+        self.buf.clear();
+        self.encoder.header(&mut self.buf)?;
+        part.writer.write_all(&self.buf)?;
+        part.bytes += self.buf.len() as u64;
+        Ok(part)
+    }
+
+    /// Makes the current part file durable and adds it to the closed ones
+    fn close_part(&mut self) -> OperatorResult<()> {
+        if let Some(mut part) = self.current.take() {
+            part.writer.flush()?;
+            part.writer.get_ref().sync_all()?;
+            self.closed.push(part.name);
+        }
+        Ok(())
+    }
+
+    fn should_roll(&self, part: &InProgressPart) -> bool {
+        part.bytes >= self.policy.max_part_size
+            || self
+                .policy
+                .rollover_interval
+                .map(|interval| part.opened_at.elapsed() >= interval)
+                .unwrap_or(false)
+    }
+}
+
+impl<IN, E> TransactionalSink for RollingFileSink<IN, E>
+where
+    IN: ArconType,
+    E: Encoder<IN>,
+{
+    type IN = IN;
+    type Transaction = PartFiles;
+
+    fn begin_txn(&mut self) -> OperatorResult<()> {
+        debug_assert!(self.current.is_none() && self.closed.is_empty());
+        self.began = true;
+        Ok(())
+    }
+
+    fn write(&mut self, element: ArconElement<IN>) -> OperatorResult<()> {
+        if self.current.as_ref().map_or(false, |p| self.should_roll(p)) {
+            self.close_part()?;
+        }
+        let part = match self.current.take() {
+            Some(part) => part,
+            None => self.open_part()?,
+        };
+
+        // The part is put back first so that it is cleaned up on abort if the write fails
+        let part = self.current.get_or_insert(part);
+        self.buf.clear();
+        self.encoder.encode(&element.data, &mut self.buf)?;
+        part.writer.write_all(&self.buf)?;
+        part.bytes += self.buf.len() as u64;
+        Ok(())
+    }
+
+    fn pre_commit(&mut self, _epoch: u64) -> OperatorResult<PartFiles> {
+        self.close_part()?;
+        Ok(PartFiles {
+            files: std::mem::take(&mut self.closed),
+        })
+    }
+
+    fn commit(&mut self, epoch: u64, txn: PartFiles) -> OperatorResult<()> {
+        for name in &txn.files {
+            let committed = self.dir.join(name);
+            match fs::rename(in_progress_path(&self.dir, name), &committed) {
+                Ok(()) => (),
+                // Already committed before a failure
+                Err(e) if e.kind() == io::ErrorKind::NotFound && committed.is_file() => (),
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!(
+                            "Failed to commit part file {} of epoch {}: {}",
+                            name, epoch, e
+                        ),
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn abort(&mut self) -> OperatorResult<()> {
+        // A restored sink is aborted before any instance writes into the directory again,
+        // so the part files that are still in progress were left behind by a failed run
+        if !self.began {
+            for entry in fs::read_dir(&self.dir)? {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                if name.starts_with(".part-") && name.ends_with(".inprogress") {
+                    fs::remove_file(self.dir.join(&name))?;
+                }
+            }
+            return Ok(());
+        }
+
+        if let Some(part) = self.current.take() {
+            self.closed.push(part.name);
+        }
+        for name in self.closed.drain(..) {
+            fs::remove_file(in_progress_path(&self.dir, &name))?;
+        }
+        Ok(())
+    }
+}
+
+fn in_progress_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!(".{}.inprogress", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stream::operator::sink::encoder::CsvEncoder;
+    use tempfile::TempDir;
+
+    fn visible_files(dir: &Path) -> Vec<String> {
+        let mut files: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.starts_with('.'))
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn rolling_file_sink_test() {
+        let dir = TempDir::new().unwrap();
+        let encoder = CsvEncoder::new(|x: &u64| vec![x.to_string()]).with_header(vec!["x"]);
+        let mut sink = RollingFileSink::new(dir.path(), encoder)
+            .unwrap()
+            .with_policy(RollingPolicy {
+                max_part_size: 8,
+                rollover_interval: None,
+            });
+
+        sink.begin_txn().unwrap();
+        for i in 0..4 {
+            sink.write(ArconElement::new(i)).unwrap();
+        }
+        let txn = sink.pre_commit(0).unwrap();
+        // "x\n0\n1\n2\n" fills the first part file
+        assert_eq!(txn.files.len(), 2);
+        assert!(visible_files(dir.path()).is_empty());
+
+        sink.commit(0, txn.clone()).unwrap();
+        // Commits replayed after a failure are no-ops
+        sink.commit(0, txn.clone()).unwrap();
+        assert_eq!(visible_files(dir.path()), txn.files);
+        let contents: Vec<String> = txn
+            .files
+            .iter()
+            .map(|f| fs::read_to_string(dir.path().join(f)).unwrap())
+            .collect();
+        assert_eq!(contents, vec!["x\n0\n1\n2\n", "x\n3\n"]);
+
+        sink.begin_txn().unwrap();
+        sink.write(ArconElement::new(4)).unwrap();
+        sink.abort().unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+
+        // A restored sink removes the part files that a failed run left in progress
+        sink.begin_txn().unwrap();
+        sink.write(ArconElement::new(5)).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+        let encoder = CsvEncoder::new(|x: &u64| vec![x.to_string()]);
+        let mut restored = RollingFileSink::new(dir.path(), encoder).unwrap();
+        restored.abort().unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+        assert_eq!(visible_files(dir.path()), txn.files);
+    }
+}
@@ -153,6 +153,7 @@ mod tests {
                         NodeState::new(NodeID::new(0), vec![node_id], backend.clone()),
                         backend,
                     )
+                    .unwrap()
                 });
                 system
                     .start_notify(&socket_sink)
@@ -0,0 +1,346 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::{
+    data::{ArconElement, ArconNever, ArconType},
+    index::EagerHashTable,
+    stream::operator::{Operator, OperatorContext},
+};
+use arcon_error::OperatorResult;
+use arcon_state::{data::Value, Backend};
+use kompact::prelude::ComponentDefinition;
+use prost::Message;
+use std::sync::Arc;
+
+/// A sink that writes its output in transactions which are committed together with the epochs of a pipeline
+///
+/// A transaction is begun for the first element of an epoch and pre-committed once the Node
+/// checkpoints the epoch. The returned handle is stored in the checkpoint and the transaction
+/// is committed after the epoch has been committed by all operators of the pipeline.
+///
+/// Commits must be idempotent, as a transaction may be committed by any instance of the sink,
+/// e.g., when the commits that are pending at a failure are replayed by a restored pipeline.
+/// Use [TwoPhaseCommitSink] to run a `TransactionalSink`.
+pub trait TransactionalSink: Send + 'static {
+    /// The type of input elements the sink writes
+    type IN: ArconType;
+    /// Handle of a pre-committed transaction, e.g., the path of a staged file
+    type Transaction: Value;
+
+    /// Starts a new transaction
+    fn begin_txn(&mut self) -> OperatorResult<()>;
+
+    /// Writes an element into the ongoing transaction
+    fn write(&mut self, element: ArconElement<Self::IN>) -> OperatorResult<()>;
+
+    /// Prepares the ongoing transaction of `epoch` to be committed
+    ///
+    /// Once this returns, the transaction must be committable even after a failure.
+    fn pre_commit(&mut self, epoch: u64) -> OperatorResult<Self::Transaction>;
+
+    /// Makes the output of a pre-committed transaction visible
+    fn commit(&mut self, epoch: u64, txn: Self::Transaction) -> OperatorResult<()>;
+
+    /// Discards the ongoing transaction
+    ///
+    /// A restored sink is aborted before it writes any element, which discards the
+    /// transactions that a failed run had begun but not pre-committed.
+    fn abort(&mut self) -> OperatorResult<()>;
+}
+
+#[derive(Message, Clone)]
+struct PendingTransaction<T: Value> {
+    #[prost(uint64, tag = "1")]
+    epoch: u64,
+    #[prost(message, required, tag = "2")]
+    txn: T,
+}
+
+/// Operator that drives a [TransactionalSink] through the epochs of a pipeline
+pub struct TwoPhaseCommitSink<S, B>
+where
+    S: TransactionalSink,
+    B: Backend,
+{
+    sink: S,
+    /// Set if a transaction has been begun since the last pre-commit
+    in_txn: bool,
+    /// Pre-committed transactions by transaction id
+    ///
+    /// Transactions are committed from this table rather than from memory, so they are
+    /// found again by a fresh instance of the sink, e.g., after a restart or rebalancing.
+    pending: EagerHashTable<String, PendingTransaction<S::Transaction>, B>,
+    op_state: (),
+}
+
+impl<S, B> TwoPhaseCommitSink<S, B>
+where
+    S: TransactionalSink,
+    B: Backend,
+{
+    pub fn new(sink: S, backend: Arc<B>) -> Self {
+        TwoPhaseCommitSink {
+            sink,
+            in_txn: false,
+            pending: EagerHashTable::new("_pending_transactions", backend),
+            op_state: (),
+        }
+    }
+
+    /// Commits the pending transactions up to `epoch`, in epoch order
+    ///
+    /// A transaction that fails to commit stays pending and is retried with
+    /// the next commit, together with the ones after it.
+    fn commit_pending(&mut self, epoch: u64) -> OperatorResult<()> {
+        // The zero-padded ids sort by epoch
+        let mut ids = Vec::new();
+        for entry in self.pending.iter()? {
+            ids.push(entry?.0);
+        }
+        ids.sort();
+
+        for id in ids {
+            // Another instance may have committed the transaction in the meantime
+            let pending = match self.pending.get(&id)? {
+                Some(pending) => pending,
+                None => continue,
+            };
+            if pending.epoch > epoch {
+                break;
+            }
+            self.sink.commit(pending.epoch, pending.txn)?;
+            self.pending.remove(&id)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S, B> Operator for TwoPhaseCommitSink<S, B>
+where
+    S: TransactionalSink,
+    B: Backend,
+{
+    type IN = S::IN;
+    type OUT = ArconNever;
+    type TimerState = ArconNever;
+    type OperatorState = ();
+
+    fn handle_element(
+        &mut self,
+        element: ArconElement<Self::IN>,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        if !self.in_txn {
+            self.sink.begin_txn()?;
+            self.in_txn = true;
+        }
+        self.sink.write(element)
+    }
+
+    fn handle_epoch(
+        &mut self,
+        epoch: u64,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        // Epochs without any output have nothing to commit
+        if !self.in_txn {
+            return Ok(());
+        }
+        self.in_txn = false;
+
+        let txn = match self.sink.pre_commit(epoch) {
+            Ok(txn) => txn,
+            Err(err) => {
+                self.sink.abort()?;
+                return Err(err);
+            }
+        };
+
+        // Zero-padded, so that the ids sort by epoch
+        let id = format!("{:020}_{}", epoch, uuid::Uuid::new_v4().to_simple());
+        self.pending.put(id, PendingTransaction { epoch, txn })?;
+        Ok(())
+    }
+
+    fn handle_epoch_commit(
+        &mut self,
+        epoch: u64,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        self.commit_pending(epoch)
+    }
+
+    fn handle_restore(
+        &mut self,
+        epoch: u64,
+        _ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        // The restored transactions may have been pre-committed by any instance
+        self.commit_pending(epoch)?;
+        self.sink.abort()
+    }
+
+    crate::ignore_timeout!();
+    crate::ignore_persist!();
+
+    fn state(&mut self) -> &mut Self::OperatorState {
+        &mut self.op_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::{ArconMessage, Epoch, NodeID},
+        stream::{
+            channel::strategy::ChannelStrategy,
+            node::{Node, NodeState},
+        },
+    };
+    use kompact::prelude::*;
+    use std::sync::Mutex;
+
+    /// Records the calls of the two-phase commit protocol
+    struct RecordingSink {
+        log: Arc<Mutex<Vec<String>>>,
+        ongoing: Vec<String>,
+    }
+
+    impl TransactionalSink for RecordingSink {
+        type IN = i32;
+        type Transaction = String;
+
+        fn begin_txn(&mut self) -> OperatorResult<()> {
+            self.log.lock().unwrap().push("begin".to_owned());
+            Ok(())
+        }
+        fn write(&mut self, element: ArconElement<i32>) -> OperatorResult<()> {
+            self.ongoing.push(element.data.to_string());
+            Ok(())
+        }
+        fn pre_commit(&mut self, epoch: u64) -> OperatorResult<String> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("pre_commit {}", epoch));
+            Ok(self.ongoing.drain(..).collect::<Vec<_>>().join(","))
+        }
+        fn commit(&mut self, epoch: u64, txn: String) -> OperatorResult<()> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("commit {} [{}]", epoch, txn));
+            Ok(())
+        }
+        fn abort(&mut self) -> OperatorResult<()> {
+            self.log.lock().unwrap().push("abort".to_owned());
+            self.ongoing.clear();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn two_phase_commit_sink_test() {
+        let system = KompactConfig::default().build().expect("KompactSystem");
+        let backend = Arc::new(crate::test_utils::temp_backend());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sender = NodeID::new(1);
+
+        let create_node = |log: Arc<Mutex<Vec<String>>>| {
+            let sink = RecordingSink {
+                log,
+                ongoing: Vec::new(),
+            };
+            let backend = backend.clone();
+            system.create(move || {
+                Node::new(
+                    String::from("sink_0"),
+                    ChannelStrategy::Mute,
+                    TwoPhaseCommitSink::new(sink, backend.clone()),
+                    NodeState::new(NodeID::new(0), vec![sender], backend.clone()),
+                    backend,
+                )
+                .unwrap()
+            })
+        };
+
+        let sink_comp = create_node(log.clone());
+        system.start(&sink_comp);
+        let sink_ref: ActorRefStrong<ArconMessage<i32>> =
+            sink_comp.actor_ref().hold().expect("Failed to fetch");
+        sink_ref.tell(ArconMessage::element(1, None, sender));
+        sink_ref.tell(ArconMessage::element(2, None, sender));
+        sink_ref.tell(ArconMessage::epoch(0, sender));
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        assert_eq!(*log.lock().unwrap(), vec!["begin", "pre_commit 0"]);
+
+        // A restored Node commits the transactions of the restored epoch
+        // and aborts the one that may have been begun afterwards
+        let restored_log = Arc::new(Mutex::new(Vec::new()));
+        let restored = create_node(restored_log.clone());
+        restored
+            .on_definition(|cd| cd.replay_commit(Epoch::new(0)))
+            .unwrap();
+        assert_eq!(*restored_log.lock().unwrap(), vec![
+            "commit 0 [1,2]",
+            "abort"
+        ]);
+
+        // Transactions are committed once
+        sink_comp
+            .on_definition(|cd| cd.commit_epoch(Epoch::new(0)))
+            .unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["begin", "pre_commit 0"]);
+        let _ = system.shutdown();
+    }
+
+    #[test]
+    fn two_phase_commit_sink_rebalance_test() {
+        let system = KompactConfig::default().build().expect("KompactSystem");
+        let backend = Arc::new(crate::test_utils::temp_backend());
+        let sender = NodeID::new(1);
+        let create_sink = |log: Arc<Mutex<Vec<String>>>| {
+            let sink = RecordingSink {
+                log,
+                ongoing: Vec::new(),
+            };
+            TwoPhaseCommitSink::new(sink, backend.clone())
+        };
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let sink = create_sink(log.clone());
+        let node_backend = backend.clone();
+        let sink_comp = system.create(move || {
+            Node::new(
+                String::from("sink_0"),
+                ChannelStrategy::Mute,
+                sink,
+                NodeState::new(NodeID::new(0), vec![sender], node_backend.clone()),
+                node_backend,
+            )
+            .unwrap()
+        });
+        system.start(&sink_comp);
+        let sink_ref: ActorRefStrong<ArconMessage<i32>> =
+            sink_comp.actor_ref().hold().expect("Failed to fetch");
+        sink_ref.tell(ArconMessage::element(1, None, sender));
+        sink_ref.tell(ArconMessage::epoch(0, sender));
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        // The Node continues with a fresh sink before the pre-committed epoch is committed
+        let rebalanced_log = Arc::new(Mutex::new(Vec::new()));
+        let rebalanced = create_sink(rebalanced_log.clone());
+        sink_comp
+            .on_definition(|cd| {
+                cd.rebalance(rebalanced, Vec::new(), Vec::new())?;
+                cd.commit_epoch(Epoch::new(0))
+            })
+            .unwrap();
+
+        assert_eq!(*log.lock().unwrap(), vec!["begin", "pre_commit 0"]);
+        assert_eq!(*rebalanced_log.lock().unwrap(), vec!["commit 0 [1]"]);
+        let _ = system.shutdown();
+    }
+}
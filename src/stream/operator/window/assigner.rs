@@ -1,18 +1,30 @@
 // Copyright (c) 2020, KTH Royal Institute of Technology.
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use super::{Window, WindowContext};
+use super::{
+    EventTimeTrigger, ProcessingTimeTrigger, Trigger, TriggerContext, TriggerResult, Window,
+    WindowContext,
+};
 use crate::{
     data::{ArconElement, ArconType},
     index::{ArconState, EagerHashTable, IndexOps, StateConstructor},
-    stream::operator::{Operator, OperatorContext},
+    stream::{
+        operator::{side_output::OutputTag, Operator, OperatorContext},
+        time::ArconTime,
+    },
 };
 use arcon_error::*;
 use arcon_macros::ArconState;
-use arcon_state::Backend;
+use arcon_state::{error::ArconStateError, Backend};
+use fxhash::FxHasher;
 use kompact::prelude::ComponentDefinition;
 use prost::Message;
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
 type Key = u64;
 type Index = u64;
@@ -25,24 +37,69 @@ pub struct WindowEvent {
     key: Key,
     #[prost(uint64, tag = "2")]
     index: Index,
+    /// End of the window
     #[prost(uint64, tag = "3")]
     timestamp: Timestamp,
+    /// Time that the timer was scheduled for
+    #[prost(uint64, tag = "4")]
+    time: Timestamp,
+    /// Whether `time` is a processing time rather than an event time
+    #[prost(bool, tag = "5")]
+    processing_time: bool,
 }
 
 impl WindowEvent {
-    fn new(key: Key, index: Index, timestamp: Timestamp) -> WindowEvent {
+    fn new(
+        key: Key,
+        index: Index,
+        timestamp: Timestamp,
+        time: Timestamp,
+        processing_time: bool,
+    ) -> WindowEvent {
         WindowEvent {
             key,
             index,
             timestamp,
+            time,
+            processing_time,
         }
     }
+
+    /// A window has at most one timer per point in time and time domain
+    fn timer_id(&self) -> u64 {
+        let mut hasher = FxHasher::default();
+        (self.key, self.index, self.time, self.processing_time).hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// A session window of a key
+#[derive(Message, PartialEq, Clone)]
+pub struct Session {
+    #[prost(uint64, tag = "1")]
+    start: Timestamp,
+    /// Timestamp after the last element plus the gap
+    #[prost(uint64, tag = "2")]
+    end: Timestamp,
+    /// Window index that the state of the session is kept under
+    #[prost(uint64, tag = "3")]
+    index: Index,
+}
+
+/// The open session windows of a key
+#[derive(Message, PartialEq, Clone)]
+pub struct Sessions {
+    #[prost(message, repeated, tag = "1")]
+    sessions: Vec<Session>,
+    #[prost(uint64, tag = "2")]
+    next_index: Index,
 }
 
 #[derive(ArconState)]
 pub struct AssignerState<B: Backend> {
     window_start: EagerHashTable<Key, Timestamp, B>,
     active_windows: EagerHashTable<WindowContext, (), B>,
+    sessions: EagerHashTable<Key, Sessions, B>,
 }
 
 impl<B: Backend> StateConstructor for AssignerState<B> {
@@ -50,12 +107,13 @@ impl<B: Backend> StateConstructor for AssignerState<B> {
     fn new(backend: Arc<Self::BackendType>) -> Self {
         Self {
             window_start: EagerHashTable::new("_window_start", backend.clone()),
-            active_windows: EagerHashTable::new("_active_windows", backend),
+            active_windows: EagerHashTable::new("_active_windows", backend.clone()),
+            sessions: EagerHashTable::new("_sessions", backend),
         }
     }
 }
 
-/// Window Assigner Based on Event Time
+/// Window Assigner Based on Event Time or Processing Time
 ///
 /// IN: Input event
 /// OUT: Output of Window
@@ -70,10 +128,21 @@ where
     window_length: u64,
     window_slide: u64,
     late_arrival_time: u64,
+    /// Time that windows are kept after they are complete to add late elements to them
+    allowed_lateness: u64,
     keyed: bool,
+    /// Inactivity gap of session windows, `None` for sliding and tumbling windows
+    session_gap: Option<u64>,
+    /// Whether the state of sliding windows is kept in panes of one slide each
+    panes: bool,
+    /// Time domain that elements are assigned to windows by
+    time: ArconTime,
 
     // window keeps its own state per key and index (via state backend api)
     window: W,
+    trigger: Box<dyn Trigger>,
+    /// Side output of elements that arrive too late to be added to their windows
+    late_output: Option<OutputTag<IN>>,
     // simply persisted state
     state: AssignerState<B>,
     op_state: (),
@@ -111,6 +180,52 @@ where
         Self::setup(window, backend, length, slide, late_arrival_time, keyed)
     }
 
+    /// Create a WindowAssigner for tumbling windows in processing time
+    ///
+    /// Elements are assigned to windows by the system time at which they are processed,
+    /// so they do not need timestamps. Windows are emitted once the system time passes their end.
+    pub fn tumbling_processing_time(window: W, backend: Arc<B>, length: u64, keyed: bool) -> Self {
+        Self::sliding_processing_time(window, backend, length, length, keyed)
+    }
+
+    /// Create a WindowAssigner for sliding windows in processing time
+    pub fn sliding_processing_time(
+        window: W,
+        backend: Arc<B>,
+        length: u64,
+        slide: u64,
+        keyed: bool,
+    ) -> Self {
+        let mut assigner = Self::setup(window, backend, length, slide, 0, keyed);
+        assigner.time = ArconTime::Process;
+        assigner.trigger = Box::new(ProcessingTimeTrigger);
+        assigner
+    }
+
+    /// Create a WindowAssigner for session windows
+    ///
+    /// A session of a key is closed once no element has arrived for `gap`. Sessions
+    /// that overlap, e.g., because of an element that arrived out of order, are merged.
+    /// Merging requires a [Window] whose state can be merged, such as an
+    /// [IncrementalWindow](super::IncrementalWindow) created with `mergeable`.
+    pub fn session(
+        window: W,
+        backend: Arc<B>,
+        gap: u64,
+        late_arrival_time: u64,
+        keyed: bool,
+    ) -> Self {
+        if gap == 0 {
+            panic!("Session gap must be greater than zero!");
+        }
+        if !window.is_mergeable() {
+            panic!("Session windows require a Window that can be merged!");
+        }
+        let mut assigner = Self::setup(window, backend, gap, gap, late_arrival_time, keyed);
+        assigner.session_gap = Some(gap);
+        assigner
+    }
+
     // Setup method for both sliding and tumbling windows
     fn setup(window: W, backend: Arc<B>, length: u64, slide: u64, late: u64, keyed: bool) -> Self {
         // Sanity check on slide and length
@@ -127,8 +242,14 @@ where
             window_length: length,
             window_slide: slide,
             late_arrival_time: late,
+            allowed_lateness: 0,
             window,
             keyed,
+            session_gap: None,
+            panes: false,
+            time: ArconTime::Event,
+            trigger: Box::new(EventTimeTrigger),
+            late_output: None,
 
             state,
             op_state: (),
@@ -136,29 +257,343 @@ where
         }
     }
 
+    /// Decides when windows are emitted with `trigger`
+    ///
+    /// By default, windows are emitted once when they are complete.
+    pub fn with_trigger(mut self, trigger: impl Trigger) -> Self {
+        self.trigger = Box::new(trigger);
+        self
+    }
+
+    /// Keeps the state of windows for `lateness` after they are complete
+    ///
+    /// Elements that arrive within this time are still added to their windows,
+    /// which, with the default trigger, emits the updated result of the window again.
+    pub fn with_allowed_lateness(mut self, lateness: u64) -> Self {
+        self.allowed_lateness = lateness;
+        self
+    }
+
+    /// Emits late elements to the side output of `tag`
+    ///
+    /// An element is late if its timestamp is lower than the current event time minus the
+    /// late arrival time and the allowed lateness, or if all windows it belongs to have been
+    /// cleaned up. Late elements are dropped by default.
+    pub fn with_late_output(mut self, tag: OutputTag<IN>) -> Self {
+        self.late_output = Some(tag);
+        self
+    }
+
+    /// Keeps the state of sliding windows in panes of one slide each
+    ///
+    /// Every element is added to a single pane instead of to all windows that it
+    /// belongs to, and windows are emitted by combining the state of their panes.
+    /// This requires a [Window] whose state can be combined, e.g., an
+    /// [IncrementalWindow](super::IncrementalWindow) created with `mergeable`.
+    ///
+    /// Triggers are called once per element with the last window of its pane, and firing
+    /// it emits all windows of the pane. As panes are shared by overlapping windows,
+    /// triggers can not purge windows.
+    pub fn with_panes(mut self) -> Self {
+        if self.session_gap.is_some() {
+            panic!("Session windows can not be split into panes!");
+        }
+        if !self.window.is_mergeable() {
+            panic!("Panes require a Window that can be merged!");
+        }
+        assert_eq!(
+            self.window_length % self.window_slide,
+            0,
+            "Panes require a window length that is divisible by the slide!"
+        );
+        self.panes = true;
+        self
+    }
+
+    /// Creates the context of a window that the trigger is called with
+    fn trigger_context(
+        &self,
+        window_ctx: WindowContext,
+        window_end: Timestamp,
+        time: Timestamp,
+    ) -> TriggerContext {
+        let completion_time = window_end + self.late_arrival_time;
+        let cleanup_time = completion_time + self.allowed_lateness;
+        TriggerContext::new(window_ctx, window_end, completion_time, cleanup_time, time)
+    }
+
+    /// Returns the time at which the state of a window ending at `window_end` is cleaned up
     #[inline]
-    fn new_window_trigger(
+    fn cleanup_time(&self, window_end: Timestamp) -> Timestamp {
+        window_end + self.late_arrival_time + self.allowed_lateness
+    }
+
+    /// Schedules the completion and cleanup timers of a window
+    ///
+    /// The window must not have expired, as its state is only cleaned up by the cleanup timer.
+    fn schedule_window_timers(
         &mut self,
         window_ctx: WindowContext,
+        window_end: Timestamp,
         ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
-    ) -> ArconResult<()> {
-        let window_start = match self.state.window_start().get(&window_ctx.key)? {
-            Some(start) => start,
+    ) -> OperatorResult<()> {
+        let WindowContext { key, index } = window_ctx;
+        let completion_time = window_end + self.late_arrival_time;
+        let cleanup_time = self.cleanup_time(window_end);
+        let processing_time = self.is_processing_time();
+
+        let cleanup = WindowEvent::new(key, index, window_end, cleanup_time, processing_time);
+        if !self.schedule_timer(cleanup, ctx)? {
+            return Err(ArconStateError::Unknown {
+                msg: format!("Window {:?} has expired at {}", window_ctx, cleanup_time),
+            });
+        }
+        // The completion timer has expired if the window is within its allowed lateness
+        if completion_time != cleanup_time {
+            let completion =
+                WindowEvent::new(key, index, window_end, completion_time, processing_time);
+            self.schedule_timer(completion, ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Schedules a timer for a window unless one is already scheduled at the same time
+    ///
+    /// Returns false if the time of the timer has already passed.
+    fn schedule_timer(
+        &mut self,
+        event: WindowEvent,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<bool> {
+        let id = event.timer_id();
+        let scheduled = if event.processing_time {
+            ctx.has_processing_timer(id)?
+                || ctx.schedule_processing_time(id, event.time, event).is_ok()
+        } else {
+            ctx.has_timer(id)? || ctx.schedule_at(id, event.time, event).is_ok()
+        };
+        Ok(scheduled)
+    }
+
+    /// Emits a late element to the late output, if there is one
+    fn handle_late_element(
+        &self,
+        element: ArconElement<IN>,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        ctx.mark_late_element();
+        if let Some(tag) = &self.late_output {
+            ctx.output_to(tag, element);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn is_processing_time(&self) -> bool {
+        self.time == ArconTime::Process
+    }
+
+    /// Returns the current time in the time domain of the assigner
+    #[inline]
+    fn current_time(
+        &self,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<Timestamp> {
+        match self.time {
+            ArconTime::Event => ctx.current_time(),
+            ArconTime::Process => Ok(ctx.current_processing_time()),
+        }
+    }
+
+    /// Adds an element to a window and calls the trigger for it
+    fn add_to_window(
+        &mut self,
+        element: &ArconElement<IN>,
+        timestamp: Timestamp,
+        window_ctx: WindowContext,
+        window_end: Timestamp,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        self.window.on_element(element.data.clone(), window_ctx)?;
+
+        let time = self.current_time(ctx)?;
+        let mut trigger_ctx = self.trigger_context(window_ctx, window_end, time);
+        let result = self.trigger.on_element(timestamp, &mut trigger_ctx)?;
+        self.handle_trigger(result, trigger_ctx, ctx)
+    }
+
+    /// Schedules the timers a trigger registered and emits or purges the window
+    fn handle_trigger(
+        &mut self,
+        result: TriggerResult,
+        trigger_ctx: TriggerContext,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let window_ctx = trigger_ctx.window();
+        let WindowContext { key, index } = window_ctx;
+        let window_end = trigger_ctx.window_end();
+        // Timers that a trigger registers for a time that has passed are never fired
+        for time in trigger_ctx.event_timers {
+            let event = WindowEvent::new(key, index, window_end, time, false);
+            self.schedule_timer(event, ctx)?;
+        }
+        for time in trigger_ctx.processing_timers {
+            let event = WindowEvent::new(key, index, window_end, time, true);
+            self.schedule_timer(event, ctx)?;
+        }
+
+        if result.is_fire() {
+            self.emit_window(window_ctx, window_end, ctx)?;
+        }
+        if result.is_purge() && !self.panes {
+            self.window.clear(window_ctx)?;
+        }
+        Ok(())
+    }
+
+    /// Emits the current result of a window
+    fn emit_window(
+        &mut self,
+        window_ctx: WindowContext,
+        window_end: Timestamp,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let WindowContext { key, index } = window_ctx;
+        let output = if self.panes {
+            let panes = index..index + self.panes_per_window();
+            self.window.combined_result(key, panes)?
+        } else {
+            self.window.result(window_ctx)?
+        };
+        ctx.output(ArconElement::with_timestamp(output, window_end));
+        Ok(())
+    }
+
+    /// Returns the number of panes of a window, window n consists of the panes n..n + length / slide
+    #[inline]
+    fn panes_per_window(&self) -> u64 {
+        self.window_length / self.window_slide
+    }
+
+    /// Adds an element to its pane and calls the trigger for the pane
+    ///
+    /// Pane n belongs to the `windows` that end with window n. Their timers are scheduled
+    /// when the pane receives its first element, so the cost of an element does not
+    /// depend on the number of windows it belongs to, unless the trigger fires.
+    fn add_to_pane(
+        &mut self,
+        element: &ArconElement<IN>,
+        timestamp: Timestamp,
+        key: Key,
+        windows: RangeInclusive<Index>,
+        window_end: Timestamp,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let (first, index) = windows.into_inner();
+        let pane_ctx = WindowContext::new(key, index);
+        let slide = self.window_slide;
+        let end_of = move |window: Index| window_end - (index - window) * slide;
+        self.window.on_element(element.data.clone(), pane_ctx)?;
+
+        if !self.state.active_windows().contains(&pane_ctx)? {
+            self.state.active_windows().put(pane_ctx, ())?;
+            for window in first..=index {
+                let window_ctx = WindowContext::new(key, window);
+                self.schedule_window_timers(window_ctx, end_of(window), ctx)?;
+            }
+        }
+
+        let time = self.current_time(ctx)?;
+        let mut trigger_ctx = self.trigger_context(pane_ctx, window_end, time);
+        let result = self.trigger.on_element(timestamp, &mut trigger_ctx)?;
+        // The last window of the pane is emitted by `handle_trigger`
+        if result.is_fire() {
+            for window in first..index {
+                self.emit_window(WindowContext::new(key, window), end_of(window), ctx)?;
+            }
+        }
+        self.handle_trigger(result, trigger_ctx, ctx)
+    }
+
+    /// Returns true if any pane of a window has received elements and has not been cleaned up
+    fn has_active_pane(&mut self, window_ctx: WindowContext) -> OperatorResult<bool> {
+        let WindowContext { key, index } = window_ctx;
+        for pane in index..index + self.panes_per_window() {
+            let pane_ctx = WindowContext::new(key, pane);
+            if self.state.active_windows().contains(&pane_ctx)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Adds an element to the session it extends, merging the sessions it connects
+    fn handle_session_element(
+        &mut self,
+        element: ArconElement<IN>,
+        gap: u64,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let ts = element.timestamp.unwrap_or(1);
+        let key = self.get_key(&element);
+        let mut sessions = self.state.sessions().get(&key)?.unwrap_or_default();
+
+        let (mut start, mut end) = (ts, ts + gap);
+        let (overlapping, mut remaining): (Vec<Session>, Vec<Session>) = sessions
+            .sessions
+            .drain(..)
+            .partition(|s| s.start <= end && start <= s.end);
+
+        // The state of all overlapping sessions is merged into the first one
+        let index = match overlapping.first() {
+            Some(session) => session.index,
             None => {
-                return arcon_err!(
-                    "Unexpected failure, could not find window start for existing key"
-                )
+                sessions.next_index += 1;
+                sessions.next_index - 1
             }
         };
+        let window_ctx = WindowContext::new(key, index);
+        for session in &overlapping {
+            start = start.min(session.start);
+            end = end.max(session.end);
+            if session.index != index {
+                let merged = WindowContext::new(key, session.index);
+                self.window.merge(window_ctx, merged)?;
+                let time = self.current_time(ctx)?;
+                let mut trigger_ctx = self.trigger_context(merged, session.end, time);
+                self.trigger.clear(&mut trigger_ctx)?;
+            }
+        }
+
+        remaining.push(Session { start, end, index });
+        sessions.sessions = remaining;
+        self.state.sessions().put(key, sessions)?;
 
-        let ts = window_start + (window_ctx.index * self.window_slide) + self.window_length;
+        // Timers of sessions that were merged or extended are ignored once they fire
+        self.schedule_window_timers(window_ctx, end, ctx)?;
 
-        ctx.schedule_at(
-            window_ctx,
-            ts + self.late_arrival_time,
-            WindowEvent::new(window_ctx.key, window_ctx.index, ts),
-        )
-        .map_err(|_| arcon_err_kind!("Attempted to schedule an expired timer"))
+        self.add_to_window(&element, ts, window_ctx, end, ctx)
+    }
+
+    /// Returns true if a session ending at `end` is still open
+    fn is_session_open(&mut self, key: Key, index: Index, end: Timestamp) -> OperatorResult<bool> {
+        let sessions = self.state.sessions().get(&key)?.unwrap_or_default();
+        Ok(sessions
+            .sessions
+            .iter()
+            .any(|s| s.index == index && s.end == end))
+    }
+
+    /// Removes a session once it is complete
+    fn close_session(&mut self, key: Key, index: Index) -> OperatorResult<()> {
+        let mut sessions = self.state.sessions().get(&key)?.unwrap_or_default();
+        sessions.sessions.retain(|s| s.index != index);
+        if sessions.sessions.is_empty() {
+            self.state.sessions().remove(&key)?;
+        } else {
+            self.state.sessions().put(key, sessions)?;
+        }
+        Ok(())
     }
 
     #[inline]
@@ -168,6 +603,56 @@ where
         }
         e.data.get_key()
     }
+
+    /// Calls the trigger for an expired window timer and cleans up the window if it is due
+    fn handle_window_timer(
+        &mut self,
+        timeout: WindowEvent,
+        ctx: &mut OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let WindowEvent {
+            key,
+            index,
+            timestamp,
+            time,
+            processing_time,
+        } = timeout;
+
+        let window_ctx = WindowContext::new(key, index);
+
+        // Timers of windows that have been cleaned up, merged or extended are ignored
+        let open = match self.session_gap {
+            Some(_) => self.is_session_open(key, index, timestamp)?,
+            None if self.panes => self.has_active_pane(window_ctx)?,
+            None => self.state.active_windows().contains(&window_ctx)?,
+        };
+        if !open {
+            return Ok(());
+        }
+
+        let mut trigger_ctx = self.trigger_context(window_ctx, timestamp, time);
+        let cleanup_time = trigger_ctx.cleanup_time();
+        let result = if processing_time {
+            self.trigger.on_processing_time(time, &mut trigger_ctx)?
+        } else {
+            self.trigger.on_event_time(time, &mut trigger_ctx)?
+        };
+        self.handle_trigger(result, trigger_ctx, ctx)?;
+
+        if time == cleanup_time && processing_time == self.is_processing_time() {
+            match self.session_gap {
+                Some(_) => self.close_session(key, index)?,
+                None => {
+                    self.state.active_windows().remove(&window_ctx)?;
+                }
+            }
+            // With panes, window n is the last window that pane n belongs to
+            self.window.clear(window_ctx)?;
+            let mut trigger_ctx = self.trigger_context(window_ctx, timestamp, time);
+            self.trigger.clear(&mut trigger_ctx)?;
+        }
+        Ok(())
+    }
 }
 
 impl<IN, OUT, W, B> Operator for WindowAssigner<IN, OUT, W, B>
@@ -187,52 +672,71 @@ where
         element: ArconElement<IN>,
         mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
     ) -> OperatorResult<()> {
-        let ts = element.timestamp.unwrap_or(1);
+        // Processing-time windows ignore the timestamps of elements
+        let ts = match self.time {
+            ArconTime::Event => element.timestamp.unwrap_or(1),
+            ArconTime::Process => ctx.current_processing_time(),
+        };
 
-        let time = ctx.current_time()?;
+        let time = self.current_time(&mut ctx)?;
 
-        let ts_lower_bound = time.saturating_sub(self.late_arrival_time);
+        let ts_lower_bound = time.saturating_sub(self.late_arrival_time + self.allowed_lateness);
 
         if ts < ts_lower_bound {
-            // Late arrival: early return
-            return Ok(());
+            // Late arrival: the element is only kept if there is a late output
+            return self.handle_late_element(element, &mut ctx);
         }
 
-        let key = self.get_key(&element);
+        if let Some(gap) = self.session_gap {
+            return self.handle_session_element(element, gap, &mut ctx);
+        }
 
-        // Will store the index of the highest and lowest window it should go into
-        let mut floor = 0;
-        let mut ceil = 0;
+        let key = self.get_key(&element);
 
-        match self.state.window_start().get(&key)? {
-            Some(start) => {
-                // Get the highest window the element goes into
-                ceil = (ts - start) / self.window_slide;
-                if ceil >= (self.window_length / self.window_slide) {
-                    floor = ceil - (self.window_length / self.window_slide) + 1;
-                }
-            }
+        let start = match self.state.window_start().get(&key)? {
+            Some(start) => start,
             None => {
                 self.state.window_start().put(key, ts)?;
+                ts
             }
+        };
+        // Windows of a key start at the first element of the key
+        if ts < start {
+            return self.handle_late_element(element, &mut ctx);
+        }
+
+        // The index of the highest and lowest window the element goes into
+        let ceil = (ts - start) / self.window_slide;
+        let windows_per_element = self.window_length / self.window_slide;
+        let floor = (ceil + 1).saturating_sub(windows_per_element);
+        let (slide, length) = (self.window_slide, self.window_length);
+        let window_end = move |index: u64| start + (index * slide) + length;
+
+        // Expired windows are not created again, as their state would never be cleaned up.
+        // Windows end in order of their index, so only the highest ones may still be open.
+        let first_open = match (floor..=ceil).find(|i| self.cleanup_time(window_end(*i)) > time) {
+            Some(index) => index,
+            None => return self.handle_late_element(element, &mut ctx),
+        };
+
+        // The element goes into the pane of the highest window
+        if self.panes {
+            let windows = first_open..=ceil;
+            return self.add_to_pane(&element, ts, key, windows, window_end(ceil), &mut ctx);
         }
 
         // For all windows, insert element....
-        for index in floor..=ceil {
+        for index in first_open..=ceil {
             let window_ctx = WindowContext { key, index };
-            self.window.on_element(element.data.clone(), window_ctx)?;
-
-            let active_exist = self.state.active_windows().contains(&window_ctx)?;
+            let window_end = window_end(index);
 
-            // if it does not exist, then add active window and create trigger
-            if !active_exist {
+            // if it does not exist, then add active window and schedule its completion
+            if !self.state.active_windows().contains(&window_ctx)? {
                 self.state.active_windows().put(window_ctx, ())?;
-
-                if let Err(event) = self.new_window_trigger(window_ctx, &mut ctx) {
-                    // I'm pretty sure this shouldn't happen
-                    unreachable!("Window was expired when scheduled: {:?}", event);
-                }
+                self.schedule_window_timers(window_ctx, window_end, &mut ctx)?;
             }
+
+            self.add_to_window(&element, ts, window_ctx, window_end, &mut ctx)?;
         }
 
         Ok(())
@@ -243,21 +747,15 @@ where
         timeout: Self::TimerState,
         mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
     ) -> OperatorResult<()> {
-        let WindowEvent {
-            key,
-            index,
-            timestamp,
-        } = timeout;
-
-        let window_ctx = WindowContext::new(key, index);
-
-        let result = self.window.result(window_ctx)?;
-
-        self.window.clear(window_ctx)?;
-        self.state.active_windows().remove(&window_ctx)?;
+        self.handle_window_timer(timeout, &mut ctx)
+    }
 
-        ctx.output(ArconElement::with_timestamp(result, timestamp));
-        Ok(())
+    fn handle_processing_timeout(
+        &mut self,
+        timeout: Self::TimerState,
+        mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        self.handle_window_timer(timeout, &mut ctx)
     }
 
     fn persist(&mut self) -> OperatorResult<()> {
@@ -273,20 +771,29 @@ mod tests {
     use super::*;
     use crate::{
         data::{ArconMessage, NodeID},
-        manager::node::{NodeManager, NodeManagerPort},
         pipeline::*,
         stream::{
             channel::{
                 strategy::{forward::Forward, ChannelStrategy},
                 Channel,
             },
-            node::{debug::DebugNode, Node, NodeState},
-            operator::window::AppenderWindow,
+            node::debug::DebugNode,
+            operator::{
+                side_output::{SideOutput, SideOutputs},
+                window::{
+                    tests::window_test_setup, AppenderWindow, CountTrigger, IncrementalWindow,
+                },
+            },
         },
     };
-    use kompact::prelude::{biconnect_components, ActorRefFactory, ActorRefStrong, Component};
+    use kompact::prelude::{ActorRefFactory, ActorRefStrong, Component};
     use std::{sync::Arc, thread, time, time::UNIX_EPOCH};
 
+    type TestAssigner =
+        WindowAssigner<u64, u64, AppenderWindow<u64, u64, arcon_state::Sled>, arcon_state::Sled>;
+
+    const LATE_OUTPUT: &str = "late";
+
     // helper functions
     fn window_assigner_test_setup(
         length: u64,
@@ -296,96 +803,58 @@ mod tests {
         ActorRefStrong<ArconMessage<u64>>,
         Arc<Component<DebugNode<u64>>>,
     ) {
-        let mut pipeline = Pipeline::default();
-        let pool_info = pipeline.get_pool_info();
-        let epoch_manager_ref = pipeline.epoch_manager();
+        let (assigner_ref, sink, _) = assigner_test_setup(move |window, backend| {
+            WindowAssigner::sliding(window, backend, length, slide, late, true)
+        });
+        (assigner_ref, sink)
+    }
 
-        // Create a sink
-        let sink = pipeline.data_system().create(DebugNode::<u64>::new);
+    /// Sets up an assigner whose side output `LATE_OUTPUT` is consumed by the third component
+    fn assigner_test_setup(
+        assigner: impl FnOnce(
+            AppenderWindow<u64, u64, arcon_state::Sled>,
+            Arc<arcon_state::Sled>,
+        ) -> TestAssigner,
+    ) -> (
+        ActorRefStrong<ArconMessage<u64>>,
+        Arc<Component<DebugNode<u64>>>,
+        Arc<Component<DebugNode<u64>>>,
+    ) {
+        let mut pipeline = Pipeline::default();
 
+        // Create a sink for the late side output
+        let late_sink = pipeline.data_system().create(DebugNode::<u64>::new);
         pipeline
             .data_system()
-            .start_notify(&sink)
+            .start_notify(&late_sink)
             .wait_timeout(std::time::Duration::from_millis(100))
             .expect("started");
-
-        let sink_ref: ActorRefStrong<ArconMessage<u64>> =
-            sink.actor_ref().hold().expect("failed to get strong ref");
-
-        let channel_strategy = ChannelStrategy::Forward(Forward::new(
-            Channel::Local(sink_ref),
+        let late_sink_ref: ActorRefStrong<ArconMessage<u64>> = late_sink
+            .actor_ref()
+            .hold()
+            .expect("failed to get strong ref");
+        let late_strategy = ChannelStrategy::Forward(Forward::new(
+            Channel::Local(late_sink_ref.clone()),
             NodeID::new(1),
-            pool_info,
+            pipeline.get_pool_info(),
         ));
-
-        let backend = Arc::new(crate::test_utils::temp_backend());
-        let descriptor = String::from("node_");
-        let in_channels = vec![0.into()];
-
-        let nm = NodeManager::<
-            WindowAssigner<
-                u64,
-                u64,
-                AppenderWindow<u64, u64, arcon_state::Sled>,
-                arcon_state::Sled,
-            >,
-            _,
-        >::new(
-            descriptor.clone(),
-            pipeline.data_system.clone(),
-            epoch_manager_ref,
-            in_channels.clone(),
-            backend.clone(),
+        let mut side_outputs = SideOutputs::default();
+        side_outputs.insert(
+            LATE_OUTPUT.to_string(),
+            SideOutput::new(late_strategy, vec![(late_sink_ref, NodeID::new(1))]),
         );
 
-        let node_manager_comp = pipeline.ctrl_system().create(|| nm);
-
-        pipeline
-            .ctrl_system()
-            .start_notify(&node_manager_comp)
-            .wait_timeout(std::time::Duration::from_millis(100))
-            .expect("started");
-
         fn appender_fn(u: &[u64]) -> u64 {
             u.len() as u64
         }
 
-        let window = AppenderWindow::new(backend.clone(), &appender_fn);
-
-        let window_assigner =
-            WindowAssigner::sliding(window, backend.clone(), length, slide, late, true);
-
-        let node = Node::new(
-            descriptor,
-            channel_strategy,
-            window_assigner,
-            NodeState::new(NodeID::new(0), in_channels, backend.clone()),
-            backend,
+        let (win_ref, sink) = window_test_setup(
+            &mut pipeline,
+            |backend| assigner(AppenderWindow::new(backend.clone(), &appender_fn), backend),
+            side_outputs,
         );
 
-        let window_comp = pipeline.data_system().create(|| node);
-        let required_ref = window_comp.on_definition(|cd| cd.node_manager_port.share());
-
-        biconnect_components::<NodeManagerPort, _, _>(&node_manager_comp, &window_comp)
-            .expect("connection");
-
-        pipeline
-            .data_system()
-            .start_notify(&window_comp)
-            .wait_timeout(std::time::Duration::from_millis(100))
-            .expect("started");
-
-        let win_ref: ActorRefStrong<ArconMessage<u64>> = window_comp
-            .actor_ref()
-            .hold()
-            .expect("failed to get strong ref");
-
-        node_manager_comp.on_definition(|cd| {
-            // Insert the created Node into the NodeManager
-            cd.nodes.insert(NodeID::new(0), (window_comp, required_ref));
-        });
-
-        (win_ref, sink)
+        (win_ref, sink, late_sink)
     }
     fn now() -> u64 {
         time::SystemTime::now()
@@ -523,6 +992,23 @@ mod tests {
         });
     }
     #[test]
+    fn window_end_of_input() {
+        // Sources send a watermark of u64::MAX once their input has ended
+        let (assigner_ref, sink) = window_assigner_test_setup(10, 10, 0);
+        wait(1);
+        let moment = now();
+        assigner_ref.tell(timestamped_event(moment));
+        assigner_ref.tell(timestamped_event(moment));
+        assigner_ref.tell(timestamped_event(moment + 20));
+        assigner_ref.tell(watermark(u64::MAX));
+        wait(2);
+        sink.on_definition(|cd| {
+            let results: Vec<u64> = cd.data.iter().map(|e| e.data).collect();
+            assert_eq!(results, vec![2, 1]);
+            assert_eq!(cd.watermarks.last().unwrap().timestamp, u64::MAX);
+        });
+    }
+    #[test]
     fn window_overlapping() {
         // Use overlapping windows (slide = length/2), check that messages appear correctly
         let (assigner_ref, sink) = window_assigner_test_setup(10, 5, 2);
@@ -544,6 +1030,193 @@ mod tests {
             assert_eq!(r1, &2);
         });
     }
+    #[test]
+    fn window_panes() {
+        // Same as window_overlapping, with every element added to a single pane
+        let (assigner_ref, sink, _) = assigner_test_setup(|window, backend| {
+            WindowAssigner::sliding(window, backend, 10, 5, 2, true).with_panes()
+        });
+        wait(1);
+        let moment = now();
+        assigner_ref.tell(timestamped_event(moment));
+        assigner_ref.tell(timestamped_event(moment + 6));
+        assigner_ref.tell(timestamped_event(moment + 6));
+        assigner_ref.tell(timestamped_event(moment + 11));
+        assigner_ref.tell(watermark(moment + 23));
+        wait(2);
+        sink.on_definition(|cd| {
+            let results: Vec<u64> = cd.data.iter().map(|e| e.data).collect();
+            assert_eq!(results, vec![3, 3, 1]);
+        });
+    }
+
+    #[test]
+    fn window_panes_trigger() {
+        let (assigner_ref, sink, _) = assigner_test_setup(|window, backend| {
+            WindowAssigner::sliding(window, backend.clone(), 10, 5, 0, true)
+                .with_panes()
+                .with_trigger(CountTrigger::new(backend, 2))
+        });
+        wait(1);
+        let moment = now();
+        assigner_ref.tell(timestamped_event(moment));
+        assigner_ref.tell(timestamped_event(moment + 6));
+        assigner_ref.tell(timestamped_event(moment + 7));
+        wait(1);
+        // The second element of the pane fires both windows of the pane
+        sink.on_definition(|cd| {
+            let results: Vec<(u64, Option<u64>)> =
+                cd.data.iter().map(|e| (e.data, e.timestamp)).collect();
+            assert_eq!(results, vec![
+                (3, Some(moment + 10)),
+                (2, Some(moment + 15))
+            ]);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Session windows require a Window that can be merged!")]
+    fn window_session_requires_merge() {
+        fn init(i: u64) -> u64 {
+            i
+        }
+        fn aggregation(i: u64, agg: &u64) -> u64 {
+            agg + i
+        }
+        let backend = Arc::new(crate::test_utils::temp_backend());
+        let window = IncrementalWindow::new(backend.clone(), &init, &aggregation);
+        let _ = WindowAssigner::<u64, u64, _, _>::session(window, backend, 5, 0, true);
+    }
+
+    #[test]
+    fn window_session_merge() {
+        let (assigner_ref, sink, _) = assigner_test_setup(|window, backend| {
+            WindowAssigner::session(window, backend, 5, 0, true)
+        });
+        wait(1);
+        let moment = now();
+        assigner_ref.tell(timestamped_keyed_event(moment, 1));
+        assigner_ref.tell(timestamped_keyed_event(moment + 3, 1));
+        assigner_ref.tell(timestamped_keyed_event(moment + 12, 1));
+        assigner_ref.tell(timestamped_keyed_event(moment, 2));
+        // Closes the gap between the two sessions of key 1
+        assigner_ref.tell(timestamped_keyed_event(moment + 7, 1));
+        assigner_ref.tell(watermark(moment + 30));
+        wait(2);
+        sink.on_definition(|cd| {
+            let results: Vec<(u64, Option<u64>)> =
+                cd.data.iter().map(|e| (e.data, e.timestamp)).collect();
+            assert_eq!(results, vec![(1, Some(moment + 5)), (4, Some(moment + 17))]);
+        });
+    }
+
+    #[test]
+    fn window_count_trigger() {
+        let (assigner_ref, sink, _) = assigner_test_setup(|window, backend| {
+            WindowAssigner::tumbling(window, backend.clone(), 10, 0, true)
+                .with_trigger(CountTrigger::new(backend, 2))
+        });
+        wait(1);
+        let moment = now();
+        for i in 0..5 {
+            assigner_ref.tell(timestamped_keyed_event(moment + i, 1));
+        }
+        wait(1);
+        // Fired early, before the window is complete
+        sink.on_definition(|cd| {
+            let results: Vec<u64> = cd.data.iter().map(|e| e.data).collect();
+            assert_eq!(results, vec![2, 4]);
+        });
+
+        // The window is cleaned up without firing again
+        assigner_ref.tell(watermark(moment + 12));
+        wait(1);
+        sink.on_definition(|cd| {
+            assert_eq!(cd.data.len(), 2);
+        });
+    }
+
+    #[test]
+    fn window_allowed_lateness() {
+        let (assigner_ref, sink, late_sink) = assigner_test_setup(|window, backend| {
+            WindowAssigner::tumbling(window, backend, 10, 0, true)
+                .with_allowed_lateness(5)
+                .with_late_output(OutputTag::new(LATE_OUTPUT))
+        });
+        wait(1);
+        let moment = now();
+        assigner_ref.tell(timestamped_event(moment));
+        assigner_ref.tell(timestamped_event(moment + 1));
+        assigner_ref.tell(watermark(moment + 12));
+        wait(1);
+        // Added to the fired window, which is emitted again
+        assigner_ref.tell(timestamped_event(moment + 8));
+        // Later than the allowed lateness
+        assigner_ref.tell(timestamped_event(moment + 3));
+        // Cleans up the window
+        assigner_ref.tell(watermark(moment + 16));
+        wait(1);
+        assigner_ref.tell(timestamped_event(moment + 9));
+        wait(1);
+
+        sink.on_definition(|cd| {
+            let results: Vec<(u64, Option<u64>)> =
+                cd.data.iter().map(|e| (e.data, e.timestamp)).collect();
+            assert_eq!(results, vec![
+                (2, Some(moment + 10)),
+                (3, Some(moment + 10))
+            ]);
+        });
+        late_sink.on_definition(|cd| {
+            let late: Vec<Option<u64>> = cd.data.iter().map(|e| e.timestamp).collect();
+            assert_eq!(late, vec![Some(moment + 3), Some(moment + 9)]);
+        });
+    }
+
+    #[test]
+    fn window_before_first_window_is_late() {
+        let (assigner_ref, sink, late_sink) = assigner_test_setup(|window, backend| {
+            WindowAssigner::tumbling(window, backend, 10, 0, true)
+                .with_late_output(OutputTag::new(LATE_OUTPUT))
+        });
+        wait(1);
+        let moment = now();
+        assigner_ref.tell(timestamped_event(moment + 5));
+        // Before the start of the first window of the key
+        assigner_ref.tell(timestamped_event(moment + 2));
+        assigner_ref.tell(watermark(moment + 16));
+        wait(1);
+
+        sink.on_definition(|cd| {
+            let results: Vec<(u64, Option<u64>)> =
+                cd.data.iter().map(|e| (e.data, e.timestamp)).collect();
+            assert_eq!(results, vec![(1, Some(moment + 15))]);
+        });
+        late_sink.on_definition(|cd| {
+            let late: Vec<Option<u64>> = cd.data.iter().map(|e| e.timestamp).collect();
+            assert_eq!(late, vec![Some(moment + 2)]);
+        });
+    }
+
+    #[test]
+    fn window_processing_time() {
+        let (assigner_ref, sink, _) = assigner_test_setup(|window, backend| {
+            WindowAssigner::tumbling_processing_time(window, backend, 200, true)
+        });
+        wait(1);
+        let before = crate::util::get_system_time();
+        for _ in 0..3 {
+            assigner_ref.tell(ArconMessage::element(1u64, None, 0.into()));
+        }
+        // Fired by the system time without any watermark
+        wait(1);
+        sink.on_definition(|cd| {
+            assert_eq!(cd.data.len(), 1);
+            assert_eq!(cd.data[0].data, 3);
+            assert!(cd.data[0].timestamp.unwrap() >= before + 200);
+        });
+    }
+
     #[test]
     fn window_empty() {
         // check that we receive correct number windows from fast forwarding
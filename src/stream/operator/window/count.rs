@@ -0,0 +1,193 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use super::{Window, WindowContext};
+use crate::{
+    data::{ArconElement, ArconNever, ArconType},
+    index::{ArconState, EagerHashTable, IndexOps, StateConstructor},
+    stream::operator::{Operator, OperatorContext},
+};
+use arcon_error::*;
+use arcon_macros::ArconState;
+use arcon_state::Backend;
+use kompact::prelude::ComponentDefinition;
+use std::{marker::PhantomData, sync::Arc};
+
+type Key = u64;
+
+#[derive(ArconState)]
+pub struct CountAssignerState<B: Backend> {
+    /// Number of elements that have been assigned per key
+    counts: EagerHashTable<Key, u64, B>,
+}
+
+impl<B: Backend> StateConstructor for CountAssignerState<B> {
+    type BackendType = B;
+    fn new(backend: Arc<Self::BackendType>) -> Self {
+        Self {
+            counts: EagerHashTable::new("_window_counts", backend),
+        }
+    }
+}
+
+/// Window Assigner Based on Element Counts
+///
+/// The n:th window of a key holds its elements `n * slide..n * slide + size` and is
+/// emitted as soon as its last element arrives, independently of watermarks. The
+/// output carries the timestamp of that element.
+///
+/// IN: Input event
+/// OUT: Output of Window
+pub struct CountWindowAssigner<IN, OUT, W, B>
+where
+    IN: ArconType,
+    OUT: ArconType,
+    W: Window<IN, OUT>,
+    B: Backend,
+{
+    window_size: u64,
+    window_slide: u64,
+    keyed: bool,
+    window: W,
+    state: CountAssignerState<B>,
+    op_state: (),
+    _marker: PhantomData<(IN, OUT)>,
+}
+
+impl<IN, OUT, W, B> CountWindowAssigner<IN, OUT, W, B>
+where
+    IN: ArconType,
+    OUT: ArconType,
+    W: Window<IN, OUT>,
+    B: Backend,
+{
+    /// Create a CountWindowAssigner for tumbling windows of `size` elements
+    pub fn tumbling(window: W, backend: Arc<B>, size: u64, keyed: bool) -> Self {
+        Self::sliding(window, backend, size, size, keyed)
+    }
+
+    /// Create a CountWindowAssigner for windows of `size` elements that start every `slide` elements
+    pub fn sliding(window: W, backend: Arc<B>, size: u64, slide: u64, keyed: bool) -> Self {
+        if slide == 0 {
+            panic!("Window slide must be greater than zero!");
+        }
+        if size < slide {
+            panic!("Window size lower than slide!");
+        }
+
+        CountWindowAssigner {
+            window_size: size,
+            window_slide: slide,
+            keyed,
+            window,
+            state: CountAssignerState::new(backend),
+            op_state: (),
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn get_key(&self, e: &ArconElement<IN>) -> u64 {
+        if !self.keyed {
+            return 0;
+        }
+        e.data.get_key()
+    }
+}
+
+impl<IN, OUT, W, B> Operator for CountWindowAssigner<IN, OUT, W, B>
+where
+    IN: ArconType,
+    OUT: ArconType,
+    W: Window<IN, OUT>,
+    B: Backend,
+{
+    type IN = IN;
+    type OUT = OUT;
+    type TimerState = ArconNever;
+    type OperatorState = ();
+
+    fn handle_element(
+        &mut self,
+        element: ArconElement<IN>,
+        mut ctx: OperatorContext<Self, impl Backend, impl ComponentDefinition>,
+    ) -> OperatorResult<()> {
+        let key = self.get_key(&element);
+        let count = self.state.counts().get(&key)?.unwrap_or(0);
+        self.state.counts().put(key, count + 1)?;
+
+        // Windows `floor..=ceil` hold the element at position `count`
+        let excess = (count + 1).checked_sub(self.window_size);
+        let floor = excess.map_or(0, |e| (e + self.window_slide - 1) / self.window_slide);
+        let ceil = count / self.window_slide;
+
+        for index in floor..=ceil {
+            self.window
+                .on_element(element.data.clone(), WindowContext::new(key, index))?;
+        }
+
+        // The element completes the lowest window if it takes its last position
+        if excess.map_or(false, |e| e % self.window_slide == 0) {
+            let window_ctx = WindowContext::new(key, floor);
+            let result = self.window.result(window_ctx)?;
+            self.window.clear(window_ctx)?;
+
+            ctx.output(ArconElement {
+                data: result,
+                timestamp: element.timestamp,
+            });
+        }
+
+        Ok(())
+    }
+
+    crate::ignore_timeout!();
+
+    fn persist(&mut self) -> OperatorResult<()> {
+        self.state.persist()
+    }
+    fn state(&mut self) -> &mut Self::OperatorState {
+        &mut self.op_state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::ArconMessage,
+        pipeline::Pipeline,
+        stream::operator::{
+            side_output::SideOutputs,
+            window::{tests::window_test_setup, AppenderWindow},
+        },
+    };
+    use std::{thread, time};
+
+    #[test]
+    fn count_window_sliding() {
+        fn sum(elements: &[u64]) -> u64 {
+            elements.iter().sum()
+        }
+        let mut pipeline = Pipeline::default();
+        let (window_ref, sink) = window_test_setup(
+            &mut pipeline,
+            |backend| {
+                let window = AppenderWindow::new(backend.clone(), &sum);
+                CountWindowAssigner::sliding(window, backend, 3, 2, false)
+            },
+            SideOutputs::default(),
+        );
+        for i in 1..=7 {
+            window_ref.tell(ArconMessage::element(i, Some(i), 0.into()));
+        }
+        thread::sleep(time::Duration::from_secs(1));
+
+        // Fired without any watermark
+        sink.on_definition(|cd| {
+            let results: Vec<(u64, Option<u64>)> =
+                cd.data.iter().map(|e| (e.data, e.timestamp)).collect();
+            assert_eq!(results, vec![(6, Some(3)), (12, Some(5)), (18, Some(7))]);
+        });
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::data::ArconType;
+
+/// Removes elements from an [AppenderWindow](super::AppenderWindow) when it is materialized
+///
+/// The elements that remain after `evict_after` are kept as the state of the window,
+/// which matters for windows that are fired more than once.
+pub trait Evictor<IN>: Send + 'static
+where
+    IN: ArconType,
+{
+    /// Called with the elements of the window before they are materialized
+    fn evict_before(&self, _elements: &mut Vec<IN>) {}
+
+    /// Called with the remaining elements of the window after they have been materialized
+    fn evict_after(&self, _elements: &mut Vec<IN>) {}
+}
+
+/// Keeps only the most recent elements of a window
+#[derive(Debug, Clone, Copy)]
+pub struct CountEvictor {
+    max_count: usize,
+    after: bool,
+}
+
+impl CountEvictor {
+    /// Materializes at most the `max_count` most recent elements
+    pub fn before(max_count: usize) -> Self {
+        CountEvictor {
+            max_count,
+            after: false,
+        }
+    }
+
+    /// Keeps at most the `max_count` most recent elements after materialization
+    pub fn after(max_count: usize) -> Self {
+        CountEvictor {
+            max_count,
+            after: true,
+        }
+    }
+
+    fn evict(&self, elements: &mut Vec<impl ArconType>) {
+        let excess = elements.len().saturating_sub(self.max_count);
+        elements.drain(..excess);
+    }
+}
+
+impl<IN> Evictor<IN> for CountEvictor
+where
+    IN: ArconType,
+{
+    fn evict_before(&self, elements: &mut Vec<IN>) {
+        if !self.after {
+            self.evict(elements);
+        }
+    }
+
+    fn evict_after(&self, elements: &mut Vec<IN>) {
+        if self.after {
+            self.evict(elements);
+        }
+    }
+}
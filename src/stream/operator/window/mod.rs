@@ -2,17 +2,32 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 pub mod assigner;
+pub mod count;
+pub mod evictor;
+pub mod trigger;
 
 pub use assigner::WindowAssigner;
+pub use count::CountWindowAssigner;
+pub use evictor::{CountEvictor, Evictor};
+pub use trigger::{
+    ContinuousEventTimeTrigger, CountTrigger, EventTimeTrigger, ProcessingTimeTrigger, Trigger,
+    TriggerContext, TriggerResult,
+};
 
 use crate::{
     prelude::*,
     util::{prost_helpers::ProstOption, SafelySendableFn},
 };
 use arcon_error::OperatorResult;
-use arcon_state::{backend::handles::ActiveHandle, Aggregator, AggregatorState, Backend, VecState};
+use arcon_state::{
+    backend::handles::ActiveHandle, error::ArconStateError, Aggregator, AggregatorState, Backend,
+    VecState,
+};
 use fxhash::FxHasher;
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    ops::Range,
+};
 
 #[derive(prost::Message, Hash, Copy, Clone)]
 pub struct WindowContext {
@@ -51,6 +66,28 @@ where
     fn result(&mut self, ctx: WindowContext) -> OperatorResult<OUT>;
     /// Clears the window state for the passed context
     fn clear(&mut self, ctx: WindowContext) -> OperatorResult<()>;
+    /// Returns true if the window supports [Window::merge] and [Window::combined_result]
+    ///
+    /// Session windows and panes are rejected for windows that can not be merged.
+    fn is_mergeable(&self) -> bool {
+        false
+    }
+    /// Merges the window state of `other` into `ctx` and clears `other`
+    fn merge(&mut self, _ctx: WindowContext, _other: WindowContext) -> OperatorResult<()> {
+        not_mergeable()
+    }
+    /// Returns the result of the windows of `key` with the given indexes as if they were one window
+    ///
+    /// Used to produce sliding windows from panes. The state of the windows is kept.
+    fn combined_result(&mut self, _key: u64, _indexes: Range<u64>) -> OperatorResult<OUT> {
+        not_mergeable()
+    }
+}
+
+fn not_mergeable<T>() -> OperatorResult<T> {
+    Err(ArconStateError::Unknown {
+        msg: "The window state can not be merged".to_owned(),
+    })
 }
 
 pub struct AppenderWindow<IN, OUT, B>
@@ -61,6 +98,7 @@ where
 {
     handle: ActiveHandle<B, VecState<IN>, u64, u64>,
     materializer: &'static dyn SafelySendableFn(&[IN]) -> OUT,
+    evictor: Option<Box<dyn Evictor<IN>>>,
 }
 
 impl<IN, OUT, B> AppenderWindow<IN, OUT, B>
@@ -84,8 +122,15 @@ where
         AppenderWindow {
             handle,
             materializer,
+            evictor: None,
         }
     }
+
+    /// Removes elements from the window with `evictor` whenever it is materialized
+    pub fn with_evictor(mut self, evictor: impl Evictor<IN>) -> Self {
+        self.evictor = Some(Box::new(evictor));
+        self
+    }
 }
 
 impl<IN, OUT, B> Window<IN, OUT> for AppenderWindow<IN, OUT, B>
@@ -106,8 +151,19 @@ where
         self.handle.set_item_key(ctx.key);
         self.handle.set_namespace(ctx.index);
 
-        let buf = self.handle.get()?;
-        Ok((self.materializer)(&buf))
+        let mut buf = self.handle.get()?;
+        let evictor = match &self.evictor {
+            Some(evictor) => evictor,
+            None => return Ok((self.materializer)(&buf)),
+        };
+
+        evictor.evict_before(&mut buf);
+        let result = (self.materializer)(&buf);
+        evictor.evict_after(&mut buf);
+
+        // Evicted elements are removed from the window
+        self.handle.set(buf)?;
+        Ok(result)
     }
 
     fn clear(&mut self, ctx: WindowContext) -> OperatorResult<()> {
@@ -117,12 +173,49 @@ where
         self.handle.clear()?;
         Ok(())
     }
+
+    fn is_mergeable(&self) -> bool {
+        true
+    }
+
+    fn merge(&mut self, ctx: WindowContext, other: WindowContext) -> OperatorResult<()> {
+        self.handle.set_item_key(other.key);
+        self.handle.set_namespace(other.index);
+
+        let elements = self.handle.get()?;
+        self.handle.clear()?;
+
+        self.handle.set_item_key(ctx.key);
+        self.handle.set_namespace(ctx.index);
+
+        self.handle.add_all(elements)?;
+        Ok(())
+    }
+
+    /// Materializes the elements of all windows in order of their index
+    ///
+    /// The evictor is only applied before materialization, as the windows are left untouched.
+    fn combined_result(&mut self, key: u64, indexes: Range<u64>) -> OperatorResult<OUT> {
+        self.handle.set_item_key(key);
+
+        let mut buf = Vec::new();
+        for index in indexes {
+            self.handle.set_namespace(index);
+            buf.extend(self.handle.get()?);
+        }
+
+        if let Some(evictor) = &self.evictor {
+            evictor.evict_before(&mut buf);
+        }
+        Ok((self.materializer)(&buf))
+    }
 }
 
 #[derive(Clone)]
 pub struct IncrementalWindowAggregator<IN: ArconType, OUT: ArconType>(
     &'static dyn SafelySendableFn(IN) -> OUT,
     &'static dyn SafelySendableFn(IN, &OUT) -> OUT,
+    Option<&'static dyn SafelySendableFn(&OUT, &OUT) -> OUT>,
 );
 
 impl<IN: ArconType, OUT: ArconType> Aggregator for IncrementalWindowAggregator<IN, OUT> {
@@ -145,10 +238,21 @@ impl<IN: ArconType, OUT: ArconType> Aggregator for IncrementalWindowAggregator<I
 
     fn merge_accumulators(
         &self,
-        _fst: Self::Accumulator,
-        _snd: Self::Accumulator,
+        fst: Self::Accumulator,
+        snd: Self::Accumulator,
     ) -> Self::Accumulator {
-        unimplemented!()
+        let (fst, snd): (Option<OUT>, Option<OUT>) = (fst.into(), snd.into());
+        match (fst, snd) {
+            (Some(fst), Some(snd)) => {
+                // Windows without a merge function are never merged, see `is_mergeable`
+                let merge = self
+                    .2
+                    .expect("IncrementalWindow was created without a merge function");
+                Some(merge(&fst, &snd)).into()
+            }
+            (fst, None) => fst.into(),
+            (None, snd) => snd.into(),
+        }
     }
 
     fn accumulator_into_result(&self, acc: Self::Accumulator) -> Self::Result {
@@ -164,6 +268,8 @@ where
     B: Backend,
 {
     aggregator: ActiveHandle<B, AggregatorState<IncrementalWindowAggregator<IN, OUT>>, u64, u64>,
+    /// Used to combine the accumulators of several windows
+    functions: IncrementalWindowAggregator<IN, OUT>,
 }
 
 impl<IN, OUT, B> IncrementalWindow<IN, OUT, B>
@@ -177,18 +283,39 @@ where
         init: &'static dyn SafelySendableFn(IN) -> OUT,
         agg: &'static dyn SafelySendableFn(IN, &OUT) -> OUT,
     ) -> IncrementalWindow<IN, OUT, B> {
-        let mut aggregator = Handle::aggregator(
-            "incremental_window_aggregating_state",
-            IncrementalWindowAggregator(init, agg),
-        )
-        .with_item_key(0)
-        .with_namespace(0);
+        Self::setup(backend, IncrementalWindowAggregator(init, agg, None))
+    }
+
+    /// Creates an IncrementalWindow whose partial aggregates can be combined by `merge`
+    ///
+    /// Required by windows that are merged, such as session windows, and by windows
+    /// that are combined from panes.
+    pub fn mergeable(
+        backend: Arc<B>,
+        init: &'static dyn SafelySendableFn(IN) -> OUT,
+        agg: &'static dyn SafelySendableFn(IN, &OUT) -> OUT,
+        merge: &'static dyn SafelySendableFn(&OUT, &OUT) -> OUT,
+    ) -> IncrementalWindow<IN, OUT, B> {
+        Self::setup(backend, IncrementalWindowAggregator(init, agg, Some(merge)))
+    }
+
+    fn setup(
+        backend: Arc<B>,
+        aggregator: IncrementalWindowAggregator<IN, OUT>,
+    ) -> IncrementalWindow<IN, OUT, B> {
+        let functions = aggregator.clone();
+        let mut aggregator = Handle::aggregator("incremental_window_aggregating_state", aggregator)
+            .with_item_key(0)
+            .with_namespace(0);
 
         backend.register_aggregator_handle(&mut aggregator);
 
         let aggregator = aggregator.activate(backend);
 
-        IncrementalWindow { aggregator }
+        IncrementalWindow {
+            aggregator,
+            functions,
+        }
     }
 }
 
@@ -220,14 +347,152 @@ where
 
         Ok(self.aggregator.clear()?)
     }
+
+    /// Only an IncrementalWindow created with `mergeable` can be merged
+    fn is_mergeable(&self) -> bool {
+        self.functions.2.is_some()
+    }
+
+    fn merge(&mut self, ctx: WindowContext, other: WindowContext) -> OperatorResult<()> {
+        if !self.is_mergeable() {
+            return not_mergeable();
+        }
+        self.aggregator.set_item_key(other.key);
+        self.aggregator.set_namespace(other.index);
+
+        let accumulator = self.aggregator.get_accumulator()?;
+        self.aggregator.clear()?;
+
+        if let Some(accumulator) = accumulator {
+            self.aggregator.set_item_key(ctx.key);
+            self.aggregator.set_namespace(ctx.index);
+
+            self.aggregator.merge(accumulator)?;
+        }
+        Ok(())
+    }
+
+    fn combined_result(&mut self, key: u64, indexes: Range<u64>) -> OperatorResult<OUT> {
+        if !self.is_mergeable() {
+            return not_mergeable();
+        }
+        self.aggregator.set_item_key(key);
+
+        let mut combined = self.functions.create_accumulator();
+        for index in indexes {
+            self.aggregator.set_namespace(index);
+            if let Some(accumulator) = self.aggregator.get_accumulator()? {
+                combined = self.functions.merge_accumulators(combined, accumulator);
+            }
+        }
+
+        Ok(self.functions.accumulator_into_result(combined))
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub mod tests {
     use super::*;
-    use crate::test_utils::temp_backend;
+    use crate::{
+        data::{ArconMessage, NodeID},
+        manager::node::{NodeManager, NodeManagerPort},
+        pipeline::Pipeline,
+        stream::{
+            channel::{
+                strategy::{forward::Forward, ChannelStrategy},
+                Channel,
+            },
+            node::{debug::DebugNode, Node, NodeState},
+            operator::side_output::SideOutputs,
+        },
+        test_utils::temp_backend,
+    };
+    use kompact::prelude::{biconnect_components, ActorRefFactory, ActorRefStrong, Component};
     use std::sync::Arc;
 
+    /// Runs the operator created by `operator` in a started Node of `pipeline`
+    ///
+    /// Returns a reference to the Node and the sink that its output is forwarded to.
+    pub(crate) fn window_test_setup<OP>(
+        pipeline: &mut Pipeline,
+        operator: impl FnOnce(Arc<arcon_state::Sled>) -> OP,
+        side_outputs: SideOutputs,
+    ) -> (
+        ActorRefStrong<ArconMessage<OP::IN>>,
+        Arc<Component<DebugNode<OP::OUT>>>,
+    )
+    where
+        OP: Operator + 'static,
+    {
+        let pool_info = pipeline.get_pool_info();
+        let epoch_manager_ref = pipeline.epoch_manager();
+
+        // Create a sink
+        let sink = pipeline.data_system().create(DebugNode::<OP::OUT>::new);
+        pipeline
+            .data_system()
+            .start_notify(&sink)
+            .wait_timeout(std::time::Duration::from_millis(100))
+            .expect("started");
+
+        let sink_ref: ActorRefStrong<ArconMessage<OP::OUT>> =
+            sink.actor_ref().hold().expect("failed to get strong ref");
+        let channel_strategy = ChannelStrategy::Forward(Forward::new(
+            Channel::Local(sink_ref),
+            NodeID::new(1),
+            pool_info,
+        ));
+
+        let backend = Arc::new(temp_backend());
+        let descriptor = String::from("window_");
+        let in_channels = vec![0.into()];
+
+        let nm = NodeManager::<OP, _>::new(
+            descriptor.clone(),
+            pipeline.data_system.clone(),
+            epoch_manager_ref,
+            in_channels.clone(),
+            backend.clone(),
+        );
+        let node_manager_comp = pipeline.ctrl_system().create(|| nm);
+        pipeline
+            .ctrl_system()
+            .start_notify(&node_manager_comp)
+            .wait_timeout(std::time::Duration::from_millis(100))
+            .expect("started");
+
+        let node = Node::new(
+            descriptor,
+            channel_strategy,
+            operator(backend.clone()),
+            NodeState::new(NodeID::new(0), in_channels, backend.clone()),
+            backend,
+        )
+        .unwrap()
+        .with_side_outputs(side_outputs);
+
+        let window_comp = pipeline.data_system().create(|| node);
+        let required_ref = window_comp.on_definition(|cd| cd.node_manager_port.share());
+        biconnect_components::<NodeManagerPort, _, _>(&node_manager_comp, &window_comp)
+            .expect("connection");
+        pipeline
+            .data_system()
+            .start_notify(&window_comp)
+            .wait_timeout(std::time::Duration::from_millis(100))
+            .expect("started");
+
+        let window_ref: ActorRefStrong<ArconMessage<OP::IN>> = window_comp
+            .actor_ref()
+            .hold()
+            .expect("failed to get strong ref");
+        node_manager_comp.on_definition(|cd| {
+            // Insert the created Node into the NodeManager
+            cd.nodes.insert(NodeID::new(0), (window_comp, required_ref));
+        });
+
+        (window_ref, sink)
+    }
+
     #[test]
     fn sum_appender_window_test() {
         let backend = Arc::new(temp_backend());
@@ -248,6 +513,29 @@ mod tests {
         assert_eq!(sum, expected);
     }
 
+    #[test]
+    fn evicting_appender_window_test() {
+        let backend = Arc::new(temp_backend());
+
+        fn materializer(buffer: &[i32]) -> i32 {
+            buffer.iter().sum()
+        }
+
+        let mut before = AppenderWindow::new(backend.clone(), &materializer)
+            .with_evictor(CountEvictor::before(3));
+        let mut after =
+            AppenderWindow::new(backend, &materializer).with_evictor(CountEvictor::after(3));
+
+        for i in 0..10 {
+            before.on_element(i, WindowContext::new(0, 0)).unwrap();
+            after.on_element(i, WindowContext::new(0, 1)).unwrap();
+        }
+
+        assert_eq!(before.result(WindowContext::new(0, 0)).unwrap(), 24);
+        assert_eq!(after.result(WindowContext::new(0, 1)).unwrap(), 45);
+        assert_eq!(after.result(WindowContext::new(0, 1)).unwrap(), 24);
+    }
+
     #[test]
     fn sum_incremental_window_test() {
         let backend = Arc::new(temp_backend());
@@ -274,4 +562,52 @@ mod tests {
         let sum_two = window.result(WindowContext::new(1, 1)).unwrap();
         assert_eq!(sum_two, 190);
     }
+
+    #[test]
+    fn merge_windows_test() {
+        let backend = Arc::new(temp_backend());
+
+        fn materializer(buffer: &[i32]) -> i32 {
+            buffer.iter().sum()
+        }
+        fn init(i: i32) -> u64 {
+            i as u64
+        }
+        fn aggregation(i: i32, agg: &u64) -> u64 {
+            agg + i as u64
+        }
+        fn merge(fst: &u64, snd: &u64) -> u64 {
+            fst + snd
+        }
+
+        let mut appender = AppenderWindow::new(backend.clone(), &materializer);
+        let mut incremental = IncrementalWindow::mergeable(backend, &init, &aggregation, &merge);
+
+        for i in 0..10 {
+            let ctx = WindowContext::new(0, i % 2);
+            appender.on_element(i as i32, ctx).unwrap();
+            incremental.on_element(i as i32, ctx).unwrap();
+        }
+
+        // Combining keeps the state of both windows
+        assert_eq!(appender.combined_result(0, 0..2).unwrap(), 45);
+        assert_eq!(incremental.combined_result(0, 0..3).unwrap(), 45);
+        assert_eq!(incremental.combined_result(0, 1..2).unwrap(), 25);
+
+        let (ctx, other) = (WindowContext::new(0, 0), WindowContext::new(0, 1));
+        appender.merge(ctx, other).unwrap();
+        incremental.merge(ctx, other).unwrap();
+
+        assert_eq!(appender.result(ctx).unwrap(), 45);
+        assert_eq!(appender.result(other).unwrap(), 0);
+        assert_eq!(incremental.result(ctx).unwrap(), 45);
+
+        // Windows without a merge function fail instead of panicking
+        let mut unmergeable = IncrementalWindow::new(Arc::new(temp_backend()), &init, &aggregation);
+        unmergeable.on_element(1, ctx).unwrap();
+        unmergeable.on_element(2, other).unwrap();
+        assert!(!unmergeable.is_mergeable());
+        assert!(unmergeable.merge(ctx, other).is_err());
+        assert!(unmergeable.combined_result(0, 0..2).is_err());
+    }
 }
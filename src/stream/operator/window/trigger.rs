@@ -0,0 +1,330 @@
+// Copyright (c) 2021, KTH Royal Institute of Technology.
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use super::WindowContext;
+use crate::index::EagerHashTable;
+use arcon_error::OperatorResult;
+use arcon_state::Backend;
+use std::sync::Arc;
+
+/// What a [WindowAssigner](super::WindowAssigner) does with a window after a [Trigger] was called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerResult {
+    /// Does nothing
+    Continue,
+    /// Emits the current result of the window and keeps its state
+    Fire,
+    /// Clears the state of the window without emitting it
+    Purge,
+    /// Emits the current result of the window and clears its state
+    FireAndPurge,
+}
+
+impl TriggerResult {
+    #[inline]
+    pub fn is_fire(self) -> bool {
+        matches!(self, TriggerResult::Fire | TriggerResult::FireAndPurge)
+    }
+
+    #[inline]
+    pub fn is_purge(self) -> bool {
+        matches!(self, TriggerResult::Purge | TriggerResult::FireAndPurge)
+    }
+}
+
+/// The window a [Trigger] is called for
+pub struct TriggerContext {
+    window: WindowContext,
+    window_end: u64,
+    completion_time: u64,
+    cleanup_time: u64,
+    current_time: u64,
+    pub(crate) event_timers: Vec<u64>,
+    pub(crate) processing_timers: Vec<u64>,
+}
+
+impl TriggerContext {
+    pub(crate) fn new(
+        window: WindowContext,
+        window_end: u64,
+        completion_time: u64,
+        cleanup_time: u64,
+        current_time: u64,
+    ) -> Self {
+        TriggerContext {
+            window,
+            window_end,
+            completion_time,
+            cleanup_time,
+            current_time,
+            event_timers: Vec::new(),
+            processing_timers: Vec::new(),
+        }
+    }
+
+    /// Returns the key and index of the window
+    #[inline]
+    pub fn window(&self) -> WindowContext {
+        self.window
+    }
+
+    /// Returns the end timestamp of the window, which is the timestamp of its results
+    #[inline]
+    pub fn window_end(&self) -> u64 {
+        self.window_end
+    }
+
+    /// Returns the time at which the window is complete
+    ///
+    /// This is the end of the window plus the late arrival time of the assigner.
+    #[inline]
+    pub fn completion_time(&self) -> u64 {
+        self.completion_time
+    }
+
+    /// Returns the time at which the state of the window is cleaned up
+    ///
+    /// This is the completion time plus the allowed lateness of the assigner.
+    /// Elements that arrive between the two are still added to the window.
+    #[inline]
+    pub fn cleanup_time(&self) -> u64 {
+        self.cleanup_time
+    }
+
+    /// Returns the current time in the time domain of the assigner
+    ///
+    /// This is the event time, or the processing time for processing-time windows.
+    #[inline]
+    pub fn current_time(&self) -> u64 {
+        self.current_time
+    }
+
+    /// Calls [Trigger::on_event_time] for the window once the event time reaches `time`
+    ///
+    /// Registering a timer twice has no effect and timers that have already expired are ignored.
+    #[inline]
+    pub fn register_event_time_timer(&mut self, time: u64) {
+        self.event_timers.push(time);
+    }
+
+    /// Calls [Trigger::on_processing_time] for the window once the system time reaches `time`
+    ///
+    /// Registering a timer twice has no effect and timers that have already expired are ignored.
+    #[inline]
+    pub fn register_processing_time_timer(&mut self, time: u64) {
+        self.processing_timers.push(time);
+    }
+}
+
+/// Decides when the windows of a [WindowAssigner](super::WindowAssigner) are emitted
+///
+/// A window is always cleaned up at its cleanup time, after the trigger has been called
+/// with it. Triggers that keep state per window should remove it in [Trigger::clear].
+pub trait Trigger: Send + 'static {
+    /// Called for every element that is added to a window
+    fn on_element(
+        &mut self,
+        timestamp: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult>;
+
+    /// Called when an event-time timer of the window expires
+    fn on_event_time(
+        &mut self,
+        time: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult>;
+
+    /// Called when a processing-time timer of the window expires
+    fn on_processing_time(
+        &mut self,
+        time: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult>;
+
+    /// Clears the state that the trigger keeps for the window
+    fn clear(&mut self, ctx: &mut TriggerContext) -> OperatorResult<()>;
+}
+
+/// Fires a window once when it is complete
+///
+/// Late elements that are added to a complete window fire it again with the updated result.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EventTimeTrigger;
+
+impl Trigger for EventTimeTrigger {
+    fn on_element(&mut self, _: u64, ctx: &mut TriggerContext) -> OperatorResult<TriggerResult> {
+        if ctx.current_time() >= ctx.completion_time() {
+            Ok(TriggerResult::Fire)
+        } else {
+            Ok(TriggerResult::Continue)
+        }
+    }
+
+    fn on_event_time(
+        &mut self,
+        time: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        if time == ctx.completion_time() {
+            Ok(TriggerResult::Fire)
+        } else {
+            Ok(TriggerResult::Continue)
+        }
+    }
+
+    fn on_processing_time(
+        &mut self,
+        _: u64,
+        _: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        Ok(TriggerResult::Continue)
+    }
+
+    fn clear(&mut self, _: &mut TriggerContext) -> OperatorResult<()> {
+        Ok(())
+    }
+}
+
+/// Fires a window once when it is complete in processing time
+///
+/// The default trigger of processing-time windows.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProcessingTimeTrigger;
+
+impl Trigger for ProcessingTimeTrigger {
+    fn on_element(&mut self, _: u64, _: &mut TriggerContext) -> OperatorResult<TriggerResult> {
+        Ok(TriggerResult::Continue)
+    }
+
+    fn on_event_time(&mut self, _: u64, _: &mut TriggerContext) -> OperatorResult<TriggerResult> {
+        Ok(TriggerResult::Continue)
+    }
+
+    fn on_processing_time(
+        &mut self,
+        time: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        if time == ctx.completion_time() {
+            Ok(TriggerResult::Fire)
+        } else {
+            Ok(TriggerResult::Continue)
+        }
+    }
+
+    fn clear(&mut self, _: &mut TriggerContext) -> OperatorResult<()> {
+        Ok(())
+    }
+}
+
+/// Fires a window every `count` elements
+///
+/// The window is not fired when it is complete, unless its last element completes a count.
+pub struct CountTrigger<B: Backend> {
+    count: u64,
+    counts: EagerHashTable<WindowContext, u64, B>,
+}
+
+impl<B: Backend> CountTrigger<B> {
+    pub fn new(backend: Arc<B>, count: u64) -> Self {
+        if count == 0 {
+            panic!("Trigger count must be greater than zero!");
+        }
+        CountTrigger {
+            count,
+            counts: EagerHashTable::new("_count_trigger", backend),
+        }
+    }
+}
+
+impl<B: Backend> Trigger for CountTrigger<B> {
+    fn on_element(&mut self, _: u64, ctx: &mut TriggerContext) -> OperatorResult<TriggerResult> {
+        let window = ctx.window();
+        let count = self.counts.get(&window)?.unwrap_or(0) + 1;
+        if count == self.count {
+            self.counts.remove(&window)?;
+            Ok(TriggerResult::Fire)
+        } else {
+            self.counts.put(window, count)?;
+            Ok(TriggerResult::Continue)
+        }
+    }
+
+    fn on_event_time(&mut self, _: u64, _: &mut TriggerContext) -> OperatorResult<TriggerResult> {
+        Ok(TriggerResult::Continue)
+    }
+
+    fn on_processing_time(
+        &mut self,
+        _: u64,
+        _: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        Ok(TriggerResult::Continue)
+    }
+
+    fn clear(&mut self, ctx: &mut TriggerContext) -> OperatorResult<()> {
+        self.counts.remove(&ctx.window())?;
+        Ok(())
+    }
+}
+
+/// Fires a window every `interval` of event time and once it is complete
+///
+/// Used to emit early, speculative results of long windows that are updated as
+/// elements arrive. Firings are aligned to multiples of `interval`.
+#[derive(Debug, Clone, Copy)]
+pub struct ContinuousEventTimeTrigger {
+    interval: u64,
+}
+
+impl ContinuousEventTimeTrigger {
+    pub fn new(interval: u64) -> Self {
+        if interval == 0 {
+            panic!("Trigger interval must be greater than zero!");
+        }
+        ContinuousEventTimeTrigger { interval }
+    }
+
+    fn register_next(&self, time: u64, ctx: &mut TriggerContext) {
+        let next = time - time % self.interval + self.interval;
+        ctx.register_event_time_timer(next.min(ctx.completion_time()));
+    }
+}
+
+impl Trigger for ContinuousEventTimeTrigger {
+    fn on_element(
+        &mut self,
+        timestamp: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        if ctx.current_time() >= ctx.completion_time() {
+            return Ok(TriggerResult::Fire);
+        }
+        self.register_next(timestamp, ctx);
+        Ok(TriggerResult::Continue)
+    }
+
+    fn on_event_time(
+        &mut self,
+        time: u64,
+        ctx: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        if time < ctx.completion_time() {
+            self.register_next(time, ctx);
+        }
+        Ok(TriggerResult::Fire)
+    }
+
+    fn on_processing_time(
+        &mut self,
+        _: u64,
+        _: &mut TriggerContext,
+    ) -> OperatorResult<TriggerResult> {
+        Ok(TriggerResult::Continue)
+    }
+
+    fn clear(&mut self, _: &mut TriggerContext) -> OperatorResult<()> {
+        Ok(())
+    }
+}
@@ -1,10 +1,10 @@
 // Copyright (c) 2020, KTH Royal Institute of Technology.
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use super::{Source, SourceContext};
+use super::{ParallelSource, Source, SourceContext};
 use crate::{data::ArconType, dataflow::conf::SourceConf, stream::time::ArconTime};
 use kompact::prelude::*;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 
 const RESCHEDULE_EVERY: usize = 5000;
 
@@ -13,6 +13,8 @@ where
     A: ArconType,
 {
     data: RefCell<Vec<A>>,
+    /// Number of records that have been read
+    position: Cell<u64>,
     conf: SourceConf<A>,
 }
 
@@ -23,6 +25,7 @@ where
     pub fn new(data: Vec<A>, conf: SourceConf<A>) -> Self {
         Self {
             data: RefCell::new(data),
+            position: Cell::new(0),
             conf,
         }
     }
@@ -36,6 +39,7 @@ where
 
     fn process_batch(&self, mut ctx: SourceContext<Self, impl ComponentDefinition>) {
         let drain_to = RESCHEDULE_EVERY.min(self.data.borrow().len());
+        self.position.set(self.position.get() + drain_to as u64);
         for record in self.data.borrow_mut().drain(..drain_to) {
             match &self.conf.time {
                 ArconTime::Event => match &self.conf.extractor {
@@ -43,6 +47,8 @@ where
                         let timestamp = extractor(&record);
                         ctx.output_with_timestamp(record, timestamp);
                     }
+                    // The record is timestamped by the SourceContext
+                    None if self.conf.ingestion_time() => ctx.output(record),
                     None => panic!("Cannot use ArconTime::Event without an timestamp extractor"),
                 },
                 ArconTime::Process => ctx.output(record),
@@ -52,6 +58,61 @@ where
             ctx.signal_end();
         }
     }
+
+    fn position(&self) -> Option<u64> {
+        Some(self.position.get())
+    }
+
+    fn seek(&mut self, position: u64) {
+        let data = self.data.get_mut();
+        let skip = (position.saturating_sub(self.position.get()) as usize).min(data.len());
+        data.drain(..skip);
+        self.position.set(self.position.get() + skip as u64);
+    }
+}
+
+/// A collection that is split into chunks which are read in parallel
+pub struct ParallelCollection<A>
+where
+    A: ArconType,
+{
+    data: Vec<A>,
+    splits: usize,
+    conf: SourceConf<A>,
+}
+
+impl<A> ParallelCollection<A>
+where
+    A: ArconType,
+{
+    pub fn new(data: Vec<A>, splits: usize, conf: SourceConf<A>) -> Self {
+        assert!(
+            splits > 0,
+            "A ParallelCollection requires at least one split"
+        );
+        Self { data, splits, conf }
+    }
+}
+
+impl<A> ParallelSource for ParallelCollection<A>
+where
+    A: ArconType,
+{
+    type Split = Vec<A>;
+    type Reader = CollectionSource<A>;
+
+    fn splits(&self) -> Vec<Self::Split> {
+        let chunk_size = ((self.data.len() + self.splits - 1) / self.splits).max(1);
+        self.data
+            .chunks(chunk_size)
+            .map(|chunk| chunk.to_vec())
+            .collect()
+    }
+
+    fn reader(&self, splits: Vec<Self::Split>) -> Self::Reader {
+        let data = splits.into_iter().flatten().collect();
+        CollectionSource::new(data, self.conf.clone())
+    }
 }
 
 /*
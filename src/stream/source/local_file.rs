@@ -11,7 +11,7 @@ use crate::{
 };
 use kompact::prelude::*;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     fs::File,
     io::{BufRead, BufReader},
     str::FromStr,
@@ -24,6 +24,8 @@ where
     A: ArconType + FromStr,
 {
     lines: RefCell<Vec<String>>,
+    /// Number of lines that have been read
+    position: Cell<u64>,
     conf: SourceConf<A>,
 }
 
@@ -40,6 +42,7 @@ where
             .expect("");
         LocalFileSource {
             lines: RefCell::new(lines),
+            position: Cell::new(0),
             conf,
         }
     }
@@ -53,6 +56,7 @@ where
 
     fn process_batch(&self, mut ctx: SourceContext<Self, impl ComponentDefinition>) {
         let drain_to = RESCHEDULE_EVERY.min(self.lines.borrow().len());
+        self.position.set(self.position.get() + drain_to as u64);
         for line in self.lines.borrow_mut().drain(..drain_to) {
             if let Ok(record) = line.parse::<A>() {
                 match &self.conf.time {
@@ -61,6 +65,8 @@ where
                             let timestamp = extractor(&record);
                             ctx.output_with_timestamp(record, timestamp);
                         }
+                        // The record is timestamped by the SourceContext
+                        None if self.conf.ingestion_time() => ctx.output(record),
                         None => {
                             panic!("Cannot use ArconTime::Event without an timestamp extractor")
                         }
@@ -75,6 +81,17 @@ where
             ctx.signal_end();
         }
     }
+
+    fn position(&self) -> Option<u64> {
+        Some(self.position.get())
+    }
+
+    fn seek(&mut self, position: u64) {
+        let lines = self.lines.get_mut();
+        let skip = (position.saturating_sub(self.position.get()) as usize).min(lines.len());
+        lines.drain(..skip);
+        self.position.set(self.position.get() + skip as u64);
+    }
 }
 
 /*
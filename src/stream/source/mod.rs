@@ -3,7 +3,7 @@
 
 use crate::{
     data::{ArconElement, ArconEvent, ArconType},
-    stream::channel::strategy::ChannelStrategy,
+    stream::{channel::strategy::ChannelStrategy, time::WatermarkStrategy},
 };
 use kompact::prelude::ComponentDefinition;
 
@@ -23,6 +23,35 @@ pub trait Source: Send + Sized + 'static {
     ///
     /// Safety: This method must be non-blocking
     fn process_batch(&self, ctx: SourceContext<Self, impl ComponentDefinition>);
+
+    /// Returns the current position of the Source in its input
+    ///
+    /// The position is checkpointed with every epoch so that a restored pipeline
+    /// continues reading where the restored epoch ended. Sources that cannot
+    /// continue from a position return `None`.
+    fn position(&self) -> Option<u64> {
+        None
+    }
+
+    /// Continues reading from a `position` returned by [Source::position]
+    fn seek(&mut self, _position: u64) {}
+}
+
+/// Defines a Source whose input is divided into splits that may be read in parallel
+///
+/// The splits are assigned to a number of [Source] instances, where
+/// each split is read by exactly one instance.
+pub trait ParallelSource: Send + Sync + 'static {
+    /// A unit of the input, e.g., a range of a file or a partition of a topic
+    type Split: Send + 'static;
+    /// The Source that reads a set of splits
+    type Reader: Source;
+
+    /// Returns all splits of the input
+    fn splits(&self) -> Vec<Self::Split>;
+
+    /// Creates a Source that reads the given splits
+    fn reader(&self, splits: Vec<Self::Split>) -> Self::Reader;
 }
 
 pub struct NodeContext<S>
@@ -30,10 +59,43 @@ where
     S: Source,
 {
     pub(crate) channel_strategy: ChannelStrategy<S::Data>,
+    pub(crate) watermark_strategy: WatermarkStrategy,
+    /// Latest watermark sent by the Source
     pub(crate) watermark: u64,
+    /// Highest timestamp output by the Source
+    pub(crate) max_timestamp: u64,
+    /// Whether the Source has output elements since it was last checked for idleness
+    pub(crate) active: bool,
     pub(crate) ended: bool,
 }
 
+impl<S> NodeContext<S>
+where
+    S: Source,
+{
+    pub(crate) fn new(
+        channel_strategy: ChannelStrategy<S::Data>,
+        watermark_strategy: WatermarkStrategy,
+    ) -> Self {
+        NodeContext {
+            channel_strategy,
+            watermark_strategy,
+            watermark: 0,
+            max_timestamp: 0,
+            active: false,
+            ended: false,
+        }
+    }
+
+    /// Returns the event-time watermark of the Source according to its [WatermarkStrategy]
+    #[inline]
+    pub(crate) fn event_watermark(&self) -> u64 {
+        self.watermark_strategy
+            .watermark(self.max_timestamp)
+            .max(self.watermark)
+    }
+}
+
 /// All Source implementations have access to a Context object
 pub struct SourceContext<'a, 'c, S, CD>
 where
@@ -58,11 +120,20 @@ where
         }
     }
 
+    /// Output a record without a timestamp
+    ///
+    /// The record is timestamped with the system time if the Source uses
+    /// [WatermarkStrategy::IngestionTime].
     #[inline]
     pub fn output(&mut self, data: S::Data) {
-        self.send(ArconEvent::Element(ArconElement::new(data)));
+        if self.node_context.watermark_strategy == WatermarkStrategy::IngestionTime {
+            self.output_with_timestamp(data, crate::util::get_system_time());
+        } else {
+            self.send(ArconEvent::Element(ArconElement::new(data)));
+        }
     }
 
+    /// Output a record with an event-time timestamp
     #[inline]
     pub fn output_with_timestamp(&mut self, data: S::Data, timestamp: u64) {
         self.update_watermark(timestamp);
@@ -73,12 +144,13 @@ where
 
     #[inline(always)]
     fn send(&mut self, event: ArconEvent<S::Data>) {
+        self.node_context.active = true;
         self.node_context.channel_strategy.add(event, self.source);
     }
 
     #[inline(always)]
     fn update_watermark(&mut self, ts: u64) {
-        self.node_context.watermark = std::cmp::max(ts, self.node_context.watermark);
+        self.node_context.max_timestamp = std::cmp::max(ts, self.node_context.max_timestamp);
     }
 
     pub fn signal_end(&mut self) {
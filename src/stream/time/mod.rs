@@ -12,3 +12,55 @@ impl Default for ArconTime {
         ArconTime::Event
     }
 }
+
+/// Decides how a Source assigns timestamps and generates watermarks in [ArconTime::Event]
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum WatermarkStrategy {
+    /// Timestamps are extracted from the records and never decrease
+    ///
+    /// The watermark is the highest timestamp seen so far.
+    Monotonic,
+    /// Timestamps are extracted from the records and arrive out of order by at most the bound
+    ///
+    /// The watermark trails the highest timestamp seen so far by the bound.
+    BoundedOutOfOrderness(u64),
+    /// Records are timestamped with the system time at which the Source outputs them
+    ///
+    /// No timestamp extractor is used and the watermark follows the system time.
+    IngestionTime,
+}
+
+impl Default for WatermarkStrategy {
+    fn default() -> Self {
+        WatermarkStrategy::Monotonic
+    }
+}
+
+impl WatermarkStrategy {
+    /// Returns the watermark for the highest timestamp a Source has output
+    #[inline]
+    pub fn watermark(&self, max_timestamp: u64) -> u64 {
+        match self {
+            WatermarkStrategy::Monotonic => max_timestamp,
+            WatermarkStrategy::BoundedOutOfOrderness(bound) => max_timestamp.saturating_sub(*bound),
+            // Records that are output within the same millisecond are not late
+            WatermarkStrategy::IngestionTime => crate::util::get_system_time().saturating_sub(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermark_strategy_test() {
+        assert_eq!(WatermarkStrategy::Monotonic.watermark(10), 10);
+        assert_eq!(WatermarkStrategy::BoundedOutOfOrderness(3).watermark(10), 7);
+        assert_eq!(
+            WatermarkStrategy::BoundedOutOfOrderness(30).watermark(10),
+            0
+        );
+        assert!(WatermarkStrategy::IngestionTime.watermark(10) > 10);
+    }
+}